@@ -1,8 +1,14 @@
+use crate::{
+    data::ContentFilter,
+    db::{AuditEntry, BlockSeverity, DomainBlock, TokenScope},
+};
 use activitystreams::iri_string::types::IriString;
 use std::collections::{BTreeMap, BTreeSet};
 use time::OffsetDateTime;
+use uuid::Uuid;
 
 pub mod client;
+pub mod dashboard;
 pub mod routes;
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -10,6 +16,46 @@ pub(crate) struct Domains {
     domains: Vec<String>,
 }
 
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct BlockDomains {
+    pub(crate) domains: Vec<String>,
+    #[serde(default)]
+    pub(crate) severity: BlockSeverity,
+    #[serde(default)]
+    pub(crate) reason: Option<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct ImportBlocks {
+    pub(crate) blocks: Vec<DomainBlock>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct Actors {
+    pub(crate) actors: Vec<IriString>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct BlockedActors {
+    pub(crate) blocked_actors: Vec<IriString>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct PendingFollowStatus {
+    pub(crate) actor_id: IriString,
+    pub(crate) requested_at: OffsetDateTime,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct PendingFollows {
+    pub(crate) pending_follows: Vec<PendingFollowStatus>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct Filters {
+    pub(crate) filters: Vec<ContentFilter>,
+}
+
 #[derive(serde::Deserialize, serde::Serialize)]
 pub(crate) struct AllowedDomains {
     pub(crate) allowed_domains: Vec<String>,
@@ -18,6 +64,8 @@ pub(crate) struct AllowedDomains {
 #[derive(serde::Deserialize, serde::Serialize)]
 pub(crate) struct BlockedDomains {
     pub(crate) blocked_domains: Vec<String>,
+    #[serde(default)]
+    pub(crate) blocks: Vec<DomainBlock>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -30,3 +78,68 @@ pub(crate) struct LastSeen {
     pub(crate) last_seen: BTreeMap<OffsetDateTime, BTreeSet<String>>,
     pub(crate) never: Vec<String>,
 }
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct BreakerStatus {
+    pub(crate) domain: String,
+    pub(crate) failures: usize,
+    pub(crate) last_attempt: OffsetDateTime,
+    pub(crate) last_success: OffsetDateTime,
+    pub(crate) should_try: bool,
+    #[serde(default)]
+    pub(crate) paused_until: Option<OffsetDateTime>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct Breakers {
+    pub(crate) breakers: Vec<BreakerStatus>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct CreateToken {
+    pub(crate) name: String,
+    pub(crate) scopes: BTreeSet<TokenScope>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct CreatedToken {
+    pub(crate) id: String,
+    pub(crate) token: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct RotatedKey {
+    pub(crate) key_id: IriString,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct TokenIds {
+    pub(crate) ids: Vec<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct TokenStatus {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) scopes: BTreeSet<TokenScope>,
+    pub(crate) created_at: OffsetDateTime,
+    pub(crate) last_used: Option<OffsetDateTime>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct Tokens {
+    pub(crate) tokens: Vec<TokenStatus>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct AuditQuery {
+    pub(crate) before: Option<Uuid>,
+    pub(crate) limit: Option<usize>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct AuditLog {
+    pub(crate) entries: Vec<AuditEntry>,
+    /// Pass as `before` to fetch the next page, when there might be one
+    pub(crate) next: Option<Uuid>,
+}
@@ -1,12 +1,21 @@
 use crate::{
-    admin::{AllowedDomains, BlockedDomains, ConnectedActors, Domains, LastSeen},
+    admin::{
+        Actors, AllowedDomains, AuditLog, BlockDomains, BlockedActors, BlockedDomains, Breakers,
+        ConnectedActors, CreateToken, CreatedToken, Domains, Filters, ImportBlocks, LastSeen,
+        PendingFollows, RotatedKey, TokenIds, Tokens,
+    },
     collector::Snapshot,
     config::{AdminUrlKind, Config},
+    data::{blocklist, ContentFilter},
+    db::{BlockSeverity, TokenScope},
     error::{Error, ErrorKind},
     extractors::XApiToken,
 };
+use activitystreams::iri_string::types::IriString;
 use reqwest_middleware::ClientWithMiddleware;
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::BTreeSet;
+use uuid::Uuid;
 
 pub(crate) async fn allow(
     client: &ClientWithMiddleware,
@@ -28,8 +37,46 @@ pub(crate) async fn block(
     client: &ClientWithMiddleware,
     config: &Config,
     domains: Vec<String>,
+    severity: BlockSeverity,
+    reason: Option<String>,
 ) -> Result<(), Error> {
-    post_domains(client, config, domains, AdminUrlKind::Block).await
+    let body = BlockDomains {
+        domains,
+        severity,
+        reason,
+    };
+
+    post_json(client, config, &body, AdminUrlKind::Block).await
+}
+
+/// Export domain blocks in Mastodon's domain-blocks CSV format
+pub(crate) async fn export_blocks(
+    client: &ClientWithMiddleware,
+    config: &Config,
+) -> Result<String, Error> {
+    let blocked = blocked(client, config).await?;
+
+    blocklist::to_csv(&blocked.blocks)
+}
+
+/// Import domain blocks from Mastodon's domain-blocks CSV format, returning how many were imported
+pub(crate) async fn import_blocks(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    csv: &str,
+) -> Result<usize, Error> {
+    let blocks = blocklist::from_csv(csv)?;
+    let count = blocks.len();
+
+    post_json(
+        client,
+        config,
+        &ImportBlocks { blocks },
+        AdminUrlKind::ImportBlocks,
+    )
+    .await?;
+
+    Ok(count)
 }
 
 pub(crate) async fn unblock(
@@ -40,6 +87,92 @@ pub(crate) async fn unblock(
     post_domains(client, config, domains, AdminUrlKind::Unblock).await
 }
 
+pub(crate) async fn block_actors(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    actors: Vec<IriString>,
+) -> Result<(), Error> {
+    post_json(
+        client,
+        config,
+        &Actors { actors },
+        AdminUrlKind::BlockActors,
+    )
+    .await
+}
+
+pub(crate) async fn unblock_actors(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    actors: Vec<IriString>,
+) -> Result<(), Error> {
+    post_json(
+        client,
+        config,
+        &Actors { actors },
+        AdminUrlKind::UnblockActors,
+    )
+    .await
+}
+
+pub(crate) async fn pending_follows(
+    client: &ClientWithMiddleware,
+    config: &Config,
+) -> Result<PendingFollows, Error> {
+    get_results(client, config, AdminUrlKind::PendingFollows).await
+}
+
+pub(crate) async fn approve_follows(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    actors: Vec<IriString>,
+) -> Result<(), Error> {
+    post_json(
+        client,
+        config,
+        &Actors { actors },
+        AdminUrlKind::ApproveFollows,
+    )
+    .await
+}
+
+pub(crate) async fn reject_follows(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    actors: Vec<IriString>,
+) -> Result<(), Error> {
+    post_json(
+        client,
+        config,
+        &Actors { actors },
+        AdminUrlKind::RejectFollows,
+    )
+    .await
+}
+
+pub(crate) async fn filter(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    filters: Vec<ContentFilter>,
+) -> Result<(), Error> {
+    post_json(client, config, &Filters { filters }, AdminUrlKind::Filter).await
+}
+
+pub(crate) async fn unfilter(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    filters: Vec<ContentFilter>,
+) -> Result<(), Error> {
+    post_json(client, config, &Filters { filters }, AdminUrlKind::Unfilter).await
+}
+
+pub(crate) async fn filters(
+    client: &ClientWithMiddleware,
+    config: &Config,
+) -> Result<Filters, Error> {
+    get_results(client, config, AdminUrlKind::Filters).await
+}
+
 pub(crate) async fn allowed(
     client: &ClientWithMiddleware,
     config: &Config,
@@ -54,6 +187,13 @@ pub(crate) async fn blocked(
     get_results(client, config, AdminUrlKind::Blocked).await
 }
 
+pub(crate) async fn blocked_actors(
+    client: &ClientWithMiddleware,
+    config: &Config,
+) -> Result<BlockedActors, Error> {
+    get_results(client, config, AdminUrlKind::BlockedActors).await
+}
+
 pub(crate) async fn connected(
     client: &ClientWithMiddleware,
     config: &Config,
@@ -75,14 +215,103 @@ pub(crate) async fn last_seen(
     get_results(client, config, AdminUrlKind::LastSeen).await
 }
 
+pub(crate) async fn breakers(
+    client: &ClientWithMiddleware,
+    config: &Config,
+) -> Result<Breakers, Error> {
+    get_results(client, config, AdminUrlKind::Breakers).await
+}
+
+pub(crate) async fn reset_breakers(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    domains: Vec<String>,
+) -> Result<(), Error> {
+    post_domains(client, config, domains, AdminUrlKind::ResetBreakers).await
+}
+
+/// Have the relay sign with a new key, returning the new key's id
+pub(crate) async fn rotate_key(
+    client: &ClientWithMiddleware,
+    config: &Config,
+) -> Result<RotatedKey, Error> {
+    post_results(client, config, &(), AdminUrlKind::RotateKey).await
+}
+
+pub(crate) async fn create_token(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    name: String,
+    scopes: BTreeSet<TokenScope>,
+) -> Result<CreatedToken, Error> {
+    post_results(
+        client,
+        config,
+        &CreateToken { name, scopes },
+        AdminUrlKind::CreateToken,
+    )
+    .await
+}
+
+pub(crate) async fn revoke_tokens(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    ids: Vec<String>,
+) -> Result<(), Error> {
+    post_json(
+        client,
+        config,
+        &TokenIds { ids },
+        AdminUrlKind::RevokeTokens,
+    )
+    .await
+}
+
+pub(crate) async fn tokens(
+    client: &ClientWithMiddleware,
+    config: &Config,
+) -> Result<Tokens, Error> {
+    get_results(client, config, AdminUrlKind::Tokens).await
+}
+
+/// Fetch a page of the audit log, newest first
+pub(crate) async fn audit_log(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    before: Option<Uuid>,
+    limit: usize,
+) -> Result<AuditLog, Error> {
+    let mut query = format!("limit={limit}");
+
+    if let Some(before) = before {
+        query += &format!("&before={before}");
+    }
+
+    get_results_with_query(client, config, AdminUrlKind::AuditLog, &query).await
+}
+
 async fn get_results<T: DeserializeOwned>(
     client: &ClientWithMiddleware,
     config: &Config,
     url_kind: AdminUrlKind,
+) -> Result<T, Error> {
+    get_results_with_query(client, config, url_kind, "").await
+}
+
+async fn get_results_with_query<T: DeserializeOwned>(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    url_kind: AdminUrlKind,
+    query: &str,
 ) -> Result<T, Error> {
     let x_api_token = config.x_api_token().ok_or(ErrorKind::MissingApiToken)?;
 
-    let iri = config.generate_admin_url(url_kind);
+    let mut iri = config.generate_admin_url(url_kind).to_string();
+
+    if !query.is_empty() {
+        iri += "?";
+        iri += query;
+    }
 
     let res = client
         .get(iri.as_str())
@@ -107,11 +336,54 @@ async fn get_results<T: DeserializeOwned>(
     Ok(t)
 }
 
+async fn post_results<T: Serialize, U: DeserializeOwned>(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    body: &T,
+    url_kind: AdminUrlKind,
+) -> Result<U, Error> {
+    let x_api_token = config.x_api_token().ok_or(ErrorKind::MissingApiToken)?;
+
+    let iri = config.generate_admin_url(url_kind);
+
+    let res = client
+        .post(iri.as_str())
+        .header(XApiToken::http1_name(), x_api_token.to_string())
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| ErrorKind::SendRequest(iri.to_string(), e.to_string()))?;
+
+    if !res.status().is_success() {
+        return Err(ErrorKind::Status(
+            iri.to_string(),
+            crate::http1::status_to_http02(res.status()),
+        )
+        .into());
+    }
+
+    let u = res
+        .json()
+        .await
+        .map_err(|e| ErrorKind::ReceiveResponse(iri.to_string(), e.to_string()))?;
+
+    Ok(u)
+}
+
 async fn post_domains(
     client: &ClientWithMiddleware,
     config: &Config,
     domains: Vec<String>,
     url_kind: AdminUrlKind,
+) -> Result<(), Error> {
+    post_json(client, config, &Domains { domains }, url_kind).await
+}
+
+async fn post_json<T: Serialize>(
+    client: &ClientWithMiddleware,
+    config: &Config,
+    body: &T,
+    url_kind: AdminUrlKind,
 ) -> Result<(), Error> {
     let x_api_token = config.x_api_token().ok_or(ErrorKind::MissingApiToken)?;
 
@@ -120,13 +392,13 @@ async fn post_domains(
     let res = client
         .post(iri.as_str())
         .header(XApiToken::http1_name(), x_api_token.to_string())
-        .json(&Domains { domains })
+        .json(body)
         .send()
         .await
         .map_err(|e| ErrorKind::SendRequest(iri.to_string(), e.to_string()))?;
 
     if !res.status().is_success() {
-        tracing::warn!("Failed to allow domains");
+        tracing::warn!("Failed to update {iri}");
     }
 
     Ok(())
@@ -0,0 +1,270 @@
+use crate::{
+    collector::MemoryCollector,
+    config::{Config, UrlKind},
+    data::State,
+    db::{AuditAction, BlockSeverity, DomainBlock, TokenScope},
+    error::{Error, ErrorKind},
+    extractors::{Admin, ADMIN_COOKIE, SESSION_TTL},
+};
+use actix_web::{
+    cookie::{Cookie, SameSite},
+    http::header::LOCATION,
+    web::{Data, Form},
+    HttpRequest, HttpResponse,
+};
+use std::io::BufWriter;
+
+#[derive(serde::Deserialize)]
+pub(crate) struct Login {
+    token: String,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct DomainForm {
+    domain: String,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct BlockForm {
+    domain: String,
+    #[serde(default)]
+    severity: BlockSeverity,
+    #[serde(default)]
+    reason: String,
+}
+
+fn render(
+    f: impl FnOnce(&mut BufWriter<Vec<u8>>) -> std::io::Result<()>,
+) -> Result<Vec<u8>, Error> {
+    let mut buf = BufWriter::new(Vec::new());
+
+    f(&mut buf)?;
+    let html = buf.into_inner().map_err(|e| {
+        tracing::error!("Error rendering template, {}", e.error());
+        ErrorKind::FlushBuffer
+    })?;
+
+    Ok(html)
+}
+
+fn redirect(location: &'static str) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .insert_header((LOCATION, location))
+        .finish()
+}
+
+fn cookie(config: &Config, value: String) -> Cookie<'static> {
+    let secure = config.generate_url(UrlKind::Index).scheme_str() == "https";
+
+    Cookie::build(ADMIN_COOKIE, value)
+        .path("/admin")
+        .http_only(true)
+        .secure(secure)
+        .same_site(SameSite::Strict)
+        .max_age(SESSION_TTL.try_into().unwrap_or_default())
+        .finish()
+}
+
+fn parse_domain(input: &str) -> Option<String> {
+    let input = input.trim();
+
+    url::Url::parse(input)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+        .or_else(|| url::Host::parse(input).ok().map(|host| host.to_string()))
+}
+
+pub(crate) async fn login_page(config: Data<Config>) -> Result<HttpResponse, Error> {
+    let html = render(|buf| crate::templates::login_html(buf, &config, false))?;
+
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+}
+
+pub(crate) async fn login(
+    req: HttpRequest,
+    config: Data<Config>,
+    Form(Login { token }): Form<Login>,
+) -> Result<HttpResponse, Error> {
+    let session = match Admin::login(&req, token).await {
+        Ok(session) => session,
+        Err(e) => {
+            tracing::info!("Rejected admin dashboard login: {e}");
+
+            let html = render(|buf| crate::templates::login_html(buf, &config, true))?;
+
+            return Ok(HttpResponse::Unauthorized()
+                .content_type("text/html")
+                .body(html));
+        }
+    };
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin"))
+        .cookie(cookie(&config, session))
+        .finish())
+}
+
+pub(crate) async fn logout(req: HttpRequest, config: Data<Config>) -> Result<HttpResponse, Error> {
+    Admin::logout(&req).await?;
+
+    let mut removal = cookie(&config, String::new());
+    removal.make_removal();
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/login"))
+        .cookie(removal)
+        .finish())
+}
+
+#[tracing::instrument(name = "Admin dashboard", skip_all)]
+pub(crate) async fn dashboard(
+    admin: Result<Admin, Error>,
+    state: Data<State>,
+    config: Data<Config>,
+    collector: Data<MemoryCollector>,
+) -> Result<HttpResponse, Error> {
+    let Ok(admin) = admin else {
+        return Ok(redirect("/admin/login"));
+    };
+
+    let db = admin.db_ref();
+
+    let blocks = if admin.has_scope(TokenScope::Blocks) {
+        Some(db.domain_blocks().await?)
+    } else {
+        None
+    };
+
+    let allowed = if admin.has_scope(TokenScope::Allows) {
+        Some(db.allows().await?)
+    } else {
+        None
+    };
+
+    let (nodes, stats) = if admin.has_scope(TokenScope::Stats) {
+        let last_seen = db.last_seen().await?;
+
+        let mut nodes = state
+            .node_cache
+            .nodes()
+            .await?
+            .into_iter()
+            .map(|node| {
+                let seen = node
+                    .base
+                    .authority_str()
+                    .and_then(|authority| last_seen.get(authority).copied().flatten());
+
+                (node, seen)
+            })
+            .collect::<Vec<_>>();
+
+        nodes.sort_by(|(lhs, _), (rhs, _)| lhs.base.as_str().cmp(rhs.base.as_str()));
+
+        (Some(nodes), Some(collector.snapshot().report()))
+    } else {
+        (None, None)
+    };
+
+    let html = render(|buf| {
+        crate::templates::dashboard_html(
+            buf,
+            &config,
+            blocks.as_deref(),
+            allowed.as_deref(),
+            nodes.as_deref(),
+            stats.as_deref(),
+        )
+    })?;
+
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+}
+
+pub(crate) async fn block(
+    admin: Admin,
+    Form(BlockForm {
+        domain,
+        severity,
+        reason,
+    }): Form<BlockForm>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Blocks)?;
+
+    if let Some(domain) = parse_domain(&domain) {
+        let reason = Some(reason.trim().to_owned()).filter(|reason| !reason.is_empty());
+
+        let block = DomainBlock::new(
+            domain.clone(),
+            severity,
+            reason.clone(),
+            Some(admin.name().to_owned()),
+        );
+
+        admin
+            .audited(
+                AuditAction::Block,
+                vec![domain],
+                reason,
+                admin.db_ref().add_blocks(vec![block]),
+            )
+            .await?;
+    }
+
+    Ok(redirect("/admin"))
+}
+
+pub(crate) async fn unblock(
+    admin: Admin,
+    Form(DomainForm { domain }): Form<DomainForm>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Blocks)?;
+
+    admin
+        .audited(
+            AuditAction::Unblock,
+            vec![domain.clone()],
+            None,
+            admin.db_ref().remove_blocks(vec![domain]),
+        )
+        .await?;
+
+    Ok(redirect("/admin"))
+}
+
+pub(crate) async fn allow(
+    admin: Admin,
+    Form(DomainForm { domain }): Form<DomainForm>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Allows)?;
+
+    if let Some(domain) = parse_domain(&domain) {
+        admin
+            .audited(
+                AuditAction::Allow,
+                vec![domain.clone()],
+                None,
+                admin.db_ref().add_allows(vec![domain]),
+            )
+            .await?;
+    }
+
+    Ok(redirect("/admin"))
+}
+
+pub(crate) async fn disallow(
+    admin: Admin,
+    Form(DomainForm { domain }): Form<DomainForm>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Allows)?;
+
+    admin
+        .audited(
+            AuditAction::Disallow,
+            vec![domain.clone()],
+            None,
+            admin.db_ref().remove_allows(vec![domain]),
+        )
+        .await?;
+
+    Ok(redirect("/admin"))
+}
@@ -1,21 +1,46 @@
 use crate::{
-    admin::{AllowedDomains, BlockedDomains, ConnectedActors, Domains, LastSeen},
+    admin::{
+        Actors, AllowedDomains, AuditLog, AuditQuery, BlockDomains, BlockedActors, BlockedDomains,
+        BreakerStatus, Breakers, ConnectedActors, CreateToken, CreatedToken, Domains, Filters,
+        ImportBlocks, LastSeen, PendingFollowStatus, PendingFollows, RotatedKey, TokenIds,
+        TokenStatus, Tokens,
+    },
     collector::{MemoryCollector, Snapshot},
+    config::{Config, UrlKind},
+    data::{ContentFilter, State},
+    db::{AuditAction, DomainBlock, TokenScope},
     error::Error,
     extractors::Admin,
+    jobs::{apub, JobServer},
+    requests::Requests,
+    spawner::Spawner,
 };
+use activitystreams::iri_string::types::IriString;
 use actix_web::{
-    web::{Data, Json},
+    web::{Data, Json, Query},
     HttpResponse,
 };
 use std::collections::{BTreeMap, BTreeSet};
 use time::OffsetDateTime;
+use uuid::Uuid;
+
+const AUDIT_LOG_PAGE_SIZE: usize = 50;
+const AUDIT_LOG_MAX_PAGE_SIZE: usize = 500;
 
 pub(crate) async fn allow(
     admin: Admin,
     Json(Domains { domains }): Json<Domains>,
 ) -> Result<HttpResponse, Error> {
-    admin.db_ref().add_allows(domains).await?;
+    admin.require(TokenScope::Allows)?;
+
+    admin
+        .audited(
+            AuditAction::Allow,
+            domains.clone(),
+            None,
+            admin.db_ref().add_allows(domains),
+        )
+        .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -24,16 +49,79 @@ pub(crate) async fn disallow(
     admin: Admin,
     Json(Domains { domains }): Json<Domains>,
 ) -> Result<HttpResponse, Error> {
-    admin.db_ref().remove_allows(domains).await?;
+    admin.require(TokenScope::Allows)?;
+
+    admin
+        .audited(
+            AuditAction::Disallow,
+            domains.clone(),
+            None,
+            admin.db_ref().remove_allows(domains),
+        )
+        .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
 
 pub(crate) async fn block(
     admin: Admin,
-    Json(Domains { domains }): Json<Domains>,
+    Json(BlockDomains {
+        domains,
+        severity,
+        reason,
+    }): Json<BlockDomains>,
 ) -> Result<HttpResponse, Error> {
-    admin.db_ref().add_blocks(domains).await?;
+    admin.require(TokenScope::Blocks)?;
+
+    let blocks = domains
+        .iter()
+        .map(|domain| {
+            DomainBlock::new(
+                domain.clone(),
+                severity,
+                reason.clone(),
+                Some(admin.name().to_owned()),
+            )
+        })
+        .collect();
+
+    admin
+        .audited(
+            AuditAction::Block,
+            domains,
+            reason,
+            admin.db_ref().add_blocks(blocks),
+        )
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub(crate) async fn import_blocks(
+    admin: Admin,
+    Json(ImportBlocks { blocks }): Json<ImportBlocks>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Blocks)?;
+
+    let blocks = blocks
+        .into_iter()
+        .map(|block| DomainBlock {
+            created_by: block.created_by.or_else(|| Some(String::from("import"))),
+            created_at: block.created_at.or_else(|| Some(OffsetDateTime::now_utc())),
+            source: None,
+            ..block
+        })
+        .collect::<Vec<_>>();
+    let domains = blocks.iter().map(|block| block.domain.clone()).collect();
+
+    admin
+        .audited(
+            AuditAction::ImportBlocks,
+            domains,
+            None,
+            admin.db_ref().add_blocks(blocks),
+        )
+        .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -42,37 +130,237 @@ pub(crate) async fn unblock(
     admin: Admin,
     Json(Domains { domains }): Json<Domains>,
 ) -> Result<HttpResponse, Error> {
-    admin.db_ref().remove_blocks(domains).await?;
+    admin.require(TokenScope::Blocks)?;
+
+    admin
+        .audited(
+            AuditAction::Unblock,
+            domains.clone(),
+            None,
+            admin.db_ref().remove_blocks(domains),
+        )
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub(crate) async fn block_actors(
+    admin: Admin,
+    Json(Actors { actors }): Json<Actors>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Blocks)?;
+
+    let targets = actors.iter().map(ToString::to_string).collect();
+
+    admin
+        .audited(
+            AuditAction::BlockActor,
+            targets,
+            None,
+            admin.db_ref().add_actor_blocks(actors),
+        )
+        .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
 
+pub(crate) async fn unblock_actors(
+    admin: Admin,
+    Json(Actors { actors }): Json<Actors>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Blocks)?;
+
+    let targets = actors.iter().map(ToString::to_string).collect();
+
+    admin
+        .audited(
+            AuditAction::UnblockActor,
+            targets,
+            None,
+            admin.db_ref().remove_actor_blocks(actors),
+        )
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub(crate) async fn pending_follows(admin: Admin) -> Result<Json<PendingFollows>, Error> {
+    admin.require(TokenScope::Allows)?;
+
+    let pending_follows = admin
+        .db_ref()
+        .pending_follows()
+        .await?
+        .into_iter()
+        .map(|pending| PendingFollowStatus {
+            actor_id: pending.actor.id,
+            requested_at: pending.requested_at,
+        })
+        .collect();
+
+    Ok(Json(PendingFollows { pending_follows }))
+}
+
+pub(crate) async fn approve_follows(
+    admin: Admin,
+    jobs: Data<JobServer>,
+    Json(Actors { actors }): Json<Actors>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Allows)?;
+
+    let actors = pending_actors(&admin, actors).await?;
+    let targets = actors.iter().map(ToString::to_string).collect();
+
+    admin
+        .audited(AuditAction::ApproveFollow, targets, None, async {
+            for actor_id in actors {
+                apub::approve_follow(admin.db_ref(), &jobs, actor_id).await?;
+            }
+
+            Ok(())
+        })
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub(crate) async fn reject_follows(
+    admin: Admin,
+    jobs: Data<JobServer>,
+    Json(Actors { actors }): Json<Actors>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Allows)?;
+
+    let actors = pending_actors(&admin, actors).await?;
+    let targets = actors.iter().map(ToString::to_string).collect();
+
+    admin
+        .audited(AuditAction::RejectFollow, targets, None, async {
+            for actor_id in actors {
+                apub::reject_follow(admin.db_ref(), &jobs, actor_id).await?;
+            }
+
+            Ok(())
+        })
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Narrow `actors` down to those with a pending follow request
+async fn pending_actors(admin: &Admin, actors: Vec<IriString>) -> Result<Vec<IriString>, Error> {
+    let pending = admin.db_ref().pending_follows().await?;
+
+    Ok(actors
+        .into_iter()
+        .filter(|actor_id| pending.iter().any(|pending| pending.actor.id == *actor_id))
+        .collect())
+}
+
+pub(crate) async fn filter(
+    admin: Admin,
+    state: Data<State>,
+    Json(Filters { filters }): Json<Filters>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Blocks)?;
+
+    let filters = filters
+        .into_iter()
+        .map(ContentFilter::normalize)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let targets = filters.iter().map(ToString::to_string).collect();
+
+    admin
+        .audited(AuditAction::Filter, targets, None, async {
+            admin.db_ref().add_content_filters(filters).await?;
+            state.reload_content_filters().await
+        })
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub(crate) async fn unfilter(
+    admin: Admin,
+    state: Data<State>,
+    Json(Filters { filters }): Json<Filters>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Blocks)?;
+
+    let filters = filters
+        .into_iter()
+        .map(ContentFilter::normalize)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let targets = filters.iter().map(ToString::to_string).collect();
+
+    admin
+        .audited(AuditAction::Unfilter, targets, None, async {
+            admin.db_ref().remove_content_filters(filters).await?;
+            state.reload_content_filters().await
+        })
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub(crate) async fn filters(admin: Admin) -> Result<Json<Filters>, Error> {
+    admin.require(TokenScope::Blocks)?;
+
+    let filters = admin.db_ref().content_filters().await?;
+
+    Ok(Json(Filters { filters }))
+}
+
 pub(crate) async fn allowed(admin: Admin) -> Result<Json<AllowedDomains>, Error> {
+    admin.require(TokenScope::Allows)?;
+
     let allowed_domains = admin.db_ref().allows().await?;
 
     Ok(Json(AllowedDomains { allowed_domains }))
 }
 
 pub(crate) async fn blocked(admin: Admin) -> Result<Json<BlockedDomains>, Error> {
-    let blocked_domains = admin.db_ref().blocks().await?;
+    admin.require(TokenScope::Blocks)?;
+
+    let blocks = admin.db_ref().domain_blocks().await?;
+    let blocked_domains = blocks.iter().map(|block| block.domain.clone()).collect();
+
+    Ok(Json(BlockedDomains {
+        blocked_domains,
+        blocks,
+    }))
+}
+
+pub(crate) async fn blocked_actors(admin: Admin) -> Result<Json<BlockedActors>, Error> {
+    admin.require(TokenScope::Blocks)?;
+
+    let blocked_actors = admin.db_ref().blocked_actors().await?;
 
-    Ok(Json(BlockedDomains { blocked_domains }))
+    Ok(Json(BlockedActors { blocked_actors }))
 }
 
 pub(crate) async fn connected(admin: Admin) -> Result<Json<ConnectedActors>, Error> {
+    admin.require(TokenScope::Stats)?;
+
     let connected_actors = admin.db_ref().connected_ids().await?;
 
     Ok(Json(ConnectedActors { connected_actors }))
 }
 
 pub(crate) async fn stats(
-    _admin: Admin,
+    admin: Admin,
     collector: Data<MemoryCollector>,
 ) -> Result<Json<Snapshot>, Error> {
+    admin.require(TokenScope::Stats)?;
+
     Ok(Json(collector.snapshot()))
 }
 
 pub(crate) async fn last_seen(admin: Admin) -> Result<Json<LastSeen>, Error> {
+    admin.require(TokenScope::Stats)?;
+
     let nodes = admin.db_ref().last_seen().await?;
 
     let mut last_seen: BTreeMap<OffsetDateTime, BTreeSet<String>> = BTreeMap::new();
@@ -88,3 +376,155 @@ pub(crate) async fn last_seen(admin: Admin) -> Result<Json<LastSeen>, Error> {
 
     Ok(Json(LastSeen { last_seen, never }))
 }
+
+pub(crate) async fn breakers(
+    admin: Admin,
+    requests: Data<Requests>,
+) -> Result<Json<Breakers>, Error> {
+    admin.require(TokenScope::Stats)?;
+
+    let mut breakers = requests
+        .breakers()
+        .into_iter()
+        .map(|(domain, breaker)| BreakerStatus {
+            domain,
+            failures: breaker.failures(),
+            last_attempt: breaker.last_attempt().into(),
+            last_success: breaker.last_success().into(),
+            should_try: breaker.should_try(),
+            paused_until: breaker.paused_until().map(Into::into),
+        })
+        .collect::<Vec<_>>();
+
+    breakers.sort_by(|lhs, rhs| lhs.domain.cmp(&rhs.domain));
+
+    Ok(Json(Breakers { breakers }))
+}
+
+pub(crate) async fn reset_breakers(
+    admin: Admin,
+    requests: Data<Requests>,
+    Json(Domains { domains }): Json<Domains>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Admin)?;
+
+    admin
+        .audited(AuditAction::ResetBreakers, domains.clone(), None, async {
+            for domain in &domains {
+                requests.reset_breaker_for_domain(domain);
+            }
+
+            Ok(())
+        })
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub(crate) async fn rotate_key(
+    admin: Admin,
+    state: Data<State>,
+    config: Data<Config>,
+    jobs: Data<JobServer>,
+) -> Result<Json<RotatedKey>, Error> {
+    admin.require(TokenScope::Admin)?;
+
+    let key_id = config.generate_url(UrlKind::Key(Uuid::new_v4()));
+
+    admin
+        .audited(
+            AuditAction::RotateKey,
+            vec![key_id.to_string()],
+            None,
+            async {
+                state
+                    .rotate_key(key_id.clone(), config.key_rotation_grace())
+                    .await?;
+                jobs.queue(apub::UpdateActor).await
+            },
+        )
+        .await?;
+
+    Ok(Json(RotatedKey { key_id }))
+}
+
+pub(crate) async fn create_token(
+    admin: Admin,
+    spawner: Data<Spawner>,
+    Json(CreateToken { name, scopes }): Json<CreateToken>,
+) -> Result<Json<CreatedToken>, Error> {
+    admin.require(TokenScope::Admin)?;
+
+    let (admin_token, token) = Admin::generate_token(&spawner, name.clone(), scopes).await?;
+    let id = admin_token.id.clone();
+
+    admin
+        .audited(
+            AuditAction::CreateToken,
+            vec![format!("{id} ({name})")],
+            None,
+            admin.db_ref().add_admin_token(admin_token),
+        )
+        .await?;
+
+    Ok(Json(CreatedToken { id, token }))
+}
+
+pub(crate) async fn revoke_tokens(
+    admin: Admin,
+    Json(TokenIds { ids }): Json<TokenIds>,
+) -> Result<HttpResponse, Error> {
+    admin.require(TokenScope::Admin)?;
+
+    admin
+        .audited(
+            AuditAction::RevokeTokens,
+            ids.clone(),
+            None,
+            admin.db_ref().revoke_admin_tokens(ids),
+        )
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub(crate) async fn tokens(admin: Admin) -> Result<Json<Tokens>, Error> {
+    admin.require(TokenScope::Admin)?;
+
+    let tokens = admin
+        .db_ref()
+        .admin_tokens()
+        .await?
+        .into_iter()
+        .map(|token| TokenStatus {
+            id: token.id,
+            name: token.name,
+            scopes: token.scopes,
+            created_at: token.created_at,
+            last_used: token.last_used,
+        })
+        .collect();
+
+    Ok(Json(Tokens { tokens }))
+}
+
+pub(crate) async fn audit_log(
+    admin: Admin,
+    Query(AuditQuery { before, limit }): Query<AuditQuery>,
+) -> Result<Json<AuditLog>, Error> {
+    admin.require(TokenScope::Admin)?;
+
+    let limit = limit
+        .unwrap_or(AUDIT_LOG_PAGE_SIZE)
+        .clamp(1, AUDIT_LOG_MAX_PAGE_SIZE);
+
+    let entries = admin.db_ref().audit_log(before, limit).await?;
+
+    let next = if entries.len() == limit {
+        entries.last().map(|entry| entry.id)
+    } else {
+        None
+    };
+
+    Ok(Json(AuditLog { entries, next }))
+}
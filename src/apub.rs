@@ -1,10 +1,20 @@
+use crate::{
+    config::{Config, UrlKind},
+    db::RetiredKey,
+    error::Error,
+    requests::Requests,
+};
 use activitystreams::{
     activity::ActorAndObject,
-    actor::{Actor, ApActor},
+    actor::{Actor, ApActor, Application, Endpoints},
+    context,
     iri_string::types::IriString,
+    prelude::*,
+    security,
     unparsed::UnparsedMutExt,
 };
 use activitystreams_ext::{Ext1, UnparsedExtension};
+use rsa::pkcs8::EncodePublicKey;
 
 #[derive(Clone, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -24,10 +34,84 @@ impl std::fmt::Debug for PublicKeyInner {
     }
 }
 
+/// An actor's `publicKey`, which may list several keys
+///
+/// Only the first key is used to verify an actor's signatures. The relay lists the keys it retired
+/// within the rotation grace period after its current one, so their ids still resolve.
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
+#[serde(try_from = "PublicKeyRepr", into = "PublicKeyRepr")]
 pub struct PublicKey {
     pub public_key: PublicKeyInner,
+    pub retired_keys: Vec<PublicKeyInner>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PublicKeyRepr {
+    public_key: PublicKeys,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum PublicKeys {
+    One(PublicKeyInner),
+    Many(Vec<PublicKeyInner>),
+}
+
+impl TryFrom<PublicKeys> for PublicKey {
+    type Error = &'static str;
+
+    fn try_from(keys: PublicKeys) -> Result<Self, Self::Error> {
+        match keys {
+            PublicKeys::One(public_key) => Ok(PublicKey {
+                public_key,
+                retired_keys: Vec::new(),
+            }),
+            PublicKeys::Many(mut keys) => {
+                if keys.is_empty() {
+                    return Err("publicKey is empty");
+                }
+
+                let public_key = keys.remove(0);
+
+                Ok(PublicKey {
+                    public_key,
+                    retired_keys: keys,
+                })
+            }
+        }
+    }
+}
+
+impl From<PublicKey> for PublicKeys {
+    fn from(key: PublicKey) -> Self {
+        if key.retired_keys.is_empty() {
+            PublicKeys::One(key.public_key)
+        } else {
+            PublicKeys::Many(
+                std::iter::once(key.public_key)
+                    .chain(key.retired_keys)
+                    .collect(),
+            )
+        }
+    }
+}
+
+impl TryFrom<PublicKeyRepr> for PublicKey {
+    type Error = &'static str;
+
+    fn try_from(repr: PublicKeyRepr) -> Result<Self, Self::Error> {
+        repr.public_key.try_into()
+    }
+}
+
+impl From<PublicKey> for PublicKeyRepr {
+    fn from(key: PublicKey) -> Self {
+        PublicKeyRepr {
+            public_key: key.into(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize)]
@@ -56,6 +140,53 @@ pub enum UndoTypes {
 pub type AcceptedUndoObjects = ActorAndObject<UndoTypes>;
 pub type AcceptedActivities = ActorAndObject<ValidTypes>;
 pub type AcceptedActors = Ext1<ApActor<Actor<String>>, PublicKey>;
+pub type RelayActor = Ext1<ApActor<Application>, PublicKey>;
+
+/// The relay's own actor, advertising the key it currently signs with, followed by any keys
+/// still within their rotation grace period
+pub(crate) fn relay_actor(
+    config: &Config,
+    requests: &Requests,
+    retired: &[RetiredKey],
+) -> Result<RelayActor, Error> {
+    let (key_id, public_key) = requests.public_key();
+
+    let mut application = Ext1::new(
+        ApActor::new(config.generate_url(UrlKind::Inbox), Application::new()),
+        PublicKey {
+            public_key: PublicKeyInner {
+                id: key_id.parse()?,
+                owner: config.generate_url(UrlKind::Actor),
+                public_key_pem: public_key.to_public_key_pem(rsa::pkcs8::LineEnding::default())?,
+            },
+            retired_keys: retired
+                .iter()
+                .map(|retired| PublicKeyInner {
+                    id: retired.key_id.clone(),
+                    owner: config.generate_url(UrlKind::Actor),
+                    public_key_pem: retired.public_key_pem.clone(),
+                })
+                .collect(),
+        },
+    );
+
+    application
+        .set_id(config.generate_url(UrlKind::Actor))
+        .set_summary("AodeRelay bot")
+        .set_name("Fediverse Relay")
+        .set_url(config.generate_url(UrlKind::Actor))
+        .set_many_contexts(vec![context(), security()])
+        .set_preferred_username("relay")
+        .set_outbox(config.generate_url(UrlKind::Outbox))
+        .set_followers(config.generate_url(UrlKind::Followers))
+        .set_following(config.generate_url(UrlKind::Following))
+        .set_endpoints(Endpoints {
+            shared_inbox: Some(config.generate_url(UrlKind::Inbox)),
+            ..Default::default()
+        });
+
+    Ok(application)
+}
 
 impl<U> UnparsedExtension<U> for PublicKey
 where
@@ -64,13 +195,62 @@ where
     type Error = serde_json::Error;
 
     fn try_from_unparsed(unparsed_mut: &mut U) -> Result<Self, Self::Error> {
-        Ok(PublicKey {
-            public_key: unparsed_mut.remove("publicKey")?,
-        })
+        let keys: PublicKeys = unparsed_mut.remove("publicKey")?;
+
+        keys.try_into().map_err(serde::de::Error::custom)
     }
 
     fn try_into_unparsed(self, unparsed_mut: &mut U) -> Result<(), Self::Error> {
-        unparsed_mut.insert("publicKey", self.public_key)?;
+        unparsed_mut.insert("publicKey", PublicKeys::from(self))?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AcceptedActors, PublicKey, PublicKeyInner};
+    use activitystreams::{
+        actor::{ApActor, Application},
+        prelude::*,
+    };
+    use activitystreams_ext::Ext1;
+
+    #[test]
+    fn retired_keys_resolve_from_the_actor() {
+        let key = |id: &str, pem: &str| PublicKeyInner {
+            id: id.parse().unwrap(),
+            owner: "https://relay.example/actor".parse().unwrap(),
+            public_key_pem: pem.to_owned(),
+        };
+
+        let mut actor = Ext1::new(
+            ApActor::new(
+                "https://relay.example/inbox".parse().unwrap(),
+                Application::new(),
+            ),
+            PublicKey {
+                public_key: key("https://relay.example/actor/keys/one", "new"),
+                retired_keys: vec![key("https://relay.example/actor#main-key", "old")],
+            },
+        );
+        actor.set_id("https://relay.example/actor".parse().unwrap());
+
+        // The old key id has no document of its own, so fetching it means fetching the actor
+        let document = serde_json::to_value(&actor).unwrap();
+        let old_key = document["publicKey"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|key| key["id"] == "https://relay.example/actor#main-key")
+            .unwrap();
+        assert_eq!(old_key["publicKeyPem"], "old");
+        assert_eq!(old_key["owner"], "https://relay.example/actor");
+
+        let parsed: AcceptedActors = serde_json::from_value(document).unwrap();
+        assert_eq!(
+            parsed.ext_one.public_key.id.as_str(),
+            "https://relay.example/actor/keys/one"
+        );
+        assert_eq!(parsed.ext_one.retired_keys.len(), 1);
+    }
+}
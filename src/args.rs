@@ -1,4 +1,11 @@
+use crate::{
+    data::ContentFilter,
+    db::{BlockSeverity, TokenScope},
+};
+use activitystreams::iri_string::types::IriString;
 use clap::Parser;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
 
 #[derive(Debug, Parser)]
 #[structopt(name = "relay", about = "An activitypub relay")]
@@ -6,10 +13,75 @@ pub(crate) struct Args {
     #[arg(short, help = "A list of domains that should be blocked")]
     blocks: Vec<String>,
 
+    #[arg(
+        long,
+        default_value = "suspend",
+        help = "How strictly to block domains, either suspend or silence"
+    )]
+    severity: BlockSeverity,
+
+    #[arg(long, help = "Why the domains are being blocked")]
+    reason: Option<String>,
+
+    #[arg(
+        long,
+        help = "Import domain blocks from a Mastodon domain-blocks CSV file"
+    )]
+    import_blocks: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Export domain blocks to a Mastodon domain-blocks CSV file"
+    )]
+    export_blocks: Option<PathBuf>,
+
     #[arg(short, help = "A list of domains that should be allowed")]
     allowed: Vec<String>,
 
-    #[arg(short, long, help = "Undo allowing or blocking domains")]
+    #[arg(long, help = "List follow requests waiting for approval")]
+    pending: bool,
+
+    #[arg(
+        long,
+        help = "A list of actor IRIs whose follow requests should be approved"
+    )]
+    approve: Vec<IriString>,
+
+    #[arg(
+        long,
+        help = "A list of actor IRIs whose follow requests should be rejected"
+    )]
+    reject: Vec<IriString>,
+
+    #[arg(long, help = "A list of actor IRIs that should be blocked")]
+    block_actor: Vec<IriString>,
+
+    #[arg(long, help = "A list of keywords whose posts should not be relayed")]
+    filter_keyword: Vec<String>,
+
+    #[arg(
+        long,
+        help = "A list of regexes whose matching posts should not be relayed"
+    )]
+    filter_regex: Vec<String>,
+
+    #[arg(long, help = "A list of hashtags whose posts should not be relayed")]
+    filter_hashtag: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Do not relay sensitive posts or posts with content warnings"
+    )]
+    filter_sensitive: bool,
+
+    #[arg(long, help = "List content filters")]
+    filters: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Undo allowing or blocking domains or actors, or adding filters"
+    )]
     undo: bool,
 
     #[arg(short, long, help = "List allowed and blocked domains")]
@@ -24,15 +96,77 @@ pub(crate) struct Args {
         help = "List domains by when they were last succesfully contacted"
     )]
     contacted: bool,
+
+    #[arg(long, help = "List circuit breakers for remote domains")]
+    breakers: bool,
+
+    #[arg(
+        long,
+        help = "A list of domains whose circuit breakers should be reset"
+    )]
+    reset_breakers: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Sign with a newly generated key, and tell subscribers to fetch it"
+    )]
+    rotate_key: bool,
+
+    #[arg(long, help = "Create an admin API token with the given name")]
+    create_token: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "stats",
+        help = "Scopes for the created token: stats, allows, blocks or admin"
+    )]
+    scope: Vec<TokenScope>,
+
+    #[arg(long, help = "A list of admin API token ids that should be revoked")]
+    revoke_token: Vec<String>,
+
+    #[arg(long, help = "List admin API tokens")]
+    tokens: bool,
+
+    #[arg(long, help = "List recent moderation and admin actions")]
+    audit_log: bool,
+
+    #[arg(long, help = "Only list audit log entries older than this entry id")]
+    before: Option<Uuid>,
+
+    #[arg(
+        long,
+        default_value = "50",
+        help = "How many audit log entries to list"
+    )]
+    limit: usize,
 }
 
 impl Args {
     pub(crate) fn any(&self) -> bool {
         !self.blocks.is_empty()
             || !self.allowed.is_empty()
+            || self.import_blocks.is_some()
+            || self.export_blocks.is_some()
+            || self.pending
+            || !self.approve.is_empty()
+            || !self.reject.is_empty()
+            || !self.block_actor.is_empty()
+            || !self.filter_keyword.is_empty()
+            || !self.filter_regex.is_empty()
+            || !self.filter_hashtag.is_empty()
+            || self.filter_sensitive
+            || self.filters
             || self.list
             || self.stats
             || self.contacted
+            || self.breakers
+            || !self.reset_breakers.is_empty()
+            || self.rotate_key
+            || self.create_token.is_some()
+            || !self.revoke_token.is_empty()
+            || self.tokens
+            || self.audit_log
     }
 
     pub(crate) fn new() -> Self {
@@ -43,10 +177,67 @@ impl Args {
         &self.blocks
     }
 
+    pub(crate) fn severity(&self) -> BlockSeverity {
+        self.severity
+    }
+
+    pub(crate) fn reason(&self) -> Option<String> {
+        self.reason.clone()
+    }
+
+    pub(crate) fn import_blocks(&self) -> Option<&Path> {
+        self.import_blocks.as_deref()
+    }
+
+    pub(crate) fn export_blocks(&self) -> Option<&Path> {
+        self.export_blocks.as_deref()
+    }
+
     pub(crate) fn allowed(&self) -> &[String] {
         &self.allowed
     }
 
+    pub(crate) fn pending(&self) -> bool {
+        self.pending
+    }
+
+    pub(crate) fn approve(&self) -> &[IriString] {
+        &self.approve
+    }
+
+    pub(crate) fn reject(&self) -> &[IriString] {
+        &self.reject
+    }
+
+    pub(crate) fn block_actors(&self) -> &[IriString] {
+        &self.block_actor
+    }
+
+    pub(crate) fn content_filters(&self) -> Vec<ContentFilter> {
+        let keywords = self
+            .filter_keyword
+            .iter()
+            .cloned()
+            .map(ContentFilter::Keyword);
+        let regexes = self.filter_regex.iter().cloned().map(ContentFilter::Regex);
+        let hashtags = self
+            .filter_hashtag
+            .iter()
+            .cloned()
+            .map(ContentFilter::Hashtag);
+        let sensitive = self.filter_sensitive.then_some(ContentFilter::Sensitive);
+
+        keywords
+            .chain(regexes)
+            .chain(hashtags)
+            .chain(sensitive)
+            .collect()
+    }
+
+    pub(crate) fn filters(&self) -> bool {
+        self.filters
+    }
+
     pub(crate) fn undo(&self) -> bool {
         self.undo
     }
@@ -62,4 +253,44 @@ impl Args {
     pub(crate) fn contacted(&self) -> bool {
         self.contacted
     }
+
+    pub(crate) fn breakers(&self) -> bool {
+        self.breakers
+    }
+
+    pub(crate) fn reset_breakers(&self) -> &[String] {
+        &self.reset_breakers
+    }
+
+    pub(crate) fn rotate_key(&self) -> bool {
+        self.rotate_key
+    }
+
+    pub(crate) fn create_token(&self) -> Option<&str> {
+        self.create_token.as_deref()
+    }
+
+    pub(crate) fn scopes(&self) -> &[TokenScope] {
+        &self.scope
+    }
+
+    pub(crate) fn revoke_tokens(&self) -> &[String] {
+        &self.revoke_token
+    }
+
+    pub(crate) fn tokens(&self) -> bool {
+        self.tokens
+    }
+
+    pub(crate) fn audit_log(&self) -> bool {
+        self.audit_log
+    }
+
+    pub(crate) fn before(&self) -> Option<Uuid> {
+        self.before
+    }
+
+    pub(crate) fn limit(&self) -> usize {
+        self.limit
+    }
 }
@@ -51,6 +51,7 @@ fn main() -> color_eyre::Result<()> {
     let mut ructe = Ructe::from_env()?;
     let mut statics = ructe.statics()?;
     statics.add_sass_file("scss/index.scss")?;
+    statics.add_sass_file("scss/admin.scss")?;
     ructe.compile_templates("templates")?;
 
     Ok(())
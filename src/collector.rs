@@ -151,10 +151,30 @@ impl MergeCounter {
     }
 }
 
+/// A group of metrics in a [`Snapshot`] report, with each metric's formatted values
+pub(crate) type Section = (&'static str, Vec<(String, Vec<String>)>);
+
 impl Snapshot {
     pub(crate) fn present(self) {
+        for (title, metrics) in self.report() {
+            println!("{title}");
+
+            for (key, values) in metrics {
+                println!("\t{key}");
+
+                for value in values {
+                    println!("\t\t{value}");
+                }
+            }
+        }
+    }
+
+    /// Format the snapshot for display, merging paired start and finish counters
+    pub(crate) fn report(self) -> Vec<Section> {
+        let mut sections = Vec::new();
+
         if !self.counters.is_empty() {
-            println!("Counters");
+            let mut metrics = Vec::new();
             let mut merging = HashMap::new();
             for (key, counters) in self.counters {
                 if let Some(((start, _), name)) = PAIRS
@@ -177,44 +197,46 @@ impl Snapshot {
                     continue;
                 }
 
-                println!("\t{key}");
-                for counter in counters {
-                    println!("\t\t{counter}");
-                }
+                let values = counters.iter().map(ToString::to_string).collect();
+                metrics.push((key, values));
             }
 
             for (key, counters) in merging {
-                println!("\t{key}");
+                let values = counters
+                    .into_values()
+                    .filter_map(MergeCounter::merge)
+                    .map(|counter| counter.to_string())
+                    .collect();
 
-                for (_, counter) in counters {
-                    if let Some(counter) = counter.merge() {
-                        println!("\t\t{counter}");
-                    }
-                }
+                metrics.push((key.to_string(), values));
             }
+
+            sections.push(("Counters", metrics));
         }
 
         if !self.gauges.is_empty() {
-            println!("Gauges");
-            for (key, gauges) in self.gauges {
-                println!("\t{key}");
+            let metrics = self
+                .gauges
+                .into_iter()
+                .map(|(key, gauges)| (key, gauges.iter().map(ToString::to_string).collect()))
+                .collect();
 
-                for gauge in gauges {
-                    println!("\t\t{gauge}");
-                }
-            }
+            sections.push(("Gauges", metrics));
         }
 
         if !self.histograms.is_empty() {
-            println!("Histograms");
-            for (key, histograms) in self.histograms {
-                println!("\t{key}");
-
-                for histogram in histograms {
-                    println!("\t\t{histogram}");
-                }
-            }
+            let metrics = self
+                .histograms
+                .into_iter()
+                .map(|(key, histograms)| {
+                    (key, histograms.iter().map(ToString::to_string).collect())
+                })
+                .collect();
+
+            sections.push(("Histograms", metrics));
         }
+
+        sections
     }
 }
 
@@ -363,6 +385,19 @@ impl MemoryCollector {
         self.inner.snapshot()
     }
 
+    /// Stop reporting a labelled gauge whose subject is gone, such as a domain with nothing left
+    /// to deliver
+    pub(crate) fn remove_gauge(&self, name: &'static str, labels: &[(&'static str, &str)]) {
+        let labels = labels
+            .iter()
+            .map(|(key, value)| metrics::Label::new(*key, value.to_string()))
+            .collect::<Vec<_>>();
+
+        self.inner
+            .registry
+            .delete_gauge(&Key::from_parts(name, labels));
+    }
+
     fn add_description_if_missing(
         &self,
         key: &metrics::KeyName,
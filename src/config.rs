@@ -1,6 +1,10 @@
 use crate::{
-    error::Error,
+    data::{InboxLimits, RateLimiter, TrustedProxies, Visibility},
+    error::{Error, ErrorKind},
     extractors::{AdminConfig, XApiToken},
+    notify::EventFilter,
+    requests::BreakerSchedule,
+    telegram::{Access, TelegramAdmin},
 };
 use activitystreams::{
     iri,
@@ -16,6 +20,7 @@ use rustls::sign::CertifiedKey;
 use std::{
     net::{IpAddr, SocketAddr},
     path::PathBuf,
+    time::Duration,
 };
 use uuid::Uuid;
 
@@ -27,6 +32,8 @@ pub(crate) struct ParsedConfig {
     debug: bool,
     restricted_mode: bool,
     validate_signatures: bool,
+    validate_object_origin: bool,
+    relay_visibility: String,
     https: bool,
     publish_blocks: bool,
     sled_path: PathBuf,
@@ -35,6 +42,20 @@ pub(crate) struct ParsedConfig {
     opentelemetry_url: Option<IriString>,
     telegram_token: Option<String>,
     telegram_admin_handle: Option<String>,
+    telegram_admins: Option<String>,
+    telegram_chats: Option<String>,
+    telegram_events: Option<String>,
+    webhook_urls: Option<String>,
+    webhook_secret: Option<String>,
+    webhook_events: Option<String>,
+    matrix_homeserver: Option<IriString>,
+    matrix_access_token: Option<String>,
+    matrix_room_id: Option<String>,
+    matrix_events: Option<String>,
+    smtp_url: Option<String>,
+    smtp_from: Option<String>,
+    smtp_to: Option<String>,
+    smtp_events: Option<String>,
     api_token: Option<String>,
     tls_key: Option<PathBuf>,
     tls_cert: Option<PathBuf>,
@@ -44,6 +65,21 @@ pub(crate) struct ParsedConfig {
     prometheus_addr: Option<IpAddr>,
     prometheus_port: Option<u16>,
     deliver_concurrency: u64,
+    deliver_domain_concurrency: u64,
+    deliver_backlog_alert: u64,
+    blocklist_urls: Option<String>,
+    blocklist_sync_interval: u64,
+    announce_cache_ttl: u64,
+    key_rotation_grace: u64,
+    inbox_rate_limit: u32,
+    inbox_rate_burst: u32,
+    inbox_ip_rate_limit: u32,
+    inbox_ip_rate_burst: u32,
+    trusted_proxies: Option<String>,
+    inbox_shed_backlog: u64,
+    breaker_threshold: u64,
+    breaker_base_wait: u64,
+    breaker_max_wait: u64,
     client_timeout: u64,
     proxy_url: Option<IriString>,
     proxy_username: Option<String>,
@@ -59,13 +95,19 @@ pub struct Config {
     debug: bool,
     restricted_mode: bool,
     validate_signatures: bool,
+    validate_object_origin: bool,
+    relay_visibility: Visibility,
     publish_blocks: bool,
     base_uri: IriAbsoluteString,
     sled_path: PathBuf,
     source_repo: IriString,
     opentelemetry_url: Option<IriString>,
     telegram_token: Option<String>,
-    telegram_admin_handle: Option<String>,
+    telegram_access: Access,
+    telegram_events: EventFilter,
+    webhook: Option<WebhookConfig>,
+    matrix: Option<MatrixConfig>,
+    smtp: Option<SmtpConfig>,
     api_token: Option<String>,
     tls: Option<TlsConfig>,
     footer_blurb: Option<String>,
@@ -73,11 +115,49 @@ pub struct Config {
     local_blurb: Option<String>,
     prometheus_config: Option<PrometheusConfig>,
     deliver_concurrency: u64,
+    deliver_domain_concurrency: u64,
+    deliver_backlog_alert: u64,
+    blocklist_urls: Vec<IriString>,
+    blocklist_sync_interval: u64,
+    announce_cache_ttl: u64,
+    key_rotation_grace: u64,
+    inbox_rate_limit: u32,
+    inbox_rate_burst: u32,
+    inbox_ip_rate_limit: u32,
+    inbox_ip_rate_burst: u32,
+    trusted_proxies: TrustedProxies,
+    inbox_shed_backlog: u64,
+    breaker_threshold: u64,
+    breaker_base_wait: u64,
+    breaker_max_wait: u64,
     client_timeout: u64,
     proxy_config: Option<ProxyConfig>,
     signature_threads: Option<usize>,
 }
 
+#[derive(Clone)]
+pub(crate) struct WebhookConfig {
+    pub(crate) urls: Vec<IriString>,
+    pub(crate) secret: String,
+    pub(crate) events: EventFilter,
+}
+
+#[derive(Clone)]
+pub(crate) struct MatrixConfig {
+    pub(crate) homeserver: IriString,
+    pub(crate) access_token: String,
+    pub(crate) room_id: String,
+    pub(crate) events: EventFilter,
+}
+
+#[derive(Clone)]
+pub(crate) struct SmtpConfig {
+    pub(crate) url: String,
+    pub(crate) from: String,
+    pub(crate) to: Vec<String>,
+    pub(crate) events: EventFilter,
+}
+
 #[derive(Clone)]
 struct TlsConfig {
     key: PathBuf,
@@ -104,6 +184,7 @@ pub enum UrlKind {
     Following,
     Inbox,
     Index,
+    Key(Uuid),
     MainKey,
     Media(Uuid),
     NodeInfo,
@@ -121,6 +202,23 @@ pub enum AdminUrlKind {
     Connected,
     Stats,
     LastSeen,
+    Breakers,
+    ResetBreakers,
+    RotateKey,
+    Filter,
+    Unfilter,
+    Filters,
+    ImportBlocks,
+    PendingFollows,
+    ApproveFollows,
+    RejectFollows,
+    BlockActors,
+    UnblockActors,
+    BlockedActors,
+    Tokens,
+    CreateToken,
+    RevokeTokens,
+    AuditLog,
 }
 
 impl std::fmt::Debug for Config {
@@ -132,6 +230,8 @@ impl std::fmt::Debug for Config {
             .field("debug", &self.debug)
             .field("restricted_mode", &self.restricted_mode)
             .field("validate_signatures", &self.validate_signatures)
+            .field("validate_object_origin", &self.validate_object_origin)
+            .field("relay_visibility", &self.relay_visibility)
             .field("publish_blocks", &self.publish_blocks)
             .field("base_uri", &self.base_uri.to_string())
             .field("sled_path", &self.sled_path)
@@ -141,7 +241,26 @@ impl std::fmt::Debug for Config {
                 &self.opentelemetry_url.as_ref().map(|url| url.to_string()),
             )
             .field("telegram_token", &"[redacted]")
-            .field("telegram_admin_handle", &self.telegram_admin_handle)
+            .field("telegram_access", &self.telegram_access)
+            .field("telegram_events", &self.telegram_events)
+            .field(
+                "webhook_urls",
+                &self.webhook.as_ref().map(|webhook| {
+                    webhook
+                        .urls
+                        .iter()
+                        .map(|url| url.to_string())
+                        .collect::<Vec<_>>()
+                }),
+            )
+            .field(
+                "matrix_homeserver",
+                &self
+                    .matrix
+                    .as_ref()
+                    .map(|matrix| matrix.homeserver.to_string()),
+            )
+            .field("smtp_to", &self.smtp.as_ref().map(|smtp| &smtp.to))
             .field("api_token", &"[redacted]")
             .field("tls_key", &"[redacted]")
             .field("tls_cert", &"[redacted]")
@@ -150,6 +269,31 @@ impl std::fmt::Debug for Config {
             .field("local_blurb", &self.local_blurb)
             .field("prometheus_config", &self.prometheus_config)
             .field("deliver_concurrency", &self.deliver_concurrency)
+            .field(
+                "deliver_domain_concurrency",
+                &self.deliver_domain_concurrency,
+            )
+            .field("deliver_backlog_alert", &self.deliver_backlog_alert)
+            .field(
+                "blocklist_urls",
+                &self
+                    .blocklist_urls
+                    .iter()
+                    .map(|url| url.to_string())
+                    .collect::<Vec<_>>(),
+            )
+            .field("blocklist_sync_interval", &self.blocklist_sync_interval)
+            .field("announce_cache_ttl", &self.announce_cache_ttl)
+            .field("key_rotation_grace", &self.key_rotation_grace)
+            .field("inbox_rate_limit", &self.inbox_rate_limit)
+            .field("inbox_rate_burst", &self.inbox_rate_burst)
+            .field("inbox_ip_rate_limit", &self.inbox_ip_rate_limit)
+            .field("inbox_ip_rate_burst", &self.inbox_ip_rate_burst)
+            .field("trusted_proxies", &self.trusted_proxies)
+            .field("inbox_shed_backlog", &self.inbox_shed_backlog)
+            .field("breaker_threshold", &self.breaker_threshold)
+            .field("breaker_base_wait", &self.breaker_base_wait)
+            .field("breaker_max_wait", &self.breaker_max_wait)
             .field("client_timeout", &self.client_timeout)
             .field("proxy_config", &self.proxy_config)
             .field("signature_threads", &self.signature_threads)
@@ -166,6 +310,8 @@ impl Config {
             .set_default("debug", true)?
             .set_default("restricted_mode", false)?
             .set_default("validate_signatures", true)?
+            .set_default("validate_object_origin", false)?
+            .set_default("relay_visibility", "public")?
             .set_default("https", true)?
             .set_default("publish_blocks", false)?
             .set_default("sled_path", "./sled/db-0-34")?
@@ -174,6 +320,20 @@ impl Config {
             .set_default("opentelemetry_url", None as Option<&str>)?
             .set_default("telegram_token", None as Option<&str>)?
             .set_default("telegram_admin_handle", None as Option<&str>)?
+            .set_default("telegram_admins", None as Option<&str>)?
+            .set_default("telegram_chats", None as Option<&str>)?
+            .set_default("telegram_events", None as Option<&str>)?
+            .set_default("webhook_urls", None as Option<&str>)?
+            .set_default("webhook_secret", None as Option<&str>)?
+            .set_default("webhook_events", None as Option<&str>)?
+            .set_default("matrix_homeserver", None as Option<&str>)?
+            .set_default("matrix_access_token", None as Option<&str>)?
+            .set_default("matrix_room_id", None as Option<&str>)?
+            .set_default("matrix_events", None as Option<&str>)?
+            .set_default("smtp_url", None as Option<&str>)?
+            .set_default("smtp_from", None as Option<&str>)?
+            .set_default("smtp_to", None as Option<&str>)?
+            .set_default("smtp_events", None as Option<&str>)?
             .set_default("api_token", None as Option<&str>)?
             .set_default("tls_key", None as Option<&str>)?
             .set_default("tls_cert", None as Option<&str>)?
@@ -183,6 +343,21 @@ impl Config {
             .set_default("prometheus_addr", None as Option<&str>)?
             .set_default("prometheus_port", None as Option<u16>)?
             .set_default("deliver_concurrency", 8u64)?
+            .set_default("deliver_domain_concurrency", 2u64)?
+            .set_default("deliver_backlog_alert", 1000u64)?
+            .set_default("blocklist_urls", None as Option<&str>)?
+            .set_default("blocklist_sync_interval", 60 * 60u64)?
+            .set_default("announce_cache_ttl", 60 * 60 * 24 * 7u64)?
+            .set_default("key_rotation_grace", 60 * 60 * 24u64)?
+            .set_default("inbox_rate_limit", 20u32)?
+            .set_default("inbox_rate_burst", 200u32)?
+            .set_default("inbox_ip_rate_limit", 0u32)?
+            .set_default("inbox_ip_rate_burst", 200u32)?
+            .set_default("trusted_proxies", None as Option<&str>)?
+            .set_default("inbox_shed_backlog", 0u64)?
+            .set_default("breaker_threshold", 10u64)?
+            .set_default("breaker_base_wait", 60 * 10u64)?
+            .set_default("breaker_max_wait", 60 * 60 * 24u64)?
             .set_default("client_timeout", 10u64)?
             .set_default("proxy_url", None as Option<&str>)?
             .set_default("proxy_username", None as Option<&str>)?
@@ -216,6 +391,117 @@ impl Config {
             .map(|d| d.to_string())
             .collect();
 
+        let blocklist_urls = config
+            .blocklist_urls
+            .iter()
+            .flat_map(|s| s.split(','))
+            .map(|url| url.trim().parse())
+            .collect::<Result<Vec<IriString>, _>>()?;
+
+        let trusted_proxies = TrustedProxies::new(
+            &config
+                .trusted_proxies
+                .iter()
+                .flat_map(|s| s.split(','))
+                .map(|range| range.trim().to_owned())
+                .filter(|range| !range.is_empty())
+                .collect::<Vec<_>>(),
+        )?;
+
+        let telegram_admins = config
+            .telegram_admins
+            .iter()
+            .flat_map(|s| s.split(','))
+            .filter(|admin| !admin.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<TelegramAdmin>, _>>()?;
+
+        let telegram_chats = config
+            .telegram_chats
+            .iter()
+            .flat_map(|s| s.split(','))
+            .filter(|chat| !chat.trim().is_empty())
+            .map(|chat| {
+                chat.trim()
+                    .parse()
+                    .map_err(|_| ErrorKind::TelegramChat(chat.trim().to_owned()))
+            })
+            .collect::<Result<Vec<i64>, _>>()?;
+
+        let telegram_access = Access::new(
+            config.telegram_admin_handle,
+            telegram_admins,
+            telegram_chats,
+        );
+
+        let telegram_events = EventFilter::parse(config.telegram_events.as_deref())?;
+
+        let relay_visibility = config.relay_visibility.parse()?;
+
+        let webhook_urls = config
+            .webhook_urls
+            .iter()
+            .flat_map(|s| s.split(','))
+            .map(|url| url.trim().parse())
+            .collect::<Result<Vec<IriString>, _>>()?;
+
+        let webhook = match (webhook_urls.is_empty(), config.webhook_secret) {
+            (false, Some(secret)) => Some(WebhookConfig {
+                urls: webhook_urls,
+                secret,
+                events: EventFilter::parse(config.webhook_events.as_deref())?,
+            }),
+            (false, None) => {
+                tracing::warn!(
+                    "WEBHOOK_URLS is set but WEBHOOK_SECRET is not set, not sending webhooks"
+                );
+                None
+            }
+            (true, _) => None,
+        };
+
+        let matrix = match (
+            config.matrix_homeserver,
+            config.matrix_access_token,
+            config.matrix_room_id,
+        ) {
+            (Some(homeserver), Some(access_token), Some(room_id)) => Some(MatrixConfig {
+                homeserver,
+                access_token,
+                room_id,
+                events: EventFilter::parse(config.matrix_events.as_deref())?,
+            }),
+            (None, None, None) => None,
+            _ => {
+                tracing::warn!("MATRIX_HOMESERVER, MATRIX_ACCESS_TOKEN and MATRIX_ROOM_ID must all be set, not sending matrix notifications");
+                None
+            }
+        };
+
+        let smtp_to: Vec<String> = config
+            .smtp_to
+            .iter()
+            .flat_map(|s| s.split(','))
+            .map(|to| to.trim().to_owned())
+            .filter(|to| !to.is_empty())
+            .collect();
+
+        let smtp = match (config.smtp_url, config.smtp_from, smtp_to.is_empty()) {
+            (Some(url), Some(from), false) => Some(SmtpConfig {
+                url,
+                from,
+                to: smtp_to,
+                events: EventFilter::parse(config.smtp_events.as_deref())?,
+            }),
+            (None, None, true) => None,
+            _ => {
+                tracing::warn!(
+                    "SMTP_URL, SMTP_FROM and SMTP_TO must all be set, not sending email notifications"
+                );
+                None
+            }
+        };
+
         let prometheus_config = match (config.prometheus_addr, config.prometheus_port) {
             (Some(addr), Some(port)) => Some(PrometheusConfig { addr, port }),
             (Some(_), None) => {
@@ -266,13 +552,19 @@ impl Config {
             debug: config.debug,
             restricted_mode: config.restricted_mode,
             validate_signatures: config.validate_signatures,
+            validate_object_origin: config.validate_object_origin,
+            relay_visibility,
             publish_blocks: config.publish_blocks,
             base_uri,
             sled_path: config.sled_path,
             source_repo: source_url,
             opentelemetry_url: config.opentelemetry_url,
             telegram_token: config.telegram_token,
-            telegram_admin_handle: config.telegram_admin_handle,
+            telegram_access,
+            telegram_events,
+            webhook,
+            matrix,
+            smtp,
             api_token: config.api_token,
             tls,
             footer_blurb: config.footer_blurb,
@@ -280,6 +572,21 @@ impl Config {
             local_blurb: config.local_blurb,
             prometheus_config,
             deliver_concurrency: config.deliver_concurrency,
+            deliver_domain_concurrency: config.deliver_domain_concurrency,
+            deliver_backlog_alert: config.deliver_backlog_alert,
+            blocklist_urls,
+            blocklist_sync_interval: config.blocklist_sync_interval,
+            announce_cache_ttl: config.announce_cache_ttl,
+            key_rotation_grace: config.key_rotation_grace,
+            inbox_rate_limit: config.inbox_rate_limit,
+            inbox_rate_burst: config.inbox_rate_burst,
+            inbox_ip_rate_limit: config.inbox_ip_rate_limit,
+            inbox_ip_rate_burst: config.inbox_ip_rate_burst,
+            trusted_proxies,
+            inbox_shed_backlog: config.inbox_shed_backlog,
+            breaker_threshold: config.breaker_threshold,
+            breaker_base_wait: config.breaker_base_wait,
+            breaker_max_wait: config.breaker_max_wait,
             client_timeout: config.client_timeout,
             proxy_config,
             signature_threads: config.signature_threads,
@@ -305,6 +612,53 @@ impl Config {
         self.deliver_concurrency
     }
 
+    pub(crate) fn deliver_domain_concurrency(&self) -> u64 {
+        self.deliver_domain_concurrency
+    }
+
+    pub(crate) fn deliver_backlog_alert(&self) -> usize {
+        self.deliver_backlog_alert as usize
+    }
+
+    pub(crate) fn blocklist_urls(&self) -> &[IriString] {
+        &self.blocklist_urls
+    }
+
+    pub(crate) fn blocklist_sync_interval(&self) -> Duration {
+        Duration::from_secs(self.blocklist_sync_interval)
+    }
+
+    pub(crate) fn announce_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.announce_cache_ttl)
+    }
+
+    pub(crate) fn key_rotation_grace(&self) -> Duration {
+        Duration::from_secs(self.key_rotation_grace)
+    }
+
+    pub(crate) fn inbox_limits(&self) -> InboxLimits {
+        InboxLimits {
+            domains: RateLimiter::new(self.inbox_rate_limit, self.inbox_rate_burst),
+            addresses: RateLimiter::new(self.inbox_ip_rate_limit, self.inbox_ip_rate_burst),
+        }
+    }
+
+    pub(crate) fn trusted_proxies(&self) -> &TrustedProxies {
+        &self.trusted_proxies
+    }
+
+    pub(crate) fn inbox_shed_backlog(&self) -> usize {
+        self.inbox_shed_backlog as usize
+    }
+
+    pub(crate) fn breaker_schedule(&self) -> BreakerSchedule {
+        BreakerSchedule::new(
+            self.breaker_threshold as usize,
+            Duration::from_secs(self.breaker_base_wait),
+            Duration::from_secs(self.breaker_max_wait),
+        )
+    }
+
     pub(crate) fn prometheus_bind_address(&self) -> Option<SocketAddr> {
         let config = self.prometheus_config.as_ref()?;
 
@@ -391,12 +745,18 @@ impl Config {
         self.validate_signatures
     }
 
+    pub(crate) fn validate_object_origin(&self) -> bool {
+        self.validate_object_origin
+    }
+
+    pub(crate) fn relay_visibility(&self) -> Visibility {
+        self.relay_visibility
+    }
+
+    /// Requests signed with RFC 9421 carry a `Content-Digest` instead of a `Digest`, so the inbox
+    /// checks that one of the two was verified rather than requiring a `Digest` here
     pub(crate) fn digest_middleware(&self) -> VerifyDigest<Sha256> {
-        if self.validate_signatures {
-            VerifyDigest::new(Sha256::new())
-        } else {
-            VerifyDigest::new(Sha256::new()).optional()
-        }
+        VerifyDigest::new(Sha256::new()).optional()
     }
 
     pub(crate) fn x_api_token(&self) -> Option<XApiToken> {
@@ -505,11 +865,33 @@ impl Config {
         self.opentelemetry_url.as_ref()
     }
 
-    pub(crate) fn telegram_info(&self) -> Option<(&str, &str)> {
-        self.telegram_token.as_deref().and_then(|token| {
-            let handle = self.telegram_admin_handle.as_deref()?;
-            Some((token, handle))
-        })
+    pub(crate) fn telegram_info(&self) -> Option<(&str, &Access)> {
+        let token = self.telegram_token.as_deref()?;
+
+        if !self.telegram_access.has_admins() {
+            tracing::warn!(
+                "TELEGRAM_TOKEN is set but no telegram admins are, not starting the bot"
+            );
+            return None;
+        }
+
+        Some((token, &self.telegram_access))
+    }
+
+    pub(crate) fn telegram_events(&self) -> &EventFilter {
+        &self.telegram_events
+    }
+
+    pub(crate) fn webhook_config(&self) -> Option<&WebhookConfig> {
+        self.webhook.as_ref()
+    }
+
+    pub(crate) fn matrix_config(&self) -> Option<&MatrixConfig> {
+        self.matrix.as_ref()
+    }
+
+    pub(crate) fn smtp_config(&self) -> Option<&SmtpConfig> {
+        self.smtp.as_ref()
     }
 
     pub(crate) fn generate_url(&self, kind: UrlKind) -> IriString {
@@ -534,6 +916,9 @@ impl Config {
                 .resolve(IriRelativeStr::new("inbox")?.as_ref())
                 .try_to_dedicated_string()?,
             UrlKind::Index => self.base_uri.clone().into(),
+            UrlKind::Key(uuid) => FixedBaseResolver::new(self.base_uri.as_ref())
+                .resolve(IriRelativeStr::new(&format!("actor/keys/{uuid}"))?.as_ref())
+                .try_to_dedicated_string()?,
             UrlKind::MainKey => {
                 let actor = IriRelativeStr::new("actor")?;
                 let fragment = IriFragmentStr::new("main-key")?;
@@ -575,6 +960,23 @@ impl Config {
             AdminUrlKind::Connected => "api/v1/admin/connected",
             AdminUrlKind::Stats => "api/v1/admin/stats",
             AdminUrlKind::LastSeen => "api/v1/admin/last_seen",
+            AdminUrlKind::Breakers => "api/v1/admin/breakers",
+            AdminUrlKind::ResetBreakers => "api/v1/admin/breakers/reset",
+            AdminUrlKind::RotateKey => "api/v1/admin/rotate_key",
+            AdminUrlKind::Filter => "api/v1/admin/filter",
+            AdminUrlKind::Unfilter => "api/v1/admin/unfilter",
+            AdminUrlKind::Filters => "api/v1/admin/filters",
+            AdminUrlKind::ImportBlocks => "api/v1/admin/import_blocks",
+            AdminUrlKind::PendingFollows => "api/v1/admin/pending_follows",
+            AdminUrlKind::ApproveFollows => "api/v1/admin/pending_follows/approve",
+            AdminUrlKind::RejectFollows => "api/v1/admin/pending_follows/reject",
+            AdminUrlKind::Tokens => "api/v1/admin/tokens",
+            AdminUrlKind::CreateToken => "api/v1/admin/tokens/create",
+            AdminUrlKind::RevokeTokens => "api/v1/admin/tokens/revoke",
+            AdminUrlKind::AuditLog => "api/v1/admin/audit_log",
+            AdminUrlKind::BlockActors => "api/v1/admin/block_actors",
+            AdminUrlKind::UnblockActors => "api/v1/admin/unblock_actors",
+            AdminUrlKind::BlockedActors => "api/v1/admin/blocked_actors",
         };
 
         let iri = FixedBaseResolver::new(self.base_uri.as_ref())
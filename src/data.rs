@@ -1,11 +1,20 @@
 mod actor;
+pub(crate) mod blocklist;
+mod content_filter;
+mod deliver_queues;
 mod last_online;
 mod media;
 mod node;
+mod rate_limit;
 mod state;
+mod visibility;
 
-pub(crate) use actor::ActorCache;
+pub(crate) use actor::{ActorCache, MaybeCached};
+pub(crate) use content_filter::{ContentFilter, ContentFilters};
+pub(crate) use deliver_queues::DeliverQueues;
 pub(crate) use last_online::LastOnline;
 pub(crate) use media::MediaCache;
 pub(crate) use node::{Node, NodeCache};
+pub(crate) use rate_limit::{client_key, retry_after, InboxLimits, RateLimiter, TrustedProxies};
 pub(crate) use state::State;
+pub(crate) use visibility::Visibility;
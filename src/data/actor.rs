@@ -1,14 +1,19 @@
+use super::content_filter::hashtags;
 use crate::{
     apub::AcceptedActors,
-    db::{Actor, Db},
+    db::{Actor, AssertionKey, Db},
     error::{Error, ErrorKind},
+    keys::VerifyingKey,
     requests::{BreakerStrategy, Requests},
 };
 use activitystreams::{iri_string::types::IriString, prelude::*};
+use serde_json::Value;
 use std::time::{Duration, SystemTime};
 
 const REFETCH_DURATION: Duration = Duration::from_secs(60 * 30);
 
+const OPT_OUT_HASHTAGS: [&str; 2] = ["nobot", "norelay"];
+
 #[derive(Debug)]
 pub enum MaybeCached<T> {
     Cached(T),
@@ -81,6 +86,9 @@ impl ActorCache {
             .ok_or(ErrorKind::MissingId)?;
 
         let inbox = get_inbox(&accepted_actor)?.clone();
+        let value = serde_json::to_value(&accepted_actor)?;
+        let opted_out = opts_out(&value);
+        let assertion_keys = assertion_keys(&value, &accepted_actor_id);
 
         let actor = Actor {
             id: accepted_actor_id.clone(),
@@ -88,6 +96,8 @@ impl ActorCache {
             public_key_id: accepted_actor.ext_one.public_key.id,
             inbox,
             saved_at: SystemTime::now(),
+            opted_out,
+            assertion_keys,
         };
 
         self.db.save_actor(actor.clone()).await?;
@@ -96,9 +106,143 @@ impl ActorCache {
     }
 }
 
+/// Whether an actor's profile asks relays and bots to leave their posts alone
+fn opts_out(actor: &Value) -> bool {
+    let hidden = ["discoverable", "indexable"]
+        .into_iter()
+        .any(|field| actor.get(field).and_then(Value::as_bool) == Some(false));
+
+    let tagged = hashtags(actor).any(|tag| OPT_OUT_HASHTAGS.contains(&tag.as_str()));
+
+    let mentioned = actor
+        .get("summary")
+        .and_then(Value::as_str)
+        .is_some_and(|summary| {
+            strip_html(summary)
+                .to_lowercase()
+                .split(|c: char| !c.is_alphanumeric() && c != '#')
+                .any(|word| {
+                    word.strip_prefix('#')
+                        .is_some_and(|tag| OPT_OUT_HASHTAGS.contains(&tag))
+                })
+        });
+
+    hidden || tagged || mentioned
+}
+
+/// The Ed25519 `Multikey`s an actor lists as its own in `assertionMethod`
+fn assertion_keys(actor: &Value, actor_id: &IriString) -> Vec<AssertionKey> {
+    let methods = match actor.get("assertionMethod") {
+        Some(Value::Array(methods)) => methods.iter().collect(),
+        Some(method) => vec![method],
+        None => Vec::new(),
+    };
+
+    methods
+        .into_iter()
+        .filter(|method| method.get("type").and_then(Value::as_str) == Some("Multikey"))
+        .filter(|method| {
+            method.get("controller").and_then(Value::as_str) == Some(actor_id.as_str())
+        })
+        .filter_map(|method| {
+            let id = method.get("id")?.as_str()?.parse().ok()?;
+            let public_key_multibase = method.get("publicKeyMultibase")?.as_str()?;
+
+            VerifyingKey::from_multibase(public_key_multibase)?;
+
+            Some(AssertionKey {
+                id,
+                public_key_multibase: public_key_multibase.to_owned(),
+            })
+        })
+        .collect()
+}
+
+fn strip_html(html: &str) -> String {
+    let mut in_tag = false;
+
+    html.chars()
+        .filter(|c| match c {
+            '<' => {
+                in_tag = true;
+                false
+            }
+            '>' => {
+                in_tag = false;
+                false
+            }
+            _ => !in_tag,
+        })
+        .collect()
+}
+
 fn get_inbox(actor: &AcceptedActors) -> Result<&IriString, Error> {
     Ok(actor
         .endpoints()?
         .and_then(|e| e.shared_inbox.as_ref())
         .unwrap_or(actor.inbox()?))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{assertion_keys, opts_out};
+
+    #[test]
+    fn honors_opt_outs() {
+        let undiscoverable = serde_json::json!({ "discoverable": false, "indexable": true });
+        let unindexable = serde_json::json!({ "discoverable": true, "indexable": false });
+        let tagged = serde_json::json!({
+            "tag": [{ "type": "Hashtag", "name": "#NoBot" }],
+        });
+        let bio = serde_json::json!({
+            "summary": "<p>hi! <a href=\"https://example.com/tags/norelay\" class=\"mention hashtag\">#<span>norelay</span></a></p>",
+        });
+        let open = serde_json::json!({
+            "discoverable": true,
+            "summary": "<p>I like robots, #notabot</p>",
+            "tag": [{ "type": "Hashtag", "name": "#bots" }],
+        });
+
+        assert!(opts_out(&undiscoverable));
+        assert!(opts_out(&unindexable));
+        assert!(opts_out(&tagged));
+        assert!(opts_out(&bio));
+        assert!(!opts_out(&open));
+    }
+
+    #[test]
+    fn reads_assertion_keys() {
+        let actor_id = "https://example.com/users/alice".parse().unwrap();
+        let actor = serde_json::json!({
+            "id": "https://example.com/users/alice",
+            "assertionMethod": [
+                {
+                    "id": "https://example.com/users/alice#ed25519-key",
+                    "type": "Multikey",
+                    "controller": "https://example.com/users/alice",
+                    "publicKeyMultibase": "z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+                },
+                {
+                    "id": "https://example.com/users/bob#ed25519-key",
+                    "type": "Multikey",
+                    "controller": "https://example.com/users/bob",
+                    "publicKeyMultibase": "z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+                },
+                {
+                    "id": "https://example.com/users/alice#broken-key",
+                    "type": "Multikey",
+                    "controller": "https://example.com/users/alice",
+                    "publicKeyMultibase": "zNotAKey",
+                },
+            ],
+        });
+
+        let keys = assertion_keys(&actor, &actor_id);
+
+        assert_eq!(keys.len(), 1);
+        assert_eq!(
+            keys[0].id.as_str(),
+            "https://example.com/users/alice#ed25519-key"
+        );
+    }
+}
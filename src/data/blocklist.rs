@@ -0,0 +1,237 @@
+use crate::{
+    db::{BlockSeverity, DomainBlock},
+    error::{Error, ErrorKind},
+};
+use serde_json::Value;
+
+const CSV_HEADER: [&str; 6] = [
+    "#domain",
+    "#severity",
+    "#reject_media",
+    "#reject_reports",
+    "#public_comment",
+    "#obfuscate",
+];
+
+/// Parse a blocklist that is either JSON or Mastodon's domain-blocks CSV format
+pub(crate) fn parse(bytes: &[u8]) -> Result<Vec<DomainBlock>, Error> {
+    match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'[' | b'{') => from_json(&serde_json::from_slice(bytes)?),
+        _ => from_csv(&String::from_utf8_lossy(bytes)),
+    }
+}
+
+/// Write blocks in Mastodon's domain-blocks CSV format
+pub(crate) fn to_csv(blocks: &[DomainBlock]) -> Result<String, Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.write_record(CSV_HEADER)?;
+
+    for block in blocks {
+        writer.write_record([
+            block.domain.as_str(),
+            block.severity.as_str(),
+            "false",
+            "false",
+            block.reason.as_deref().unwrap_or_default(),
+            "false",
+        ])?;
+    }
+
+    let vec = writer
+        .into_inner()
+        .map_err(|e| ErrorKind::Io(e.into_error()))?;
+
+    Ok(String::from_utf8_lossy(&vec).to_string())
+}
+
+/// Read blocks from Mastodon's domain-blocks CSV format, or from a plain list of domains
+pub(crate) fn from_csv(input: &str) -> Result<Vec<DomainBlock>, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(input.as_bytes());
+
+    let mut columns = (0, Some(1), None);
+    let mut blocks = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+
+        let column = |name: &str| {
+            record
+                .iter()
+                .position(|field| field.trim().trim_start_matches('#') == name)
+        };
+
+        if index == 0 {
+            if let Some(domain) = column("domain") {
+                columns = (domain, column("severity"), column("public_comment"));
+                continue;
+            }
+        }
+
+        let (domain, severity, reason) = columns;
+
+        let domain = record.get(domain);
+        let severity = severity.and_then(|severity| record.get(severity));
+        let reason = reason.and_then(|reason| record.get(reason));
+
+        if let Some(block) = domain_block(domain, severity, reason) {
+            blocks.push(block);
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Read blocks from a list of domains, a list of Mastodon's public domain block objects, or this
+/// relay's own admin API response
+fn from_json(value: &Value) -> Result<Vec<DomainBlock>, Error> {
+    let entries = match value {
+        Value::Array(entries) => entries,
+        Value::Object(object) => match object.get("blocks") {
+            Some(Value::Array(entries)) => entries,
+            _ => return Err(ErrorKind::ObjectFormat.into()),
+        },
+        _ => return Err(ErrorKind::ObjectFormat.into()),
+    };
+
+    let mut blocks = Vec::new();
+
+    for entry in entries {
+        let block = match entry {
+            Value::String(domain) => domain_block(Some(domain), None, None),
+            entry => {
+                let field = |name: &str| entry.get(name).and_then(Value::as_str);
+
+                domain_block(
+                    field("domain"),
+                    field("severity"),
+                    field("reason")
+                        .or_else(|| field("comment"))
+                        .or_else(|| field("public_comment")),
+                )
+            }
+        };
+
+        blocks.extend(block);
+    }
+
+    Ok(blocks)
+}
+
+/// Build a block from one entry, skipping entries that aren't usable
+///
+/// Anything that isn't a hostname is skipped, so an error page served in place of the list can't
+/// turn into blocks.
+fn domain_block(
+    domain: Option<&str>,
+    severity: Option<&str>,
+    reason: Option<&str>,
+) -> Option<DomainBlock> {
+    let domain = domain?.trim().to_lowercase();
+
+    // Obfuscated entries from Mastodon's public list can't be matched against
+    if domain.is_empty() || domain.contains('*') {
+        return None;
+    }
+
+    if url::Host::parse(&domain).is_err() {
+        tracing::debug!("Skipping blocklist entry that isn't a hostname: {domain}");
+        return None;
+    }
+
+    let severity = match severity.map(str::trim) {
+        // Mastodon's "noop" blocks only reject media or reports, which a relay doesn't handle
+        Some("noop") => return None,
+        Some(severity) if !severity.is_empty() => match severity.parse() {
+            Ok(severity) => severity,
+            Err(_) => {
+                tracing::warn!("Skipping block for {domain} with unknown severity {severity}");
+                return None;
+            }
+        },
+        _ => BlockSeverity::Suspend,
+    };
+
+    Some(DomainBlock {
+        domain,
+        severity,
+        reason: reason.map(String::from).filter(|reason| !reason.is_empty()),
+        created_by: None,
+        created_at: None,
+        source: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_csv, parse, to_csv};
+    use crate::db::{BlockSeverity, DomainBlock};
+
+    #[test]
+    fn round_trips_mastodon_csv() {
+        let blocks = vec![
+            DomainBlock::new(
+                "spam.example".into(),
+                BlockSeverity::Suspend,
+                Some("spam, mostly".into()),
+                None,
+            ),
+            DomainBlock::new("loud.example".into(), BlockSeverity::Silence, None, None),
+        ];
+
+        let csv = to_csv(&blocks).unwrap();
+        let parsed = from_csv(&csv).unwrap();
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].domain, "spam.example");
+        assert_eq!(parsed[0].severity, BlockSeverity::Suspend);
+        assert_eq!(parsed[0].reason.as_deref(), Some("spam, mostly"));
+        assert_eq!(parsed[1].domain, "loud.example");
+        assert_eq!(parsed[1].severity, BlockSeverity::Silence);
+        assert_eq!(parsed[1].reason, None);
+    }
+
+    #[test]
+    fn imports_plain_lists_and_skips_noop() {
+        let csv = "domain,severity\nspam.example,suspend\nmedia.example,noop\n";
+        let parsed = from_csv(csv).unwrap();
+        assert_eq!(parsed.len(), 1);
+
+        let parsed = from_csv("spam.example\nloud.example\n").unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[1].severity, BlockSeverity::Suspend);
+    }
+
+    #[test]
+    fn skips_entries_that_arent_hostnames() {
+        let page = "<!DOCTYPE html>\n<html>\n<body>\nNot Found\n</body>\n</html>\n";
+        assert!(parse(page.as_bytes()).unwrap().is_empty());
+
+        let csv = "domain,severity\nspam.example,suspend\nloud.example,yell\nhttps://x.example/,suspend\n";
+        let parsed = from_csv(csv).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].domain, "spam.example");
+    }
+
+    #[test]
+    fn parses_json_lists() {
+        let parsed = parse(br#"["spam.example", "LOUD.example"]"#).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[1].domain, "loud.example");
+
+        let parsed = parse(
+            br#"[
+                {"domain": "spam.example", "severity": "suspend", "comment": "spam"},
+                {"domain": "lo**.example", "severity": "silence"},
+                {"domain": "loud.example", "severity": "silence"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].reason.as_deref(), Some("spam"));
+        assert_eq!(parsed[1].severity, BlockSeverity::Silence);
+    }
+}
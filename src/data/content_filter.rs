@@ -0,0 +1,218 @@
+use crate::error::{Error, ErrorKind};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// An operator-defined rule for dropping posts before they are relayed
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub(crate) enum ContentFilter {
+    /// Case-insensitive substring of the post's content, summary or name
+    Keyword(String),
+
+    /// Regular expression matched against the post's content, summary or name
+    Regex(String),
+
+    /// Hashtag attached to the post, with or without the leading `#`
+    Hashtag(String),
+
+    /// Posts marked sensitive or hidden behind a content warning
+    Sensitive,
+}
+
+impl ContentFilter {
+    /// Check the filter is usable and bring it into the form it is stored in
+    pub(crate) fn normalize(self) -> Result<Self, Error> {
+        match self {
+            Self::Keyword(keyword) => {
+                let keyword = keyword.trim().to_lowercase();
+
+                // An empty keyword would be found in every post
+                if keyword.is_empty() {
+                    return Err(ErrorKind::InvalidFilter(String::from("keyword is empty")).into());
+                }
+
+                Ok(Self::Keyword(keyword))
+            }
+            Self::Regex(regex) => {
+                Regex::new(&regex).map_err(|e| ErrorKind::InvalidFilter(e.to_string()))?;
+                Ok(Self::Regex(regex))
+            }
+            Self::Hashtag(hashtag) => {
+                let hashtag = normalize_hashtag(&hashtag);
+
+                if hashtag.is_empty() {
+                    return Err(ErrorKind::InvalidFilter(String::from("hashtag is empty")).into());
+                }
+
+                Ok(Self::Hashtag(hashtag))
+            }
+            Self::Sensitive => Ok(Self::Sensitive),
+        }
+    }
+}
+
+impl std::fmt::Display for ContentFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Keyword(keyword) => write!(f, "keyword: {keyword}"),
+            Self::Regex(regex) => write!(f, "regex: {regex}"),
+            Self::Hashtag(hashtag) => write!(f, "hashtag: #{hashtag}"),
+            Self::Sensitive => write!(f, "sensitive"),
+        }
+    }
+}
+
+/// A set of content filters, prepared for matching against posts
+#[derive(Debug, Default)]
+pub(crate) struct ContentFilters {
+    keywords: Vec<String>,
+    regexes: Vec<Regex>,
+    hashtags: HashSet<String>,
+    sensitive: bool,
+}
+
+impl ContentFilters {
+    pub(crate) fn new(filters: Vec<ContentFilter>) -> Self {
+        let mut content_filters = ContentFilters::default();
+
+        for filter in filters {
+            match filter {
+                ContentFilter::Keyword(keyword) => content_filters.keywords.push(keyword),
+                ContentFilter::Regex(regex) => match Regex::new(&regex) {
+                    Ok(regex) => content_filters.regexes.push(regex),
+                    Err(e) => tracing::warn!("Skipping invalid filter regex {regex}: {e}"),
+                },
+                ContentFilter::Hashtag(hashtag) => {
+                    content_filters.hashtags.insert(hashtag);
+                }
+                ContentFilter::Sensitive => content_filters.sensitive = true,
+            }
+        }
+
+        content_filters
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.keywords.is_empty()
+            && self.regexes.is_empty()
+            && self.hashtags.is_empty()
+            && !self.sensitive
+    }
+
+    /// Determine whether the given post should be dropped
+    pub(crate) fn matches(&self, object: &Value) -> bool {
+        if self.sensitive && is_sensitive(object) {
+            return true;
+        }
+
+        if !self.hashtags.is_empty() && hashtags(object).any(|tag| self.hashtags.contains(&tag)) {
+            return true;
+        }
+
+        if self.keywords.is_empty() && self.regexes.is_empty() {
+            return false;
+        }
+
+        texts(object).any(|text| {
+            let lowercase = text.to_lowercase();
+
+            self.keywords
+                .iter()
+                .any(|keyword| lowercase.contains(keyword.as_str()))
+                || self.regexes.iter().any(|regex| regex.is_match(text))
+        })
+    }
+}
+
+fn normalize_hashtag(hashtag: &str) -> String {
+    hashtag.trim().trim_start_matches('#').to_lowercase()
+}
+
+fn is_sensitive(object: &Value) -> bool {
+    object.get("sensitive").and_then(Value::as_bool) == Some(true)
+        || object
+            .get("summary")
+            .and_then(Value::as_str)
+            .is_some_and(|summary| !summary.trim().is_empty())
+}
+
+pub(super) fn hashtags(object: &Value) -> impl Iterator<Item = String> + '_ {
+    one_or_many(object.get("tag"))
+        .filter(|tag| tag.get("type").and_then(Value::as_str) == Some("Hashtag"))
+        .filter_map(|tag| tag.get("name").and_then(Value::as_str))
+        .map(normalize_hashtag)
+}
+
+fn texts(object: &Value) -> impl Iterator<Item = &str> {
+    ["content", "summary", "name"]
+        .into_iter()
+        .flat_map(move |field| {
+            let value = object.get(field).into_iter();
+            let map = object
+                .get(format!("{field}Map"))
+                .and_then(Value::as_object)
+                .into_iter()
+                .flat_map(|map| map.values());
+
+            value.chain(map).collect::<Vec<_>>()
+        })
+        .filter_map(Value::as_str)
+}
+
+fn one_or_many(value: Option<&Value>) -> impl Iterator<Item = &Value> {
+    match value {
+        Some(Value::Array(values)) => values.iter().collect::<Vec<_>>().into_iter(),
+        Some(value) => vec![value].into_iter(),
+        None => Vec::new().into_iter(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContentFilter, ContentFilters};
+
+    #[test]
+    fn matches_posts() {
+        let filters = ContentFilters::new(vec![
+            ContentFilter::Keyword("crypto".into()).normalize().unwrap(),
+            ContentFilter::Regex(r"(?i)buy\s+now".into())
+                .normalize()
+                .unwrap(),
+            ContentFilter::Hashtag("#Spam".into()).normalize().unwrap(),
+        ]);
+
+        let keyword = serde_json::json!({ "content": "<p>All about Crypto</p>" });
+        let regex = serde_json::json!({ "contentMap": { "en": "BUY   now!" } });
+        let hashtag = serde_json::json!({
+            "content": "hello",
+            "tag": [{ "type": "Hashtag", "name": "#spam" }],
+        });
+        let sensitive =
+            serde_json::json!({ "content": "hello", "summary": "cw", "sensitive": true });
+        let clean =
+            serde_json::json!({ "content": "hello", "tag": { "type": "Mention", "name": "spam" } });
+
+        assert!(filters.matches(&keyword));
+        assert!(filters.matches(&regex));
+        assert!(filters.matches(&hashtag));
+        assert!(!filters.matches(&sensitive));
+        assert!(!filters.matches(&clean));
+
+        let filters = ContentFilters::new(vec![ContentFilter::Sensitive]);
+        assert!(filters.matches(&sensitive));
+        assert!(!filters.matches(&clean));
+    }
+
+    #[test]
+    fn rejects_invalid_regex() {
+        assert!(ContentFilter::Regex("(".into()).normalize().is_err());
+    }
+
+    #[test]
+    fn rejects_empty_filters() {
+        assert!(ContentFilter::Keyword("  ".into()).normalize().is_err());
+        assert!(ContentFilter::Hashtag("".into()).normalize().is_err());
+        assert!(ContentFilter::Hashtag(" # ".into()).normalize().is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use crate::collector::MemoryCollector;
+use activitystreams::iri_string::types::IriStr;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+const DEFER_BASE: Duration = Duration::from_secs(1);
+const DEFER_MAX: Duration = Duration::from_secs(60);
+
+// Past this many domains, further domains' queues aren't given their own gauges
+const MAX_REPORTED_DOMAINS: usize = 50;
+
+/// Tracks outstanding deliveries per remote domain
+///
+/// Deliveries share one job queue, so a slow domain could otherwise occupy every deliver worker.
+/// Each domain may only have `per_domain` deliveries in flight at a time. Any others for that
+/// domain are pushed to the back of the queue, letting other domains' deliveries run in between.
+/// The backlog is saved periodically and on shutdown, and counted back in on startup.
+pub(crate) struct DeliverQueues {
+    domains: Mutex<HashMap<String, DomainQueue>>,
+    per_domain: usize,
+    backed_up: AtomicBool,
+    reported: AtomicUsize,
+    collector: MemoryCollector,
+}
+
+#[derive(Default)]
+struct DomainQueue {
+    queued: usize,
+    in_flight: usize,
+    reported: bool,
+}
+
+pub(crate) struct DeliverPermit {
+    queues: Arc<DeliverQueues>,
+    authority: String,
+}
+
+impl DeliverQueues {
+    pub(crate) fn new(per_domain: usize, collector: MemoryCollector) -> Self {
+        DeliverQueues {
+            domains: Mutex::new(HashMap::new()),
+            per_domain: per_domain.max(1),
+            backed_up: AtomicBool::new(false),
+            reported: AtomicUsize::new(0),
+            collector,
+        }
+    }
+
+    /// How many deliveries are waiting to start for each domain
+    pub(crate) fn backlog(&self) -> Vec<(String, usize)> {
+        self.domains
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, queue)| queue.queued > 0)
+            .map(|(authority, queue)| (authority.clone(), queue.queued))
+            .collect()
+    }
+
+    /// Count a backlog saved by a previous run toward each domain's queue
+    pub(crate) fn restore(&self, backlog: Vec<(String, usize)>) {
+        let mut guard = self.domains.lock().unwrap();
+
+        for (authority, queued) in backlog {
+            let queue = guard.entry(authority.clone()).or_default();
+            queue.queued += queued;
+            self.record(&authority, queue);
+        }
+    }
+
+    /// How many deliveries are waiting to start, across every domain
+    pub(crate) fn queued(&self) -> usize {
+        self.domains
+            .lock()
+            .unwrap()
+            .values()
+            .map(|queue| queue.queued)
+            .sum()
+    }
+
+    /// Return the backlog when it first reaches `threshold`
+    ///
+    /// The backlog isn't reported again until it has drained below half of `threshold`.
+    pub(crate) fn check_backlog(&self, threshold: usize) -> Option<usize> {
+        let queued = self.queued();
+
+        if queued >= threshold {
+            if !self.backed_up.swap(true, Ordering::AcqRel) {
+                return Some(queued);
+            }
+        } else if queued < threshold / 2 {
+            self.backed_up.store(false, Ordering::Release);
+        }
+
+        None
+    }
+
+    pub(crate) fn enqueued(&self, inbox: &IriStr) {
+        let Some(authority) = inbox.authority_str() else {
+            return;
+        };
+
+        let mut guard = self.domains.lock().unwrap();
+        let queue = guard.entry(authority.to_owned()).or_default();
+        queue.queued += 1;
+        self.record(authority, queue);
+    }
+
+    /// Claim a delivery slot for the inbox's domain, or return how long to wait before retrying
+    pub(crate) fn try_start(self: &Arc<Self>, inbox: &IriStr) -> Result<DeliverPermit, Duration> {
+        let authority = inbox.authority_str().unwrap_or_default().to_owned();
+
+        let mut guard = self.domains.lock().unwrap();
+        let queue = guard.entry(authority.clone()).or_default();
+
+        if queue.in_flight >= self.per_domain {
+            metrics::counter!("relay.deliver.deferred").increment(1);
+
+            let rounds = u32::try_from(queue.queued / self.per_domain).unwrap_or(u32::MAX);
+            return Err(DEFER_BASE
+                .saturating_mul(rounds.saturating_add(1))
+                .min(DEFER_MAX));
+        }
+
+        queue.queued = queue.queued.saturating_sub(1);
+        queue.in_flight += 1;
+        self.record(&authority, queue);
+
+        Ok(DeliverPermit {
+            queues: Arc::clone(self),
+            authority,
+        })
+    }
+}
+
+impl DeliverQueues {
+    /// Update a domain's gauges, if it already has them or there's room for another domain's
+    ///
+    /// Called with `domains` locked.
+    fn record(&self, authority: &str, queue: &mut DomainQueue) {
+        if !queue.reported {
+            if self.reported.load(Ordering::Acquire) >= MAX_REPORTED_DOMAINS {
+                return;
+            }
+
+            self.reported.fetch_add(1, Ordering::AcqRel);
+            queue.reported = true;
+        }
+
+        metrics::gauge!("relay.deliver.queue-depth", "domain" => authority.to_owned())
+            .set(crate::collector::recordable(queue.queued));
+        metrics::gauge!("relay.deliver.in-flight", "domain" => authority.to_owned())
+            .set(crate::collector::recordable(queue.in_flight));
+    }
+
+    /// Zero a drained domain's gauges, so exporters that keep series report it as idle, and
+    /// drop them from the stats
+    ///
+    /// Called with `domains` locked.
+    fn forget(&self, authority: &str, queue: DomainQueue) {
+        if !queue.reported {
+            return;
+        }
+
+        self.reported.fetch_sub(1, Ordering::AcqRel);
+
+        for name in ["relay.deliver.queue-depth", "relay.deliver.in-flight"] {
+            metrics::gauge!(name, "domain" => authority.to_owned()).set(0);
+            self.collector.remove_gauge(name, &[("domain", authority)]);
+        }
+    }
+}
+
+impl Drop for DeliverPermit {
+    fn drop(&mut self) {
+        let mut guard = self.queues.domains.lock().unwrap();
+
+        if let Some(queue) = guard.get_mut(&self.authority) {
+            queue.in_flight = queue.in_flight.saturating_sub(1);
+
+            if queue.queued == 0 && queue.in_flight == 0 {
+                if let Some(queue) = guard.remove(&self.authority) {
+                    self.queues.forget(&self.authority, queue);
+                }
+            } else {
+                self.queues.record(&self.authority, queue);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for DeliverQueues {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeliverQueues")
+            .field("per_domain", &self.per_domain)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeliverQueues, MAX_REPORTED_DOMAINS};
+    use crate::collector::MemoryCollector;
+    use activitystreams::iri_string::types::IriString;
+    use std::sync::{atomic::Ordering, Arc};
+
+    #[test]
+    fn caps_in_flight_per_domain() {
+        let queues = Arc::new(DeliverQueues::new(1, MemoryCollector::new()));
+        let slow: IriString = "https://slow.example/inbox".parse().unwrap();
+        let fast: IriString = "https://fast.example/inbox".parse().unwrap();
+
+        let permit = queues.try_start(&slow).unwrap();
+        assert!(queues.try_start(&slow).is_err());
+        assert!(queues.try_start(&fast).is_ok());
+
+        drop(permit);
+        assert!(queues.try_start(&slow).is_ok());
+    }
+
+    #[test]
+    fn reports_backlog_once() {
+        let queues = DeliverQueues::new(1, MemoryCollector::new());
+        let inbox: IriString = "https://slow.example/inbox".parse().unwrap();
+
+        for _ in 0..4 {
+            queues.enqueued(&inbox);
+        }
+
+        assert_eq!(queues.check_backlog(4), Some(4));
+        assert_eq!(queues.check_backlog(4), None);
+    }
+
+    #[test]
+    fn caps_reported_domains() {
+        let queues = Arc::new(DeliverQueues::new(1, MemoryCollector::new()));
+
+        for i in 0..MAX_REPORTED_DOMAINS + 10 {
+            let inbox: IriString = format!("https://{i}.example/inbox").parse().unwrap();
+            queues.enqueued(&inbox);
+        }
+        assert_eq!(
+            queues.reported.load(Ordering::Acquire),
+            MAX_REPORTED_DOMAINS
+        );
+
+        let first: IriString = "https://0.example/inbox".parse().unwrap();
+        drop(queues.try_start(&first).unwrap());
+        assert_eq!(
+            queues.reported.load(Ordering::Acquire),
+            MAX_REPORTED_DOMAINS - 1
+        );
+    }
+
+    #[test]
+    fn restores_backlog() {
+        let queues = DeliverQueues::new(1, MemoryCollector::new());
+        queues.restore(vec![(String::from("slow.example"), 3)]);
+
+        assert_eq!(queues.backlog(), vec![(String::from("slow.example"), 3)]);
+        assert_eq!(queues.queued(), 3);
+    }
+}
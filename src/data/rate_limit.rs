@@ -0,0 +1,273 @@
+use crate::error::{Error, ErrorKind};
+use lru::LruCache;
+use std::{
+    net::{IpAddr, Ipv6Addr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// Past this many senders, the least recently seen one's bucket is forgotten
+const MAX_TRACKED: usize = 1024 * 8;
+
+/// The rate limits applied to inbox requests, each disabled when None
+#[derive(Debug)]
+pub(crate) struct InboxLimits {
+    /// Keyed by the verified sender's domain
+    pub(crate) domains: Option<RateLimiter>,
+
+    /// Keyed by the client's address
+    pub(crate) addresses: Option<RateLimiter>,
+}
+
+/// Token buckets keyed by sender
+///
+/// Each sender may make `burst` requests at once, and after that `rate` requests per second.
+pub(crate) struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<LruCache<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("rate", &self.rate)
+            .field("burst", &self.burst)
+            .finish()
+    }
+}
+
+impl RateLimiter {
+    /// Build a limiter, or None when `rate` is 0 and limiting is disabled
+    pub(crate) fn new(rate: u32, burst: u32) -> Option<Self> {
+        if rate == 0 {
+            return None;
+        }
+
+        Some(RateLimiter {
+            rate: f64::from(rate),
+            burst: f64::from(burst.max(1)),
+            buckets: Mutex::new(LruCache::new(MAX_TRACKED.try_into().expect("nonzero"))),
+        })
+    }
+
+    /// Take a token for the sender, or return how long until one is available
+    pub(crate) fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let bucket = buckets.get_or_insert_mut_ref(key, || Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+
+        if self.refill(bucket, now) >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+        bucket.tokens
+    }
+}
+
+/// The key a client address is rate limited by
+///
+/// IPv6 clients are limited by their /64, since a single host is usually handed a whole one.
+pub(crate) fn client_key(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => {
+            let prefix = u128::from(ip) & (u128::MAX << 64);
+            format!("{}/64", Ipv6Addr::from(prefix))
+        }
+    }
+}
+
+/// Round a wait up to the whole seconds `Retry-After` is given in
+pub(crate) fn retry_after(wait: Duration) -> u64 {
+    wait.as_secs() + u64::from(wait.subsec_nanos() > 0)
+}
+
+/// Reverse proxies whose `X-Forwarded-For` header is trusted to name the client
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TrustedProxies(Vec<IpRange>);
+
+#[derive(Clone, Debug)]
+struct IpRange {
+    addr: IpAddr,
+    prefix: u32,
+}
+
+impl TrustedProxies {
+    pub(crate) fn new(ranges: &[String]) -> Result<Self, Error> {
+        ranges
+            .iter()
+            .map(|range| range.parse())
+            .collect::<Result<_, _>>()
+            .map(TrustedProxies)
+    }
+
+    fn trusts(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|range| range.contains(ip))
+    }
+
+    /// The address of the client behind any trusted proxies
+    ///
+    /// `X-Forwarded-For` is read from the right, skipping the trusted proxies each hop added,
+    /// since anything further left was written by the client and can't be trusted.
+    pub(crate) fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        if !self.trusts(peer) {
+            return peer;
+        }
+
+        let mut client = peer;
+
+        for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+            let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+
+            client = ip;
+
+            if !self.trusts(ip) {
+                break;
+            }
+        }
+
+        client
+    }
+}
+
+impl IpRange {
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(range), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                u32::from(range) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(range), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                u128::from(range) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::str::FromStr for IpRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || ErrorKind::TrustedProxy(s.to_owned());
+
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse().map_err(|_| invalid())?,
+            None => max,
+        };
+
+        if prefix > max {
+            return Err(invalid().into());
+        }
+
+        Ok(IpRange { addr, prefix })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{client_key, RateLimiter, TrustedProxies, MAX_TRACKED};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn refills_buckets() {
+        let limiter = RateLimiter::new(2, 3).unwrap();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("example.com", start).is_ok());
+        }
+
+        let wait = limiter.check_at("example.com", start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+        assert!(limiter.check_at("other.example", start).is_ok());
+
+        assert!(limiter
+            .check_at("example.com", start + Duration::from_millis(500))
+            .is_ok());
+        assert!(limiter
+            .check_at("example.com", start + Duration::from_millis(500))
+            .is_err());
+
+        assert!(RateLimiter::new(0, 3).is_none());
+    }
+
+    #[test]
+    fn forgets_least_recent_senders() {
+        let limiter = RateLimiter::new(1, 1).unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check_at("first.example", start).is_ok());
+        assert!(limiter.check_at("first.example", start).is_err());
+
+        for i in 0..MAX_TRACKED {
+            let _ = limiter.check_at(&format!("{i}.example"), start);
+        }
+
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_TRACKED);
+        assert!(limiter.check_at("first.example", start).is_ok());
+    }
+
+    #[test]
+    fn keys_ipv6_clients_by_prefix() {
+        let key = |ip: &str| client_key(ip.parse().unwrap());
+
+        assert_eq!(key("203.0.113.7"), "203.0.113.7");
+        assert_eq!(key("2001:db8:1:2:3:4:5:6"), "2001:db8:1:2::/64");
+        assert_eq!(key("2001:db8:1:2::ffff"), key("2001:db8:1:2:3:4:5:6"));
+        assert_ne!(key("2001:db8:1:3::1"), key("2001:db8:1:2::1"));
+    }
+
+    #[test]
+    fn finds_client_behind_trusted_proxies() {
+        let proxies =
+            TrustedProxies::new(&["127.0.0.1".to_owned(), "10.0.0.0/8".to_owned()]).unwrap();
+
+        let localhost = "127.0.0.1".parse().unwrap();
+        let stranger = "203.0.113.7".parse().unwrap();
+
+        assert_eq!(
+            proxies.client_ip(localhost, Some("198.51.100.1, 203.0.113.9, 10.1.2.3")),
+            "203.0.113.9".parse::<std::net::IpAddr>().unwrap()
+        );
+        assert_eq!(proxies.client_ip(stranger, Some("198.51.100.1")), stranger);
+        assert_eq!(proxies.client_ip(localhost, None), localhost);
+        assert_eq!(proxies.client_ip(localhost, Some("garbage")), localhost);
+
+        assert!(TrustedProxies::new(&["10.0.0.0/33".to_owned()]).is_err());
+    }
+}
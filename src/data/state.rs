@@ -1,8 +1,9 @@
 use crate::{
+    collector::MemoryCollector,
     data::NodeCache,
-    db::Db,
+    db::{Db, RelayedObject, RetiredKey},
     error::Error,
-    requests::{Breakers, Requests},
+    requests::{BreakerSchedule, Breakers, Requests, SignatureSchemes},
     spawner::Spawner,
 };
 use activitystreams::iri_string::types::IriString;
@@ -10,19 +11,26 @@ use actix_web::web;
 use lru::LruCache;
 use rand::thread_rng;
 use reqwest_middleware::ClientWithMiddleware;
-use rsa::{RsaPrivateKey, RsaPublicKey};
-use std::sync::{Arc, RwLock};
+use rsa::{pkcs8::EncodePublicKey, RsaPrivateKey};
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use time::OffsetDateTime;
 
-use super::LastOnline;
+use super::{ContentFilters, DeliverQueues, InboxLimits, LastOnline};
 
 #[derive(Clone)]
 pub struct State {
     pub(crate) requests: Requests,
-    pub(crate) public_key: RsaPublicKey,
     object_cache: Arc<RwLock<LruCache<IriString, IriString>>>,
     pub(crate) node_cache: NodeCache,
-    breakers: Breakers,
+    pub(crate) breakers: Breakers,
+    pub(crate) signature_schemes: SignatureSchemes,
     pub(crate) last_online: Arc<LastOnline>,
+    pub(crate) deliver_queues: Arc<DeliverQueues>,
+    pub(crate) inbox_limits: Arc<InboxLimits>,
+    content_filters: Arc<RwLock<Arc<ContentFilters>>>,
     pub(crate) db: Db,
 }
 
@@ -31,12 +39,29 @@ impl std::fmt::Debug for State {
         f.debug_struct("State")
             .field("node_cache", &self.node_cache)
             .field("breakers", &self.breakers)
+            .field("signature_schemes", &self.signature_schemes)
+            .field("deliver_queues", &self.deliver_queues)
+            .field("inbox_limits", &self.inbox_limits)
             .field("db", &self.db)
             .finish()
     }
 }
 
 impl State {
+    /// The content filters relayed posts are checked against
+    pub(crate) fn content_filters(&self) -> Arc<ContentFilters> {
+        self.content_filters.read().unwrap().clone()
+    }
+
+    /// Rebuild the content filters from the database, after they've been changed
+    pub(crate) async fn reload_content_filters(&self) -> Result<(), Error> {
+        let filters = ContentFilters::new(self.db.content_filters().await?);
+
+        *self.content_filters.write().unwrap() = Arc::new(filters);
+
+        Ok(())
+    }
+
     #[tracing::instrument(
         level = "debug",
         name = "Get inboxes for other domains",
@@ -68,69 +93,155 @@ impl State {
             .collect())
     }
 
-    pub(crate) fn is_cached(&self, object_id: &IriString) -> bool {
-        self.object_cache.read().unwrap().contains(object_id)
+    pub(crate) async fn is_cached(&self, object_id: &IriString) -> Result<bool, Error> {
+        if self.object_cache.read().unwrap().contains(object_id) {
+            return Ok(true);
+        }
+
+        Ok(self.db.relayed_object(object_id.clone()).await?.is_some())
+    }
+
+    /// Remember the Announce sent for an object, both in memory and in the db
+    pub(crate) async fn cache(
+        &self,
+        object_id: IriString,
+        activity_id: IriString,
+        actor_id: IriString,
+    ) -> Result<(), Error> {
+        {
+            let mut guard = self.object_cache.write().unwrap();
+            guard.put(object_id.clone(), activity_id.clone());
+            metrics::gauge!("relay.object-cache.size")
+                .set(crate::collector::recordable(guard.len()));
+        }
+
+        self.db
+            .save_relayed_object(
+                object_id,
+                RelayedObject {
+                    activity_id,
+                    actor_id,
+                    announced_at: OffsetDateTime::now_utc(),
+                },
+            )
+            .await
+    }
+
+    /// Forget an object, so it can be relayed again
+    pub(crate) async fn uncache(&self, object_id: &IriString) -> Result<(), Error> {
+        self.object_cache.write().unwrap().pop(object_id);
+
+        self.db.remove_relayed_object(object_id.clone()).await
+    }
+
+    /// Sign with a freshly generated key from now on, keeping the old public key servable until
+    /// `grace` has passed
+    pub(crate) async fn rotate_key(&self, key_id: IriString, grace: Duration) -> Result<(), Error> {
+        let private_key = generate_key().await?;
+        let (old_key_id, old_public_key) = self.requests.public_key();
+        let now = OffsetDateTime::now_utc();
+
+        let retired = RetiredKey {
+            key_id: old_key_id.parse()?,
+            public_key_pem: old_public_key.to_public_key_pem(rsa::pkcs8::LineEnding::default())?,
+            retired_at: now,
+        };
+
+        self.db
+            .rotate_private_key(&private_key, key_id.clone(), retired, now - grace)
+            .await?;
+
+        self.requests.rotate_key(key_id.to_string(), private_key);
+
+        Ok(())
     }
 
-    pub(crate) fn cache(&self, object_id: IriString, actor_id: IriString) {
-        let mut guard = self.object_cache.write().unwrap();
-        guard.put(object_id, actor_id);
-        metrics::gauge!("relay.object-cache.size").set(crate::collector::recordable(guard.len()));
+    /// Keys retired recently enough that signatures made with them should still verify
+    pub(crate) async fn retired_keys(&self, grace: Duration) -> Result<Vec<RetiredKey>, Error> {
+        let cutoff = OffsetDateTime::now_utc() - grace;
+
+        Ok(self
+            .db
+            .retired_keys()
+            .await?
+            .into_iter()
+            .filter(|retired| retired.retired_at >= cutoff)
+            .collect())
     }
 
     pub(crate) fn is_connected(&self, iri: &IriString) -> bool {
         self.breakers.should_try(iri)
     }
 
+    #[allow(clippy::too_many_arguments)]
     #[tracing::instrument(level = "debug", name = "Building state", skip_all)]
     pub(crate) async fn build(
         db: Db,
         key_id: String,
         spawner: Spawner,
         client: ClientWithMiddleware,
+        deliver_domain_concurrency: usize,
+        collector: MemoryCollector,
+        inbox_limits: InboxLimits,
+        breaker_schedule: BreakerSchedule,
     ) -> Result<Self, Error> {
         let private_key = if let Ok(Some(key)) = db.private_key().await {
             tracing::debug!("Using existing key");
             key
         } else {
             tracing::info!("Generating new keys");
-            let key = web::block(move || {
-                let mut rng = thread_rng();
-                RsaPrivateKey::new(&mut rng, 4096)
-            })
-            .await??;
+            let key = generate_key().await?;
 
             db.update_private_key(&key).await?;
 
             key
         };
 
-        let public_key = private_key.to_public_key();
+        let key_id = match db.key_id().await? {
+            Some(key_id) => key_id.to_string(),
+            None => key_id,
+        };
 
-        let breakers = Breakers::default();
+        let breakers = Breakers::load(&db, breaker_schedule).await?;
+        let signature_schemes = SignatureSchemes::load(&db).await?;
         let last_online = Arc::new(LastOnline::empty());
+        let content_filters = ContentFilters::new(db.content_filters().await?);
 
         let requests = Requests::new(
             key_id,
             private_key,
             breakers.clone(),
             last_online.clone(),
+            signature_schemes.clone(),
             spawner,
             client,
         );
 
         let state = State {
             requests,
-            public_key,
             object_cache: Arc::new(RwLock::new(LruCache::new(
                 (1024 * 8).try_into().expect("nonzero"),
             ))),
             node_cache: NodeCache::new(db.clone()),
             breakers,
+            signature_schemes,
             db,
             last_online,
+            deliver_queues: Arc::new(DeliverQueues::new(deliver_domain_concurrency, collector)),
+            inbox_limits: Arc::new(inbox_limits),
+            content_filters: Arc::new(RwLock::new(Arc::new(content_filters))),
         };
 
         Ok(state)
     }
 }
+
+async fn generate_key() -> Result<RsaPrivateKey, Error> {
+    let key = web::block(move || {
+        let mut rng = thread_rng();
+        RsaPrivateKey::new(&mut rng, 4096)
+    })
+    .await??;
+
+    Ok(key)
+}
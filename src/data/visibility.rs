@@ -0,0 +1,100 @@
+use crate::error::{Error, ErrorKind};
+use serde_json::Value;
+
+const PUBLIC: [&str; 3] = [
+    "https://www.w3.org/ns/activitystreams#Public",
+    "as:Public",
+    "Public",
+];
+
+/// How widely a post is addressed, from most to least visible
+///
+/// Posts that aren't addressed to the public collection at all, such as followers-only posts and
+/// direct messages, have no visibility the relay will consider.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Visibility {
+    /// Addressed to the public collection in `to`
+    Public,
+
+    /// Addressed to the public collection only in `cc`
+    Unlisted,
+}
+
+impl Visibility {
+    /// Read the visibility from an activity or object's `to` and `cc`
+    pub(crate) fn of(value: &Value) -> Option<Self> {
+        if addresses_public(value.get("to")) {
+            Some(Self::Public)
+        } else if addresses_public(value.get("cc")) {
+            Some(Self::Unlisted)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a post of the given visibility may be relayed when this is the widest allowed
+    pub(crate) fn allows(&self, visibility: Option<Visibility>) -> bool {
+        visibility.is_some_and(|visibility| visibility <= *self)
+    }
+}
+
+impl std::str::FromStr for Visibility {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "public" => Ok(Self::Public),
+            "unlisted" => Ok(Self::Unlisted),
+            other => Err(ErrorKind::Visibility(other.to_owned()).into()),
+        }
+    }
+}
+
+fn addresses_public(recipients: Option<&Value>) -> bool {
+    let recipients = match recipients {
+        Some(Value::Array(recipients)) => recipients.iter().collect(),
+        Some(recipient) => vec![recipient],
+        None => Vec::new(),
+    };
+
+    recipients
+        .into_iter()
+        .filter_map(|recipient| match recipient {
+            Value::String(id) => Some(id.as_str()),
+            recipient => recipient.get("id").and_then(Value::as_str),
+        })
+        .any(|id| PUBLIC.contains(&id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Visibility;
+
+    #[test]
+    fn reads_visibility_from_addressing() {
+        let public = serde_json::json!({
+            "to": ["https://www.w3.org/ns/activitystreams#Public"],
+            "cc": ["https://example.com/users/alice/followers"],
+        });
+        let unlisted = serde_json::json!({
+            "to": "https://example.com/users/alice/followers",
+            "cc": "as:Public",
+        });
+        let followers = serde_json::json!({
+            "to": ["https://example.com/users/alice/followers"],
+        });
+
+        assert_eq!(Visibility::of(&public), Some(Visibility::Public));
+        assert_eq!(Visibility::of(&unlisted), Some(Visibility::Unlisted));
+        assert_eq!(Visibility::of(&followers), None);
+    }
+
+    #[test]
+    fn unlisted_allows_public() {
+        assert!(Visibility::Public.allows(Some(Visibility::Public)));
+        assert!(!Visibility::Public.allows(Some(Visibility::Unlisted)));
+        assert!(Visibility::Unlisted.allows(Some(Visibility::Public)));
+        assert!(Visibility::Unlisted.allows(Some(Visibility::Unlisted)));
+        assert!(!Visibility::Unlisted.allows(None));
+    }
+}
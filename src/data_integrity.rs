@@ -0,0 +1,245 @@
+//! Verification of FEP-8b32 object integrity proofs made with the `eddsa-jcs-2022` cryptosuite
+use crate::{
+    error::{Error, ErrorKind},
+    keys::VerifyingKey,
+    rfc9421::{MAX_AGE, MAX_SKEW},
+};
+use ring::digest::{digest, SHA256};
+use serde_json::Value;
+use std::time::SystemTime;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+const CRYPTOSUITE: &str = "eddsa-jcs-2022";
+
+/// A Data Integrity proof embedded in an activity
+#[derive(Debug)]
+pub(crate) struct Proof<'a>(&'a serde_json::Map<String, Value>);
+
+impl<'a> Proof<'a> {
+    /// Find the first `eddsa-jcs-2022` proof on a document
+    ///
+    /// Proofs in other cryptosuites are ignored, since the relay can't check them.
+    pub(crate) fn find(document: &'a Value) -> Option<Self> {
+        let proofs = match document.get("proof")? {
+            Value::Array(proofs) => proofs.iter().collect(),
+            proof => vec![proof],
+        };
+
+        proofs
+            .into_iter()
+            .filter_map(Value::as_object)
+            .find(|proof| {
+                proof.get("type").and_then(Value::as_str) == Some("DataIntegrityProof")
+                    && proof.get("cryptosuite").and_then(Value::as_str) == Some(CRYPTOSUITE)
+            })
+            .map(Proof)
+    }
+
+    /// The id of the key the proof claims to be made with
+    pub(crate) fn verification_method(&self) -> Option<&'a str> {
+        self.0.get("verificationMethod").and_then(Value::as_str)
+    }
+
+    /// Reject proofs created too long ago or in the future, so old activities can't be replayed
+    ///
+    /// The window is the same one RFC 9421 message signatures are held to.
+    pub(crate) fn check_created(&self, now: SystemTime) -> Result<(), Error> {
+        let created = self
+            .0
+            .get("created")
+            .and_then(Value::as_str)
+            .ok_or(ErrorKind::Proof("missing created time"))?;
+        let created = OffsetDateTime::parse(created, &Rfc3339)
+            .map_err(|_| ErrorKind::Proof("invalid created time"))?;
+        let now = OffsetDateTime::from(now);
+
+        if created > now + MAX_SKEW {
+            return Err(ErrorKind::Proof("created in the future").into());
+        }
+
+        if created + MAX_AGE < now {
+            return Err(ErrorKind::Proof("expired").into());
+        }
+
+        Ok(())
+    }
+
+    /// Check the proof over the document it was found on
+    pub(crate) fn verify(&self, document: &Value, key: &VerifyingKey) -> Result<(), Error> {
+        if self.0.get("proofPurpose").and_then(Value::as_str) != Some("assertionMethod") {
+            return Err(ErrorKind::Proof("unsupported proof purpose").into());
+        }
+
+        let signature = self
+            .0
+            .get("proofValue")
+            .and_then(Value::as_str)
+            .and_then(|value| value.strip_prefix('z'))
+            .and_then(|value| bs58::decode(value).into_vec().ok())
+            .ok_or(ErrorKind::Proof("malformed proof value"))?;
+
+        let mut options = self.0.clone();
+        options.remove("proofValue");
+
+        let Value::Object(mut unsecured) = document.clone() else {
+            return Err(ErrorKind::Proof("document isn't an object").into());
+        };
+        unsecured.remove("proof");
+
+        if let Some(context) = options.get("@context") {
+            let document_context = as_list(unsecured.get("@context"));
+            let proof_context = as_list(Some(context));
+
+            if !document_context.starts_with(&proof_context) {
+                return Err(ErrorKind::Proof("mismatched context").into());
+            }
+
+            unsecured.insert("@context".to_owned(), context.clone());
+        }
+
+        let hash_data = hash_data(&Value::Object(options), &Value::Object(unsecured));
+
+        key.verify(Some("ed25519"), &hash_data, &signature)
+            .map_err(|_| ErrorKind::Proof("signature does not match").into())
+    }
+}
+
+fn as_list(value: Option<&Value>) -> Vec<Value> {
+    match value {
+        Some(Value::Array(values)) => values.clone(),
+        Some(value) => vec![value.clone()],
+        None => Vec::new(),
+    }
+}
+
+/// The hashes of the proof options and the document, which together are what gets signed
+fn hash_data(options: &Value, unsecured: &Value) -> Vec<u8> {
+    let mut hash_data = digest(&SHA256, canonicalize(options).as_bytes())
+        .as_ref()
+        .to_vec();
+    hash_data.extend_from_slice(digest(&SHA256, canonicalize(unsecured).as_bytes()).as_ref());
+    hash_data
+}
+
+/// Serialize JSON following the JSON Canonicalization Scheme of RFC 8785
+fn canonicalize(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Array(values) => {
+            out.push('[');
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_canonical(value, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            // Keys are ordered by their UTF-16 code units rather than their UTF-8 bytes
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push('{');
+            for (index, (key, value)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        Value::Number(number) => match number.as_f64() {
+            // Integral floats are written like integers, as ECMAScript would
+            Some(float) if number.is_f64() && float.fract() == 0.0 && float.abs() < 1e21 => {
+                out.push_str(&format!("{float:.0}"))
+            }
+            _ => out.push_str(&number.to_string()),
+        },
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonicalize, hash_data, Proof};
+    use crate::keys::VerifyingKey;
+    use ring::{
+        rand::SystemRandom,
+        signature::{Ed25519KeyPair, KeyPair},
+    };
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn canonicalizes_json() {
+        let value: serde_json::Value = serde_json::from_str(
+            r#"{"b": 1.0, "a": [true, null, "\u20ac\n"], "\ufb01": 3, "\ud83d\ude00": 1, "": 2, "c": {"z": -0.5}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            canonicalize(&value),
+            "{\"\":2,\"a\":[true,null,\"\u{20ac}\\n\"],\"b\":1,\"c\":{\"z\":-0.5},\"\u{1f600}\":1,\"\u{fb01}\":3}"
+        );
+    }
+
+    #[test]
+    fn verifies_proofs() {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let key = VerifyingKey::Ed25519(pair.public_key().as_ref().try_into().unwrap());
+
+        let unsecured = serde_json::json!({
+            "@context": ["https://www.w3.org/ns/activitystreams"],
+            "id": "https://example.com/activities/1",
+            "type": "Delete",
+            "actor": "https://example.com/users/alice",
+            "object": "https://example.com/objects/1",
+        });
+        let mut options = serde_json::json!({
+            "@context": ["https://www.w3.org/ns/activitystreams"],
+            "type": "DataIntegrityProof",
+            "cryptosuite": "eddsa-jcs-2022",
+            "verificationMethod": "https://example.com/users/alice#ed25519-key",
+            "proofPurpose": "assertionMethod",
+            "created": "2024-01-01T00:00:00Z",
+        });
+
+        let signature = pair.sign(&hash_data(&options, &unsecured));
+        options["proofValue"] = format!("z{}", bs58::encode(signature).into_string()).into();
+
+        let mut document = unsecured.clone();
+        document["proof"] = options;
+
+        let proof = Proof::find(&document).unwrap();
+        assert_eq!(
+            proof.verification_method(),
+            Some("https://example.com/users/alice#ed25519-key")
+        );
+        assert!(proof.verify(&document, &key).is_ok());
+
+        let mut forged = document.clone();
+        forged["object"] = "https://example.com/objects/2".into();
+        assert!(Proof::find(&forged).unwrap().verify(&forged, &key).is_err());
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_067_200);
+        assert!(proof.check_created(now).is_ok());
+        assert!(proof
+            .check_created(now + Duration::from_secs(2 * 60 * 60))
+            .is_err());
+        assert!(proof
+            .check_created(now - Duration::from_secs(10 * 60))
+            .is_err());
+
+        let mut other_suite = document.clone();
+        other_suite["proof"]["cryptosuite"] = "eddsa-rdfc-2022".into();
+        assert!(Proof::find(&other_suite).is_none());
+    }
+}
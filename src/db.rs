@@ -1,17 +1,20 @@
 use crate::{
+    apub::AcceptedActivities,
     config::Config,
+    data::ContentFilter,
     error::{Error, ErrorKind},
+    requests::{Breaker, Negotiated},
 };
-use activitystreams::iri_string::types::IriString;
+use activitystreams::{base::BaseExt, iri_string::types::IriString};
 use rsa::{
     pkcs8::{DecodePrivateKey, EncodePrivateKey},
     RsaPrivateKey,
 };
 use sled::{transaction::TransactionError, Batch, Transactional, Tree};
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     sync::{
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
     time::SystemTime,
@@ -19,6 +22,8 @@ use std::{
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+const ANNOUNCE_LOG_LIMIT: usize = 1024;
+
 #[derive(Clone, Debug)]
 pub(crate) struct Db {
     inner: Arc<Inner>,
@@ -32,6 +37,7 @@ struct Inner {
     connected_actor_ids: Tree,
     allowed_domains: Tree,
     blocked_domains: Tree,
+    blocked_actors: Tree,
     settings: Tree,
     media_url_media_id: Tree,
     media_id_media_url: Tree,
@@ -39,6 +45,18 @@ struct Inner {
     actor_id_instance: Tree,
     actor_id_contact: Tree,
     last_seen: Tree,
+    announces: Tree,
+    announce_count: AtomicUsize,
+    relayed_objects: Tree,
+    breakers: Tree,
+    signature_schemes: Tree,
+    content_filters: Tree,
+    pending_follows: Tree,
+    admin_tokens: Tree,
+    admin_sessions: Tree,
+    audit_log: Tree,
+    deliver_backlog: Tree,
+    job_storage: background_jobs::sled::Storage,
     restricted_mode: bool,
 }
 
@@ -57,6 +75,39 @@ pub struct Actor {
     pub(crate) public_key_id: IriString,
     pub(crate) inbox: IriString,
     pub(crate) saved_at: SystemTime,
+    /// Whether the actor has asked not to have their posts amplified
+    #[serde(default)]
+    pub(crate) opted_out: bool,
+    /// Ed25519 keys the actor lists in its `assertionMethod`
+    #[serde(default)]
+    pub(crate) assertion_keys: Vec<AssertionKey>,
+}
+
+impl Actor {
+    /// Whether the key id names one of this actor's keys
+    pub(crate) fn owns_key(&self, key_id: &str) -> bool {
+        self.public_key_id.as_str() == key_id
+            || self
+                .assertion_keys
+                .iter()
+                .any(|key| key.id.as_str() == key_id)
+    }
+}
+
+/// A `Multikey` an actor uses to sign messages and objects
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub(crate) struct AssertionKey {
+    pub(crate) id: IriString,
+    pub(crate) public_key_multibase: String,
+}
+
+impl std::fmt::Debug for AssertionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AssertionKey")
+            .field("id", &self.id.to_string())
+            .field("public_key_multibase", &self.public_key_multibase)
+            .finish()
+    }
 }
 
 impl std::fmt::Debug for Actor {
@@ -67,6 +118,268 @@ impl std::fmt::Debug for Actor {
             .field("public_key_id", &self.public_key_id.to_string())
             .field("inbox", &self.inbox.to_string())
             .field("saved_at", &self.saved_at)
+            .field("opted_out", &self.opted_out)
+            .field("assertion_keys", &self.assertion_keys)
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BlockSeverity {
+    /// Refuse all contact with the domain
+    #[default]
+    Suspend,
+
+    /// Let the domain stay subscribed, but don't relay its posts
+    Silence,
+}
+
+impl BlockSeverity {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Suspend => "suspend",
+            Self::Silence => "silence",
+        }
+    }
+}
+
+impl std::str::FromStr for BlockSeverity {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "suspend" => Ok(Self::Suspend),
+            "silence" => Ok(Self::Silence),
+            other => Err(ErrorKind::Severity(other.to_owned())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct DomainBlock {
+    pub(crate) domain: String,
+    #[serde(default)]
+    pub(crate) severity: BlockSeverity,
+    #[serde(default)]
+    pub(crate) reason: Option<String>,
+    #[serde(default)]
+    pub(crate) created_by: Option<String>,
+    #[serde(default)]
+    pub(crate) created_at: Option<OffsetDateTime>,
+    /// The remote blocklist this block was synced from, or None for manual blocks
+    #[serde(default)]
+    pub(crate) source: Option<String>,
+}
+
+impl DomainBlock {
+    pub(crate) fn new(
+        domain: String,
+        severity: BlockSeverity,
+        reason: Option<String>,
+        created_by: Option<String>,
+    ) -> Self {
+        DomainBlock {
+            domain,
+            severity,
+            reason: reason.filter(|reason| !reason.is_empty()),
+            created_by,
+            created_at: Some(OffsetDateTime::now_utc()),
+            source: None,
+        }
+    }
+
+    // Blocks used to be stored as bare domain names
+    fn from_ivec(ivec: &[u8]) -> Self {
+        serde_json::from_slice(ivec).unwrap_or_else(|_| DomainBlock {
+            domain: String::from_utf8_lossy(ivec).to_string(),
+            severity: BlockSeverity::Suspend,
+            reason: None,
+            created_by: None,
+            created_at: None,
+            source: None,
+        })
+    }
+}
+
+/// What an admin API token is allowed to do
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TokenScope {
+    /// Read stats, last-seen times, connected actors and breaker state
+    Stats,
+
+    /// Manage allowed domains and follow requests
+    Allows,
+
+    /// Manage domain blocks, actor blocks and content filters
+    Blocks,
+
+    /// Everything, including managing other tokens
+    Admin,
+}
+
+impl TokenScope {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stats => "stats",
+            Self::Allows => "allows",
+            Self::Blocks => "blocks",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for TokenScope {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "stats" => Ok(Self::Stats),
+            "allows" => Ok(Self::Allows),
+            "blocks" => Ok(Self::Blocks),
+            "admin" => Ok(Self::Admin),
+            other => Err(ErrorKind::TokenScope(other.to_owned())),
+        }
+    }
+}
+
+/// A named admin API token. Only a hash of the token's secret is kept
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct AdminToken {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) scopes: BTreeSet<TokenScope>,
+    pub(crate) hashed_secret: String,
+    pub(crate) created_at: OffsetDateTime,
+    pub(crate) last_used: Option<OffsetDateTime>,
+}
+
+/// A login to the admin dashboard, which the browser refers to by its id alone
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct AdminSession {
+    pub(crate) id: String,
+    /// The token that was logged in with, or `None` for the configured API token
+    pub(crate) token_id: Option<String>,
+    pub(crate) name: String,
+    pub(crate) scopes: BTreeSet<TokenScope>,
+    pub(crate) expires_at: OffsetDateTime,
+}
+
+/// What was done in an [`AuditEntry`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AuditAction {
+    Block,
+    Unblock,
+    ImportBlocks,
+    SyncBlocks,
+    Allow,
+    Disallow,
+    BlockActor,
+    UnblockActor,
+    Filter,
+    Unfilter,
+    ApproveFollow,
+    RejectFollow,
+    Disconnect,
+    ResetBreakers,
+    RotateKey,
+    CreateToken,
+    RevokeTokens,
+}
+
+impl AuditAction {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::Unblock => "unblock",
+            Self::ImportBlocks => "import_blocks",
+            Self::SyncBlocks => "sync_blocks",
+            Self::Allow => "allow",
+            Self::Disallow => "disallow",
+            Self::BlockActor => "block_actor",
+            Self::UnblockActor => "unblock_actor",
+            Self::Filter => "filter",
+            Self::Unfilter => "unfilter",
+            Self::ApproveFollow => "approve_follow",
+            Self::RejectFollow => "reject_follow",
+            Self::Disconnect => "disconnect",
+            Self::ResetBreakers => "reset_breakers",
+            Self::RotateKey => "rotate_key",
+            Self::CreateToken => "create_token",
+            Self::RevokeTokens => "revoke_tokens",
+        }
+    }
+}
+
+/// A record of who changed what, kept in the append-only audit log
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct AuditEntry {
+    /// A UUIDv7, so entries sort by when they were recorded
+    pub(crate) id: Uuid,
+    pub(crate) actor: String,
+    pub(crate) action: AuditAction,
+    pub(crate) targets: Vec<String>,
+    #[serde(default)]
+    pub(crate) reason: Option<String>,
+    pub(crate) at: OffsetDateTime,
+}
+
+impl AuditEntry {
+    pub(crate) fn new(
+        actor: String,
+        action: AuditAction,
+        targets: Vec<String>,
+        reason: Option<String>,
+    ) -> Self {
+        AuditEntry {
+            id: Uuid::now_v7(),
+            actor,
+            action,
+            targets,
+            reason: reason.filter(|reason| !reason.is_empty()),
+            at: OffsetDateTime::now_utc(),
+        }
+    }
+}
+
+/// The Announce the relay sent for an object, kept so it can be undone if the object goes away
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct RelayedObject {
+    pub(crate) activity_id: IriString,
+    /// The actor whose activity the relay announced
+    pub(crate) actor_id: IriString,
+    pub(crate) announced_at: OffsetDateTime,
+}
+
+/// The public half of a key the relay signed with before its last rotation
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct RetiredKey {
+    pub(crate) key_id: IriString,
+    pub(crate) public_key_pem: String,
+    pub(crate) retired_at: OffsetDateTime,
+}
+
+/// A follow request waiting on an admin's decision
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub(crate) struct PendingFollow {
+    /// A short handle for the request, small enough to fit in a telegram button
+    pub(crate) id: Uuid,
+    pub(crate) input: AcceptedActivities,
+    pub(crate) actor: Actor,
+    pub(crate) requested_at: OffsetDateTime,
+}
+
+impl std::fmt::Debug for PendingFollow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PendingFollow")
+            .field("id", &self.id)
+            .field("input", &self.input.id_unchecked())
+            .field("actor", &self.actor.id)
+            .field("requested_at", &self.requested_at)
             .finish()
     }
 }
@@ -136,12 +449,32 @@ impl Inner {
             })
     }
 
-    fn blocks(&self) -> impl DoubleEndedIterator<Item = String> {
+    fn blocks(&self) -> impl DoubleEndedIterator<Item = DomainBlock> {
         self.blocked_domains
             .iter()
             .values()
             .filter_map(|res| res.ok())
-            .map(|s| String::from_utf8_lossy(&s).to_string())
+            .map(|ivec| DomainBlock::from_ivec(&ivec))
+    }
+
+    fn is_blocked_with(&self, authority: &str, severity: BlockSeverity) -> bool {
+        let reverse_domain = domain_key(authority);
+
+        self.blocked_domains
+            .scan_prefix(domain_prefix(authority))
+            .filter_map(|res| res.ok())
+            .any(|(rdnn, block)| {
+                reverse_domain.starts_with(String::from_utf8_lossy(&rdnn).as_ref())
+                    && DomainBlock::from_ivec(&block).severity == severity
+            })
+    }
+
+    fn blocked_actors(&self) -> impl DoubleEndedIterator<Item = IriString> {
+        self.blocked_actors
+            .iter()
+            .values()
+            .filter_map(|res| res.ok())
+            .filter_map(url_from_ivec)
     }
 
     fn allowed(&self) -> impl DoubleEndedIterator<Item = String> {
@@ -228,12 +561,7 @@ impl Inner {
                     reverse_domain.starts_with(rdnn_string.as_ref())
                 })
         } else {
-            !self
-                .blocked_domains
-                .scan_prefix(prefix)
-                .keys()
-                .filter_map(|res| res.ok())
-                .any(|rdnn| reverse_domain.starts_with(String::from_utf8_lossy(&rdnn).as_ref()))
+            !self.is_blocked_with(authority, BlockSeverity::Suspend)
         }
     }
 }
@@ -245,6 +573,9 @@ impl Db {
     }
 
     fn build_inner(restricted_mode: bool, db: sled::Db) -> Result<Self, Error> {
+        let announces = db.open_tree("announces")?;
+        let announce_count = AtomicUsize::new(announces.len());
+
         Ok(Db {
             inner: Arc::new(Inner {
                 healthz: db.open_tree("healthz")?,
@@ -254,6 +585,7 @@ impl Db {
                 connected_actor_ids: db.open_tree("connected-actor-ids")?,
                 allowed_domains: db.open_tree("allowed-actor-ids")?,
                 blocked_domains: db.open_tree("blocked-actor-ids")?,
+                blocked_actors: db.open_tree("blocked-actors")?,
                 settings: db.open_tree("settings")?,
                 media_url_media_id: db.open_tree("media-url-media-id")?,
                 media_id_media_url: db.open_tree("media-id-media-url")?,
@@ -261,11 +593,29 @@ impl Db {
                 actor_id_instance: db.open_tree("actor-id-instance")?,
                 actor_id_contact: db.open_tree("actor-id-contact")?,
                 last_seen: db.open_tree("last-seen")?,
+                announces,
+                announce_count,
+                relayed_objects: db.open_tree("relayed-objects")?,
+                breakers: db.open_tree("breakers")?,
+                signature_schemes: db.open_tree("signature-schemes")?,
+                content_filters: db.open_tree("content-filters")?,
+                pending_follows: db.open_tree("pending-follows")?,
+                admin_tokens: db.open_tree("admin-tokens")?,
+                admin_sessions: db.open_tree("admin-sessions")?,
+                audit_log: db.open_tree("audit-log")?,
+                deliver_backlog: db.open_tree("deliver-backlog")?,
+                job_storage: background_jobs::sled::Storage::new(db)?,
                 restricted_mode,
             }),
         })
     }
 
+    /// An empty database, deleted once it's dropped
+    #[cfg(test)]
+    pub(crate) fn temporary() -> Self {
+        Db::build_inner(true, sled::Config::new().temporary(true).open().unwrap()).unwrap()
+    }
+
     async fn unblock<T>(
         &self,
         f: impl FnOnce(&Inner) -> Result<T, Error> + Send + 'static,
@@ -280,6 +630,56 @@ impl Db {
         Ok(t)
     }
 
+    /// Replace the saved count of deliveries waiting to start for each domain
+    pub(crate) async fn save_deliver_backlog(
+        &self,
+        backlog: Vec<(String, usize)>,
+    ) -> Result<(), Error> {
+        self.unblock(move |inner| {
+            let mut batch = Batch::default();
+
+            for res in inner.deliver_backlog.iter().keys() {
+                batch.remove(res?);
+            }
+
+            for (authority, queued) in backlog {
+                batch.insert(authority.as_bytes(), &(queued as u64).to_be_bytes()[..]);
+            }
+
+            inner.deliver_backlog.apply_batch(batch)?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// The count of deliveries that were waiting to start for each domain when last saved
+    pub(crate) async fn deliver_backlog(&self) -> Result<Vec<(String, usize)>, Error> {
+        self.unblock(|inner| {
+            let mut backlog = Vec::new();
+
+            for res in inner.deliver_backlog.iter() {
+                let (authority, queued) = res?;
+
+                let (Ok(authority), Ok(queued)) = (
+                    String::from_utf8(authority.to_vec()),
+                    <[u8; 8]>::try_from(queued.as_ref()),
+                ) else {
+                    continue;
+                };
+
+                backlog.push((authority, u64::from_be_bytes(queued) as usize));
+            }
+
+            Ok(backlog)
+        })
+        .await
+    }
+
+    pub(crate) fn job_storage(&self) -> background_jobs::sled::Storage {
+        self.inner.job_storage.clone()
+    }
+
     pub(crate) async fn check_health(&self) -> Result<(), Error> {
         let next = self.inner.healthz_counter.fetch_add(1, Ordering::Relaxed);
         self.unblock(move |inner| {
@@ -342,6 +742,140 @@ impl Db {
         .await
     }
 
+    pub(crate) async fn save_breakers(
+        &self,
+        breakers: HashMap<String, Option<Breaker>>,
+    ) -> Result<(), Error> {
+        let mut batch = Batch::default();
+
+        for (authority, breaker) in breakers {
+            if let Some(breaker) = breaker {
+                batch.insert(authority.as_bytes(), serde_json::to_vec(&breaker)?);
+            } else {
+                batch.remove(authority.as_bytes());
+            }
+        }
+
+        self.unblock(move |inner| {
+            inner.breakers.apply_batch(batch)?;
+
+            metrics::gauge!("relay.db.breakers.size")
+                .set(crate::collector::recordable(inner.breakers.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub(crate) async fn breakers(&self) -> Result<HashMap<String, Breaker>, Error> {
+        self.unblock(|inner| {
+            inner
+                .breakers
+                .iter()
+                .map(|res| {
+                    let (authority, breaker) = res?;
+
+                    Ok((
+                        String::from_utf8_lossy(&authority).to_string(),
+                        serde_json::from_slice(&breaker)?,
+                    ))
+                })
+                .collect()
+        })
+        .await
+    }
+
+    pub(crate) async fn save_signature_schemes(
+        &self,
+        schemes: HashMap<String, Negotiated>,
+    ) -> Result<(), Error> {
+        let mut batch = Batch::default();
+
+        for (authority, negotiated) in schemes {
+            batch.insert(authority.as_bytes(), serde_json::to_vec(&negotiated)?);
+        }
+
+        self.unblock(move |inner| {
+            inner.signature_schemes.apply_batch(batch)?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub(crate) async fn signature_schemes(&self) -> Result<HashMap<String, Negotiated>, Error> {
+        self.unblock(|inner| {
+            inner
+                .signature_schemes
+                .iter()
+                .map(|res| {
+                    let (authority, negotiated) = res?;
+
+                    Ok((
+                        String::from_utf8_lossy(&authority).to_string(),
+                        serde_json::from_slice(&negotiated)?,
+                    ))
+                })
+                .collect()
+        })
+        .await
+    }
+
+    pub(crate) async fn add_content_filters(
+        &self,
+        filters: Vec<ContentFilter>,
+    ) -> Result<(), Error> {
+        let mut batch = Batch::default();
+
+        for filter in filters {
+            let vec = serde_json::to_vec(&filter)?;
+            batch.insert(vec.clone(), vec);
+        }
+
+        self.unblock(move |inner| {
+            inner.content_filters.apply_batch(batch)?;
+
+            metrics::gauge!("relay.db.content-filters.size")
+                .set(crate::collector::recordable(inner.content_filters.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub(crate) async fn remove_content_filters(
+        &self,
+        filters: Vec<ContentFilter>,
+    ) -> Result<(), Error> {
+        let mut batch = Batch::default();
+
+        for filter in filters {
+            batch.remove(serde_json::to_vec(&filter)?);
+        }
+
+        self.unblock(move |inner| {
+            inner.content_filters.apply_batch(batch)?;
+
+            metrics::gauge!("relay.db.content-filters.size")
+                .set(crate::collector::recordable(inner.content_filters.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub(crate) async fn content_filters(&self) -> Result<Vec<ContentFilter>, Error> {
+        self.unblock(|inner| {
+            inner
+                .content_filters
+                .iter()
+                .keys()
+                .map(|res| Ok(serde_json::from_slice(&res?)?))
+                .collect()
+        })
+        .await
+    }
+
     pub(crate) async fn connected_ids(&self) -> Result<Vec<IriString>, Error> {
         self.unblock(|inner| Ok(inner.connected().collect())).await
     }
@@ -453,52 +987,179 @@ impl Db {
             .await
     }
 
-    pub(crate) async fn save_url(&self, url: IriString, id: Uuid) -> Result<(), Error> {
+    pub(crate) async fn save_announce(&self, activity: serde_json::Value) -> Result<(), Error> {
         self.unblock(move |inner| {
-            inner
-                .media_id_media_url
-                .insert(id.as_bytes(), url.as_str().as_bytes())?;
-            inner
-                .media_url_media_id
-                .insert(url.as_str().as_bytes(), id.as_bytes())?;
+            let vec = serde_json::to_vec(&activity)?;
 
-            metrics::gauge!("relay.db.media-id-media-url.size")
-                .set(crate::collector::recordable(inner.media_id_media_url.len()));
-            metrics::gauge!("relay.db.media-url-media-id.size")
-                .set(crate::collector::recordable(inner.media_url_media_id.len()));
+            inner.announces.insert(Uuid::now_v7().as_bytes(), vec)?;
+            let mut count = inner.announce_count.fetch_add(1, Ordering::Relaxed) + 1;
+
+            while count > ANNOUNCE_LOG_LIMIT {
+                if inner.announces.pop_min()?.is_none() {
+                    break;
+                }
+                count = inner.announce_count.fetch_sub(1, Ordering::Relaxed) - 1;
+            }
+
+            metrics::gauge!("relay.db.announces.size").set(crate::collector::recordable(count));
 
             Ok(())
         })
         .await
     }
 
-    pub(crate) async fn media_id(&self, url: IriString) -> Result<Option<Uuid>, Error> {
+    pub(crate) async fn announces(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>, Error> {
         self.unblock(move |inner| {
-            Ok(inner
-                .media_url_media_id
-                .get(url.as_str().as_bytes())?
-                .and_then(uuid_from_ivec))
+            inner
+                .announces
+                .iter()
+                .values()
+                .rev()
+                .skip(offset)
+                .take(limit)
+                .map(|res| Ok(serde_json::from_slice(&res?)?))
+                .collect()
         })
         .await
     }
 
-    pub(crate) async fn media_url(&self, id: Uuid) -> Result<Option<IriString>, Error> {
+    pub(crate) async fn announce_count(&self) -> Result<usize, Error> {
+        self.unblock(|inner| Ok(inner.announce_count.load(Ordering::Relaxed)))
+            .await
+    }
+
+    pub(crate) async fn save_relayed_object(
+        &self,
+        object_id: IriString,
+        relayed: RelayedObject,
+    ) -> Result<(), Error> {
+        let vec = serde_json::to_vec(&relayed)?;
+
         self.unblock(move |inner| {
-            Ok(inner
-                .media_id_media_url
-                .get(id.as_bytes())?
-                .and_then(url_from_ivec))
+            inner
+                .relayed_objects
+                .insert(object_id.as_str().as_bytes(), vec)?;
+
+            Ok(())
         })
         .await
     }
 
-    pub(crate) async fn blocks(&self) -> Result<Vec<String>, Error> {
-        self.unblock(|inner| Ok(inner.blocks().collect())).await
+    pub(crate) async fn relayed_object(
+        &self,
+        object_id: IriString,
+    ) -> Result<Option<RelayedObject>, Error> {
+        self.unblock(move |inner| {
+            match inner.relayed_objects.get(object_id.as_str().as_bytes())? {
+                Some(ivec) => Ok(Some(serde_json::from_slice(&ivec)?)),
+                None => Ok(None),
+            }
+        })
+        .await
     }
 
-    pub(crate) async fn allows(&self) -> Result<Vec<String>, Error> {
-        self.unblock(|inner| Ok(inner.allowed().collect())).await
-    }
+    pub(crate) async fn remove_relayed_object(&self, object_id: IriString) -> Result<(), Error> {
+        self.unblock(move |inner| {
+            inner
+                .relayed_objects
+                .remove(object_id.as_str().as_bytes())?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Forget every object announced before `cutoff`, returning how many were forgotten
+    ///
+    /// Counting the tree is as slow as walking it, so its size is only reported from here.
+    pub(crate) async fn expire_relayed_objects(
+        &self,
+        cutoff: OffsetDateTime,
+    ) -> Result<usize, Error> {
+        self.unblock(move |inner| {
+            let mut expired = 0;
+            let mut remaining = 0;
+
+            for res in inner.relayed_objects.iter() {
+                let (key, value) = res?;
+                let relayed: RelayedObject = serde_json::from_slice(&value)?;
+
+                if relayed.announced_at < cutoff {
+                    inner.relayed_objects.remove(key)?;
+                    expired += 1;
+                } else {
+                    remaining += 1;
+                }
+            }
+
+            metrics::gauge!("relay.db.relayed-objects.size")
+                .set(crate::collector::recordable(remaining));
+
+            Ok(expired)
+        })
+        .await
+    }
+
+    pub(crate) async fn save_url(&self, url: IriString, id: Uuid) -> Result<(), Error> {
+        self.unblock(move |inner| {
+            inner
+                .media_id_media_url
+                .insert(id.as_bytes(), url.as_str().as_bytes())?;
+            inner
+                .media_url_media_id
+                .insert(url.as_str().as_bytes(), id.as_bytes())?;
+
+            metrics::gauge!("relay.db.media-id-media-url.size")
+                .set(crate::collector::recordable(inner.media_id_media_url.len()));
+            metrics::gauge!("relay.db.media-url-media-id.size")
+                .set(crate::collector::recordable(inner.media_url_media_id.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub(crate) async fn media_id(&self, url: IriString) -> Result<Option<Uuid>, Error> {
+        self.unblock(move |inner| {
+            Ok(inner
+                .media_url_media_id
+                .get(url.as_str().as_bytes())?
+                .and_then(uuid_from_ivec))
+        })
+        .await
+    }
+
+    pub(crate) async fn media_url(&self, id: Uuid) -> Result<Option<IriString>, Error> {
+        self.unblock(move |inner| {
+            Ok(inner
+                .media_id_media_url
+                .get(id.as_bytes())?
+                .and_then(url_from_ivec))
+        })
+        .await
+    }
+
+    pub(crate) async fn blocks(&self) -> Result<Vec<String>, Error> {
+        self.unblock(|inner| Ok(inner.blocks().map(|block| block.domain).collect()))
+            .await
+    }
+
+    pub(crate) async fn domain_blocks(&self) -> Result<Vec<DomainBlock>, Error> {
+        self.unblock(|inner| Ok(inner.blocks().collect())).await
+    }
+
+    pub(crate) async fn blocked_actors(&self) -> Result<Vec<IriString>, Error> {
+        self.unblock(|inner| Ok(inner.blocked_actors().collect()))
+            .await
+    }
+
+    pub(crate) async fn allows(&self) -> Result<Vec<String>, Error> {
+        self.unblock(|inner| Ok(inner.allowed().collect())).await
+    }
 
     pub(crate) async fn inboxes(&self) -> Result<Vec<IriString>, Error> {
         self.unblock(|inner| Ok(inner.connected_actors().map(|actor| actor.inbox).collect()))
@@ -555,6 +1216,11 @@ impl Db {
                 actor.public_key_id.as_str().as_bytes(),
                 actor.id.as_str().as_bytes(),
             )?;
+            for key in &actor.assertion_keys {
+                inner
+                    .public_key_id_actor_id
+                    .insert(key.id.as_str().as_bytes(), actor.id.as_str().as_bytes())?;
+            }
             inner
                 .actor_id_actor
                 .insert(actor.id.as_str().as_bytes(), vec)?;
@@ -603,9 +1269,20 @@ impl Db {
         .await
     }
 
-    pub(crate) async fn add_blocks(&self, domains: Vec<String>) -> Result<(), Error> {
+    pub(crate) async fn add_blocks(&self, blocks: Vec<DomainBlock>) -> Result<(), Error> {
         self.unblock(move |inner| {
-            let connected_by_domain = inner.connected_by_domain(&domains).collect::<Vec<_>>();
+            let suspended = blocks
+                .iter()
+                .filter(|block| block.severity == BlockSeverity::Suspend)
+                .map(|block| block.domain.clone())
+                .collect::<Vec<_>>();
+
+            let connected_by_domain = inner.connected_by_domain(&suspended).collect::<Vec<_>>();
+
+            let blocks = blocks
+                .iter()
+                .map(|block| Ok((domain_key(&block.domain), serde_json::to_vec(block)?)))
+                .collect::<Result<Vec<_>, Error>>()?;
 
             let res = (
                 &inner.connected_actor_ids,
@@ -621,9 +1298,11 @@ impl Db {
                         connected_batch.remove(connected.as_str().as_bytes());
                     }
 
-                    for authority in &domains {
-                        blocked_batch
-                            .insert(domain_key(authority).as_bytes(), authority.as_bytes());
+                    for (key, vec) in &blocks {
+                        blocked_batch.insert(key.as_bytes(), vec.as_slice());
+                    }
+
+                    for authority in &suspended {
                         allowed_batch.remove(domain_key(authority).as_bytes());
                     }
 
@@ -650,6 +1329,108 @@ impl Db {
         .await
     }
 
+    /// Bring the blocks synced from a remote blocklist in line with its current contents
+    ///
+    /// Domains that are already blocked manually or by another source are left alone, and only
+    /// blocks that came from this source are ever removed. Returns how many blocks were added or
+    /// updated, and how many were removed.
+    pub(crate) async fn sync_blocks(
+        &self,
+        source: String,
+        blocks: Vec<DomainBlock>,
+    ) -> Result<(usize, usize), Error> {
+        let existing = self
+            .domain_blocks()
+            .await?
+            .into_iter()
+            .map(|block| (block.domain.clone(), block))
+            .collect::<HashMap<_, _>>();
+
+        let mut fetched = HashMap::new();
+        for block in blocks {
+            fetched.insert(block.domain.clone(), block);
+        }
+
+        // An empty or broken response shouldn't lift every block the source ever gave us
+        if fetched.is_empty()
+            && existing
+                .values()
+                .any(|block| block.source.as_deref() == Some(source.as_str()))
+        {
+            tracing::warn!("Blocklist {source} has no valid entries, keeping its existing blocks");
+            return Ok((0, 0));
+        }
+
+        let removals = existing
+            .values()
+            .filter(|block| block.source.as_deref() == Some(source.as_str()))
+            .filter(|block| !fetched.contains_key(&block.domain))
+            .map(|block| block.domain.clone())
+            .collect::<Vec<_>>();
+
+        let additions = fetched
+            .into_values()
+            .filter_map(|block| match existing.get(&block.domain) {
+                None => Some(DomainBlock::new(
+                    block.domain,
+                    block.severity,
+                    block.reason,
+                    Some(String::from("blocklist sync")),
+                )),
+                Some(current)
+                    if current.source.as_deref() == Some(source.as_str())
+                        && (current.severity != block.severity
+                            || current.reason != block.reason) =>
+                {
+                    Some(DomainBlock {
+                        severity: block.severity,
+                        reason: block.reason,
+                        ..current.clone()
+                    })
+                }
+                Some(_) => None,
+            })
+            .map(|block| DomainBlock {
+                source: Some(source.clone()),
+                ..block
+            })
+            .collect::<Vec<_>>();
+
+        let counts = (additions.len(), removals.len());
+
+        if !additions.is_empty() {
+            self.add_blocks(additions).await?;
+        }
+
+        if !removals.is_empty() {
+            self.remove_blocks(removals).await?;
+        }
+
+        Ok(counts)
+    }
+
+    pub(crate) async fn is_blocked(&self, url: IriString) -> Result<bool, Error> {
+        self.unblock(move |inner| {
+            if let Some(authority) = url.authority_str() {
+                Ok(inner.is_blocked_with(authority, BlockSeverity::Suspend))
+            } else {
+                Ok(false)
+            }
+        })
+        .await
+    }
+
+    pub(crate) async fn is_silenced(&self, url: IriString) -> Result<bool, Error> {
+        self.unblock(move |inner| {
+            if let Some(authority) = url.authority_str() {
+                Ok(inner.is_blocked_with(authority, BlockSeverity::Silence))
+            } else {
+                Ok(false)
+            }
+        })
+        .await
+    }
+
     pub(crate) async fn remove_blocks(&self, domains: Vec<String>) -> Result<(), Error> {
         self.unblock(move |inner| {
             let mut blocked_batch = Batch::default();
@@ -668,6 +1449,72 @@ impl Db {
         .await
     }
 
+    pub(crate) async fn add_actor_blocks(&self, actor_ids: Vec<IriString>) -> Result<(), Error> {
+        self.unblock(move |inner| {
+            let res = (&inner.connected_actor_ids, &inner.blocked_actors).transaction(
+                |(connected, blocked)| {
+                    let mut connected_batch = Batch::default();
+                    let mut blocked_batch = Batch::default();
+
+                    for actor_id in &actor_ids {
+                        connected_batch.remove(actor_id.as_str().as_bytes());
+                        blocked_batch
+                            .insert(actor_id.as_str().as_bytes(), actor_id.as_str().as_bytes());
+                    }
+
+                    connected.apply_batch(&connected_batch)?;
+                    blocked.apply_batch(&blocked_batch)?;
+
+                    Ok(())
+                },
+            );
+
+            metrics::gauge!("relay.db.connected-actor-ids.size").set(crate::collector::recordable(
+                inner.connected_actor_ids.len(),
+            ));
+            metrics::gauge!("relay.db.blocked-actors.size")
+                .set(crate::collector::recordable(inner.blocked_actors.len()));
+
+            match res {
+                Ok(()) => Ok(()),
+                Err(TransactionError::Abort(e) | TransactionError::Storage(e)) => Err(e.into()),
+            }
+        })
+        .await
+    }
+
+    pub(crate) async fn remove_actor_blocks(&self, actor_ids: Vec<IriString>) -> Result<(), Error> {
+        self.unblock(move |inner| {
+            let mut blocked_batch = Batch::default();
+
+            for actor_id in &actor_ids {
+                blocked_batch.remove(actor_id.as_str().as_bytes());
+            }
+
+            inner.blocked_actors.apply_batch(blocked_batch)?;
+
+            metrics::gauge!("relay.db.blocked-actors.size")
+                .set(crate::collector::recordable(inner.blocked_actors.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub(crate) async fn has_actor_blocks(&self) -> Result<bool, Error> {
+        self.unblock(|inner| Ok(!inner.blocked_actors.is_empty()))
+            .await
+    }
+
+    pub(crate) async fn is_actor_blocked(&self, actor_id: IriString) -> Result<bool, Error> {
+        self.unblock(move |inner| {
+            Ok(inner
+                .blocked_actors
+                .contains_key(actor_id.as_str().as_bytes())?)
+        })
+        .await
+    }
+
     pub(crate) async fn add_allows(&self, domains: Vec<String>) -> Result<(), Error> {
         self.unblock(move |inner| {
             let mut allowed_batch = Batch::default();
@@ -745,6 +1592,331 @@ impl Db {
         .await
     }
 
+    /// The id of the relay's key, if it has been rotated away from the original main key
+    pub(crate) async fn key_id(&self) -> Result<Option<IriString>, Error> {
+        self.unblock(|inner| {
+            if let Some(ivec) = inner.settings.get("key-id")? {
+                Ok(Some(String::from_utf8_lossy(&ivec).parse()?))
+            } else {
+                Ok(None)
+            }
+        })
+        .await
+    }
+
+    pub(crate) async fn retired_keys(&self) -> Result<Vec<RetiredKey>, Error> {
+        self.unblock(|inner| {
+            if let Some(ivec) = inner.settings.get("retired-keys")? {
+                Ok(serde_json::from_slice(&ivec)?)
+            } else {
+                Ok(Vec::new())
+            }
+        })
+        .await
+    }
+
+    /// Replace the relay's key, keeping the old public key around to be served until `cutoff`
+    ///
+    /// Keys retired before `cutoff` are forgotten.
+    pub(crate) async fn rotate_private_key(
+        &self,
+        private_key: &RsaPrivateKey,
+        key_id: IriString,
+        retired: RetiredKey,
+        cutoff: OffsetDateTime,
+    ) -> Result<(), Error> {
+        let pem_pkcs8 = private_key.to_pkcs8_pem(rsa::pkcs8::LineEnding::default())?;
+        let mut retired_keys = self.retired_keys().await?;
+        retired_keys.retain(|key| key.retired_at >= cutoff);
+        retired_keys.push(retired);
+        let retired_keys = serde_json::to_vec(&retired_keys)?;
+
+        let mut batch = Batch::default();
+        batch.insert("private-key".as_bytes(), pem_pkcs8.as_bytes());
+        batch.insert("key-id".as_bytes(), key_id.as_str().as_bytes());
+        batch.insert("retired-keys".as_bytes(), retired_keys);
+
+        self.unblock(move |inner| {
+            inner.settings.apply_batch(batch)?;
+
+            metrics::gauge!("relay.db.settings.size")
+                .set(crate::collector::recordable(inner.settings.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Record a follow request for an admin to decide on, returning false if its domain already
+    /// has one waiting
+    ///
+    /// Requests are kept one per domain, so a server can't flood the queue by making new actors.
+    pub(crate) async fn add_pending_follow(&self, pending: PendingFollow) -> Result<bool, Error> {
+        let authority = pending
+            .actor
+            .id
+            .authority_str()
+            .ok_or(ErrorKind::MissingDomain)?
+            .to_owned();
+        let vec = serde_json::to_vec(&pending)?;
+
+        self.unblock(move |inner| {
+            let added = inner
+                .pending_follows
+                .compare_and_swap(authority.as_bytes(), None as Option<&[u8]>, Some(vec))?
+                .is_ok();
+
+            metrics::gauge!("relay.db.pending-follows.size")
+                .set(crate::collector::recordable(inner.pending_follows.len()));
+
+            Ok(added)
+        })
+        .await
+    }
+
+    pub(crate) async fn pending_follows(&self) -> Result<Vec<PendingFollow>, Error> {
+        self.unblock(|inner| {
+            inner
+                .pending_follows
+                .iter()
+                .values()
+                .map(|res| Ok(serde_json::from_slice(&res?)?))
+                .collect()
+        })
+        .await
+    }
+
+    pub(crate) async fn take_pending_follow(
+        &self,
+        actor_id: IriString,
+    ) -> Result<Option<PendingFollow>, Error> {
+        self.unblock(move |inner| {
+            let authority = actor_id.authority_str().unwrap_or_default();
+
+            let Some(ivec) = inner.pending_follows.get(authority.as_bytes())? else {
+                return Ok(None);
+            };
+
+            let pending: PendingFollow = serde_json::from_slice(&ivec)?;
+
+            if pending.actor.id != actor_id {
+                return Ok(None);
+            }
+
+            inner.pending_follows.remove(authority.as_bytes())?;
+
+            metrics::gauge!("relay.db.pending-follows.size")
+                .set(crate::collector::recordable(inner.pending_follows.len()));
+
+            Ok(Some(pending))
+        })
+        .await
+    }
+
+    pub(crate) async fn add_admin_token(&self, token: AdminToken) -> Result<(), Error> {
+        let vec = serde_json::to_vec(&token)?;
+
+        self.unblock(move |inner| {
+            inner.admin_tokens.insert(token.id.as_bytes(), vec)?;
+
+            metrics::gauge!("relay.db.admin-tokens.size")
+                .set(crate::collector::recordable(inner.admin_tokens.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub(crate) async fn admin_token(&self, id: String) -> Result<Option<AdminToken>, Error> {
+        self.unblock(move |inner| match inner.admin_tokens.get(id.as_bytes())? {
+            Some(ivec) => Ok(Some(serde_json::from_slice(&ivec)?)),
+            None => Ok(None),
+        })
+        .await
+    }
+
+    pub(crate) async fn admin_tokens(&self) -> Result<Vec<AdminToken>, Error> {
+        self.unblock(|inner| {
+            inner
+                .admin_tokens
+                .iter()
+                .values()
+                .map(|res| Ok(serde_json::from_slice(&res?)?))
+                .collect()
+        })
+        .await
+    }
+
+    /// Record that a token was just used
+    pub(crate) async fn touch_admin_token(&self, id: String) -> Result<(), Error> {
+        self.unblock(move |inner| {
+            let Some(ivec) = inner.admin_tokens.get(id.as_bytes())? else {
+                return Ok(());
+            };
+
+            let mut token: AdminToken = serde_json::from_slice(&ivec)?;
+            token.last_used = Some(OffsetDateTime::now_utc());
+
+            // A token revoked in the meantime stays revoked
+            let _ = inner.admin_tokens.compare_and_swap(
+                id.as_bytes(),
+                Some(ivec),
+                Some(serde_json::to_vec(&token)?),
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Revoke tokens by id, ending their dashboard sessions, and return how many existed
+    pub(crate) async fn revoke_admin_tokens(&self, ids: Vec<String>) -> Result<usize, Error> {
+        self.unblock(move |inner| {
+            let mut revoked = 0;
+
+            for id in &ids {
+                if inner.admin_tokens.remove(id.as_bytes())?.is_some() {
+                    revoked += 1;
+                }
+            }
+
+            for res in inner.admin_sessions.iter() {
+                let (key, ivec) = res?;
+                let session: AdminSession = serde_json::from_slice(&ivec)?;
+
+                if session
+                    .token_id
+                    .is_some_and(|token_id| ids.contains(&token_id))
+                {
+                    inner.admin_sessions.remove(key)?;
+                }
+            }
+
+            metrics::gauge!("relay.db.admin-tokens.size")
+                .set(crate::collector::recordable(inner.admin_tokens.len()));
+
+            Ok(revoked)
+        })
+        .await
+    }
+
+    /// Store a new dashboard session, clearing out any that have expired
+    pub(crate) async fn add_admin_session(&self, session: AdminSession) -> Result<(), Error> {
+        let vec = serde_json::to_vec(&session)?;
+
+        self.unblock(move |inner| {
+            let now = OffsetDateTime::now_utc();
+
+            for res in inner.admin_sessions.iter() {
+                let (key, ivec) = res?;
+                let expired: AdminSession = serde_json::from_slice(&ivec)?;
+
+                if expired.expires_at <= now {
+                    inner.admin_sessions.remove(key)?;
+                }
+            }
+
+            inner.admin_sessions.insert(session.id.as_bytes(), vec)?;
+
+            metrics::gauge!("relay.db.admin-sessions.size")
+                .set(crate::collector::recordable(inner.admin_sessions.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Look up a dashboard session, unless it has expired
+    pub(crate) async fn admin_session(&self, id: String) -> Result<Option<AdminSession>, Error> {
+        self.unblock(move |inner| {
+            let Some(ivec) = inner.admin_sessions.get(id.as_bytes())? else {
+                return Ok(None);
+            };
+
+            let session: AdminSession = serde_json::from_slice(&ivec)?;
+
+            if session.expires_at <= OffsetDateTime::now_utc() {
+                inner.admin_sessions.remove(id.as_bytes())?;
+                return Ok(None);
+            }
+
+            Ok(Some(session))
+        })
+        .await
+    }
+
+    pub(crate) async fn remove_admin_session(&self, id: String) -> Result<(), Error> {
+        self.unblock(move |inner| {
+            inner.admin_sessions.remove(id.as_bytes())?;
+
+            metrics::gauge!("relay.db.admin-sessions.size")
+                .set(crate::collector::recordable(inner.admin_sessions.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub(crate) async fn audit(&self, entry: AuditEntry) -> Result<(), Error> {
+        let vec = serde_json::to_vec(&entry)?;
+
+        self.unblock(move |inner| {
+            inner.audit_log.insert(entry.id.as_bytes(), vec)?;
+
+            metrics::gauge!("relay.db.audit-log.size")
+                .set(crate::collector::recordable(inner.audit_log.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Read the audit log newest first, starting after `before` when it's set
+    pub(crate) async fn audit_log(
+        &self,
+        before: Option<Uuid>,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, Error> {
+        self.unblock(move |inner| {
+            let iter = match before {
+                Some(before) => inner.audit_log.range(..before.as_bytes().as_slice()),
+                None => inner.audit_log.iter(),
+            };
+
+            iter.values()
+                .rev()
+                .take(limit)
+                .map(|res| Ok(serde_json::from_slice(&res?)?))
+                .collect()
+        })
+        .await
+    }
+
+    pub(crate) async fn telegram_admin_chat(&self) -> Result<Option<i64>, Error> {
+        self.unblock(|inner| {
+            Ok(inner
+                .settings
+                .get("telegram-admin-chat")?
+                .and_then(|ivec| ivec.as_ref().try_into().ok())
+                .map(i64::from_be_bytes))
+        })
+        .await
+    }
+
+    pub(crate) async fn set_telegram_admin_chat(&self, chat_id: i64) -> Result<(), Error> {
+        self.unblock(move |inner| {
+            inner
+                .settings
+                .insert("telegram-admin-chat", &chat_id.to_be_bytes())?;
+
+            metrics::gauge!("relay.db.settings.size")
+                .set(crate::collector::recordable(inner.settings.len()));
+
+            Ok(())
+        })
+        .await
+    }
+
     pub(crate) async fn update_private_key(
         &self,
         private_key: &RsaPrivateKey,
@@ -789,9 +1961,349 @@ fn uuid_from_ivec(ivec: sled::IVec) -> Option<Uuid> {
 
 #[cfg(test)]
 mod tests {
-    use super::Db;
+    use super::{
+        Actor, AdminSession, AdminToken, AuditAction, AuditEntry, BlockSeverity, Db, DomainBlock,
+        PendingFollow, RelayedObject, RetiredKey, TokenScope, ANNOUNCE_LOG_LIMIT,
+    };
     use activitystreams::iri_string::types::IriString;
-    use std::future::Future;
+    use std::{collections::BTreeSet, future::Future};
+    use time::OffsetDateTime;
+
+    #[test]
+    fn silenced_domain_stays_allowed() {
+        run_with(false, |db| async move {
+            let loud_actor: IriString = "http://loud.example/actor".parse().unwrap();
+            let spam_actor: IriString = "http://spam.example/actor".parse().unwrap();
+            db.add_connection(loud_actor.clone()).await.unwrap();
+            db.add_connection(spam_actor.clone()).await.unwrap();
+
+            db.add_blocks(vec![
+                DomainBlock::new("loud.example".into(), BlockSeverity::Silence, None, None),
+                DomainBlock::new(
+                    "spam.example".into(),
+                    BlockSeverity::Suspend,
+                    Some("spam".into()),
+                    None,
+                ),
+            ])
+            .await
+            .unwrap();
+
+            assert!(db.is_allowed(loud_actor.clone()).await.unwrap());
+            assert!(db.is_silenced(loud_actor.clone()).await.unwrap());
+            assert!(db.is_connected(loud_actor).await.unwrap());
+
+            assert!(!db.is_allowed(spam_actor.clone()).await.unwrap());
+            assert!(!db.is_silenced(spam_actor.clone()).await.unwrap());
+            assert!(!db.is_connected(spam_actor).await.unwrap());
+
+            let blocks = db.domain_blocks().await.unwrap();
+            assert!(blocks
+                .iter()
+                .any(|block| block.reason.as_deref() == Some("spam")));
+        })
+    }
+
+    #[test]
+    fn sync_keeps_manual_blocks() {
+        run_with(false, |db| async move {
+            let source = String::from("https://lists.example/blocks.csv");
+            let block =
+                |domain: &str| DomainBlock::new(domain.into(), BlockSeverity::Suspend, None, None);
+
+            db.add_blocks(vec![block("manual.example")]).await.unwrap();
+
+            let counts = db
+                .sync_blocks(
+                    source.clone(),
+                    vec![block("manual.example"), block("synced.example")],
+                )
+                .await
+                .unwrap();
+            assert_eq!(counts, (1, 0));
+
+            let counts = db.sync_blocks(source.clone(), Vec::new()).await.unwrap();
+            assert_eq!(counts, (0, 0));
+
+            let counts = db
+                .sync_blocks(source, vec![block("manual.example")])
+                .await
+                .unwrap();
+            assert_eq!(counts, (0, 1));
+
+            let blocks = db.blocks().await.unwrap();
+            assert_eq!(blocks, vec![String::from("manual.example")]);
+        })
+    }
+
+    #[test]
+    fn pending_follows_are_kept_per_domain() {
+        run(|db| async move {
+            let pending = |actor_id: &str| {
+                let actor_id: IriString = actor_id.parse().unwrap();
+
+                PendingFollow {
+                    id: uuid::Uuid::new_v4(),
+                    input: serde_json::from_value(serde_json::json!({
+                        "id": format!("{actor_id}/follow"),
+                        "type": "Follow",
+                        "actor": actor_id.as_str(),
+                        "object": "https://relay.example/actor",
+                    }))
+                    .unwrap(),
+                    actor: Actor {
+                        id: actor_id.clone(),
+                        public_key: String::new(),
+                        public_key_id: format!("{actor_id}#main-key").parse().unwrap(),
+                        inbox: format!("{actor_id}/inbox").parse().unwrap(),
+                        saved_at: std::time::SystemTime::now(),
+                        opted_out: false,
+                        assertion_keys: Vec::new(),
+                    },
+                    requested_at: OffsetDateTime::now_utc(),
+                }
+            };
+
+            let relay = "https://spam.example/actor";
+            let other = "https://spam.example/users/another";
+
+            assert!(db.add_pending_follow(pending(relay)).await.unwrap());
+            assert!(!db.add_pending_follow(pending(other)).await.unwrap());
+            assert_eq!(db.pending_follows().await.unwrap().len(), 1);
+
+            let taken = db
+                .take_pending_follow(other.parse().unwrap())
+                .await
+                .unwrap();
+            assert!(taken.is_none());
+
+            let taken = db
+                .take_pending_follow(relay.parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(taken.unwrap().actor.id.as_str(), relay);
+            assert!(db.pending_follows().await.unwrap().is_empty());
+        })
+    }
+
+    #[test]
+    fn deliver_backlog_round_trips() {
+        run(|db| async move {
+            db.save_deliver_backlog(vec![
+                (String::from("one.example"), 3),
+                (String::from("two.example"), 1),
+            ])
+            .await
+            .unwrap();
+            db.save_deliver_backlog(vec![(String::from("one.example"), 2)])
+                .await
+                .unwrap();
+
+            let backlog = db.deliver_backlog().await.unwrap();
+            assert_eq!(backlog, vec![(String::from("one.example"), 2)]);
+        })
+    }
+
+    #[test]
+    fn relayed_objects_expire() {
+        run(|db| async move {
+            let relayed = |object: &str, announced_at| {
+                (
+                    object.parse::<IriString>().unwrap(),
+                    RelayedObject {
+                        activity_id: format!("{object}/announce").parse().unwrap(),
+                        actor_id: "https://example.com/actor".parse().unwrap(),
+                        announced_at,
+                    },
+                )
+            };
+
+            let now = OffsetDateTime::now_utc();
+            let (old, old_relayed) =
+                relayed("https://example.com/old", now - time::Duration::days(10));
+            let (new, new_relayed) = relayed("https://example.com/new", now);
+
+            db.save_relayed_object(old.clone(), old_relayed)
+                .await
+                .unwrap();
+            db.save_relayed_object(new.clone(), new_relayed)
+                .await
+                .unwrap();
+
+            let expired = db
+                .expire_relayed_objects(now - time::Duration::days(1))
+                .await
+                .unwrap();
+            assert_eq!(expired, 1);
+
+            assert!(db.relayed_object(old).await.unwrap().is_none());
+            assert!(db.relayed_object(new).await.unwrap().is_some());
+        })
+    }
+
+    #[test]
+    fn rotated_keys_are_retired() {
+        run(|db| async move {
+            let key = rsa::RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+            let now = OffsetDateTime::now_utc();
+
+            let retired = |key_id: &str, retired_at| RetiredKey {
+                key_id: key_id.parse().unwrap(),
+                public_key_pem: String::new(),
+                retired_at,
+            };
+
+            assert!(db.key_id().await.unwrap().is_none());
+
+            db.rotate_private_key(
+                &key,
+                "https://relay.example/actor/keys/one".parse().unwrap(),
+                retired(
+                    "https://relay.example/actor#main-key",
+                    now - time::Duration::days(10),
+                ),
+                now - time::Duration::days(1),
+            )
+            .await
+            .unwrap();
+
+            db.rotate_private_key(
+                &key,
+                "https://relay.example/actor/keys/two".parse().unwrap(),
+                retired("https://relay.example/actor/keys/one", now),
+                now - time::Duration::days(1),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(
+                db.key_id().await.unwrap().unwrap().as_str(),
+                "https://relay.example/actor/keys/two"
+            );
+
+            let retired_keys = db
+                .retired_keys()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|key| key.key_id.to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(retired_keys, ["https://relay.example/actor/keys/one"]);
+            assert!(db.private_key().await.unwrap().is_some());
+        })
+    }
+
+    #[test]
+    fn audit_log_pages_newest_first() {
+        run(|db| async move {
+            for domain in ["one.example", "two.example", "three.example"] {
+                db.audit(AuditEntry::new(
+                    String::from("test"),
+                    AuditAction::Block,
+                    vec![domain.into()],
+                    None,
+                ))
+                .await
+                .unwrap();
+            }
+
+            let page = db.audit_log(None, 2).await.unwrap();
+            assert_eq!(page.len(), 2);
+            assert_eq!(page[0].targets, vec![String::from("three.example")]);
+            assert_eq!(page[1].targets, vec![String::from("two.example")]);
+
+            let page = db.audit_log(Some(page[1].id), 2).await.unwrap();
+            assert_eq!(page.len(), 1);
+            assert_eq!(page[0].targets, vec![String::from("one.example")]);
+        })
+    }
+
+    #[test]
+    fn revoked_tokens_are_gone() {
+        run(|db| async move {
+            let token = AdminToken {
+                id: String::from("abc"),
+                name: String::from("monitoring"),
+                scopes: BTreeSet::from([TokenScope::Stats]),
+                hashed_secret: String::from("hash"),
+                created_at: OffsetDateTime::now_utc(),
+                last_used: None,
+            };
+
+            db.add_admin_token(token).await.unwrap();
+            db.touch_admin_token(String::from("abc")).await.unwrap();
+
+            let token = db.admin_token(String::from("abc")).await.unwrap().unwrap();
+            assert!(token.last_used.is_some());
+
+            let revoked = db
+                .revoke_admin_tokens(vec![String::from("abc"), String::from("missing")])
+                .await
+                .unwrap();
+            assert_eq!(revoked, 1);
+
+            db.touch_admin_token(String::from("abc")).await.unwrap();
+            assert!(db.admin_token(String::from("abc")).await.unwrap().is_none());
+        })
+    }
+
+    #[test]
+    fn sessions_end_with_their_token() {
+        run(|db| async move {
+            let session = |id: &str, token_id: Option<&str>, expires_in: i64| AdminSession {
+                id: id.to_owned(),
+                token_id: token_id.map(String::from),
+                name: String::from("monitoring"),
+                scopes: BTreeSet::from([TokenScope::Stats]),
+                expires_at: OffsetDateTime::now_utc() + time::Duration::hours(expires_in),
+            };
+
+            db.add_admin_session(session("revoked", Some("abc"), 1))
+                .await
+                .unwrap();
+            db.add_admin_session(session("api", None, 1)).await.unwrap();
+            db.add_admin_session(session("expired", Some("def"), -1))
+                .await
+                .unwrap();
+
+            db.revoke_admin_tokens(vec![String::from("abc")])
+                .await
+                .unwrap();
+
+            let found = |id: &'static str| {
+                let db = db.clone();
+                async move { db.admin_session(String::from(id)).await.unwrap() }
+            };
+
+            assert!(found("revoked").await.is_none());
+            assert!(found("expired").await.is_none());
+            assert_eq!(found("api").await.unwrap().name, "monitoring");
+
+            db.remove_admin_session(String::from("api")).await.unwrap();
+            assert!(found("api").await.is_none());
+        })
+    }
+
+    #[test]
+    fn blocked_actor_is_disconnected() {
+        run(|db| async move {
+            let example_actor: IriString = "http://example.com/actor".parse().unwrap();
+            let other_actor: IriString = "http://example.com/users/fine".parse().unwrap();
+            db.add_connection(example_actor.clone()).await.unwrap();
+
+            db.add_actor_blocks(vec![example_actor.clone()])
+                .await
+                .unwrap();
+            assert!(db.is_actor_blocked(example_actor.clone()).await.unwrap());
+            assert!(!db.is_actor_blocked(other_actor).await.unwrap());
+            assert!(!db.connected_ids().await.unwrap().contains(&example_actor));
+
+            db.remove_actor_blocks(vec![example_actor.clone()])
+                .await
+                .unwrap();
+            assert!(!db.is_actor_blocked(example_actor).await.unwrap());
+        })
+    }
 
     #[test]
     fn connect_and_verify() {
@@ -837,13 +2349,59 @@ mod tests {
         })
     }
 
+    #[test]
+    fn announces_are_newest_first() {
+        run(|db| async move {
+            db.save_announce(serde_json::json!({ "id": "first" }))
+                .await
+                .unwrap();
+            db.save_announce(serde_json::json!({ "id": "second" }))
+                .await
+                .unwrap();
+
+            let announces = db.announces(0, 10).await.unwrap();
+
+            assert_eq!(db.announce_count().await.unwrap(), 2);
+            assert_eq!(announces[0]["id"], "second");
+            assert_eq!(announces[1]["id"], "first");
+        })
+    }
+
+    #[test]
+    fn announce_log_is_trimmed_to_limit() {
+        run(|db| async move {
+            for i in 0..=ANNOUNCE_LOG_LIMIT {
+                db.save_announce(serde_json::json!({ "id": i }))
+                    .await
+                    .unwrap();
+            }
+
+            let announces = db.announces(ANNOUNCE_LOG_LIMIT - 1, 10).await.unwrap();
+
+            assert_eq!(db.announce_count().await.unwrap(), ANNOUNCE_LOG_LIMIT);
+            assert_eq!(announces.len(), 1);
+            assert_eq!(announces[0]["id"], 1);
+        })
+    }
+
     fn run<F, Fut>(f: F)
     where
         F: Fn(Db) -> Fut,
         Fut: Future<Output = ()> + 'static,
     {
-        let db =
-            Db::build_inner(true, sled::Config::new().temporary(true).open().unwrap()).unwrap();
+        run_with(true, f)
+    }
+
+    fn run_with<F, Fut>(restricted_mode: bool, f: F)
+    where
+        F: Fn(Db) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let db = Db::build_inner(
+            restricted_mode,
+            sled::Config::new().temporary(true).open().unwrap(),
+        )
+        .unwrap();
 
         tokio::runtime::Builder::new_current_thread()
             .enable_all()
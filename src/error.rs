@@ -111,6 +111,9 @@ pub(crate) enum ErrorKind {
     #[error("Error queueing job")]
     Queue(#[from] BoxError),
 
+    #[error("Couldn't open job storage")]
+    JobStorage(#[from] background_jobs::sled::Error),
+
     #[error("Error in configuration")]
     Config(#[from] config::ConfigError),
 
@@ -150,6 +153,9 @@ pub(crate) enum ErrorKind {
     #[error("Couldn't do the json thing")]
     Json(#[from] serde_json::Error),
 
+    #[error("Couldn't parse activity, {0}")]
+    MalformedActivity(serde_json::Error),
+
     #[error("Couldn't sign request")]
     Sign(#[from] SignError),
 
@@ -201,6 +207,21 @@ pub(crate) enum ErrorKind {
     #[error("Invalid algorithm provided to verifier, {0}")]
     Algorithm(String),
 
+    #[error("Invalid message signature, {0}")]
+    MessageSignature(&'static str),
+
+    #[error("Invalid integrity proof, {0}")]
+    Proof(&'static str),
+
+    #[error("Invalid trusted proxy, {0}")]
+    TrustedProxy(String),
+
+    #[error("Too many requests, retry in {0} seconds")]
+    RateLimited(u64),
+
+    #[error("The relay is overloaded, retry in {0} seconds")]
+    Overloaded(u64),
+
     #[error("Object has already been relayed")]
     Duplicate,
 
@@ -242,6 +263,42 @@ pub(crate) enum ErrorKind {
 
     #[error("No API Token supplied")]
     MissingApiToken,
+
+    #[error("Invalid content filter, {0}")]
+    InvalidFilter(String),
+
+    #[error("Couldn't read or write CSV")]
+    Csv(#[from] csv::Error),
+
+    #[error("Unknown block severity, {0}")]
+    Severity(String),
+
+    #[error("Unknown token scope, {0}")]
+    TokenScope(String),
+
+    #[error("Invalid telegram admin, {0}")]
+    TelegramAdmin(String),
+
+    #[error("Invalid telegram chat id, {0}")]
+    TelegramChat(String),
+
+    #[error("Unknown notification event, {0}")]
+    EventKind(String),
+
+    #[error("Unknown visibility, {0}")]
+    Visibility(String),
+
+    #[error("Couldn't send telegram message")]
+    Telegram(#[from] teloxide::RequestError),
+
+    #[error("Couldn't send email")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+
+    #[error("Couldn't build email")]
+    Email(#[from] lettre::error::Error),
+
+    #[error("Invalid email address")]
+    Address(#[from] lettre::address::AddressError),
 }
 
 impl ResponseError for Error {
@@ -250,19 +307,37 @@ impl ResponseError for Error {
             ErrorKind::NotAllowed(_) | ErrorKind::WrongActor(_) | ErrorKind::BadActor(_, _) => {
                 StatusCode::FORBIDDEN
             }
-            ErrorKind::NotSubscribed(_) => StatusCode::UNAUTHORIZED,
+            ErrorKind::Extractor(crate::extractors::ErrorKind::Invalid) => StatusCode::UNAUTHORIZED,
+            ErrorKind::Extractor(crate::extractors::ErrorKind::MissingScope(_)) => {
+                StatusCode::FORBIDDEN
+            }
+            ErrorKind::NotSubscribed(_) | ErrorKind::MessageSignature(_) | ErrorKind::Proof(_) => {
+                StatusCode::UNAUTHORIZED
+            }
             ErrorKind::Duplicate => StatusCode::ACCEPTED,
+            ErrorKind::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            ErrorKind::Overloaded(_) => StatusCode::SERVICE_UNAVAILABLE,
             ErrorKind::Kind(_)
             | ErrorKind::MissingKind
             | ErrorKind::MissingId
             | ErrorKind::ObjectCount
-            | ErrorKind::NoSignature(_) => StatusCode::BAD_REQUEST,
+            | ErrorKind::MalformedActivity(_)
+            | ErrorKind::NoSignature(_)
+            | ErrorKind::InvalidFilter(_)
+            | ErrorKind::Severity(_)
+            | ErrorKind::TokenScope(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code())
+        let mut response = HttpResponse::build(self.status_code());
+
+        if let ErrorKind::RateLimited(seconds) | ErrorKind::Overloaded(seconds) = self.kind() {
+            response.insert_header(("Retry-After", seconds.to_string()));
+        }
+
+        response
             .insert_header(("Content-Type", "application/activity+json"))
             .body(
                 serde_json::to_string(&serde_json::json!({
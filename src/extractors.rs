@@ -7,9 +7,30 @@ use actix_web::{
 };
 use bcrypt::{BcryptError, DEFAULT_COST};
 use http_signature_normalization_actix::{prelude::InvalidHeaderValue, Canceled, Spawn};
-use std::{convert::Infallible, str::FromStr, time::Instant};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use std::{
+    collections::BTreeSet,
+    convert::Infallible,
+    future::Future,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{
+    db::{AdminSession, AdminToken, AuditAction, AuditEntry, Db, TokenScope},
+    error::Error,
+    future::LocalBoxFuture,
+    notify::{Event, Notifier},
+    spawner::Spawner,
+};
+
+/// The cookie the admin dashboard keeps its session id in, for browsers that can't set headers
+pub(crate) const ADMIN_COOKIE: &str = "relay-admin-session";
 
-use crate::{db::Db, error::Error, future::LocalBoxFuture, spawner::Spawner};
+/// How long a dashboard login lasts
+pub(crate) const SESSION_TTL: Duration = Duration::from_secs(60 * 60 * 12);
 
 #[derive(Clone)]
 pub(crate) struct AdminConfig {
@@ -30,18 +51,42 @@ impl AdminConfig {
 
 pub(crate) struct Admin {
     db: Data<Db>,
+    notifier: Option<Data<Notifier>>,
+    name: String,
+    scopes: BTreeSet<TokenScope>,
 }
 
-type PrepareTuple = (Data<Db>, Data<AdminConfig>, Data<Spawner>, XApiToken);
+/// What a request identifies its admin with
+enum Credential {
+    Token(XApiToken),
+    Session(String),
+}
+
+type AppData = (Data<Db>, Option<Data<AdminConfig>>, Data<Spawner>);
+type PrepareTuple = (
+    Data<Db>,
+    Option<Data<AdminConfig>>,
+    Data<Spawner>,
+    Credential,
+);
 
 impl Admin {
     fn prepare_verify(req: &HttpRequest) -> Result<PrepareTuple, Error> {
-        let hashed_api_token = req
-            .app_data::<Data<AdminConfig>>()
-            .ok_or_else(Error::missing_config)?
-            .clone();
+        let (db, hashed_api_token, spawner) = Self::app_data(req)?;
 
-        let x_api_token = XApiToken::parse(req).map_err(Error::parse_header)?;
+        let credential = match XApiToken::parse(req) {
+            Ok(x_api_token) => Credential::Token(x_api_token),
+            Err(e) => req
+                .cookie(ADMIN_COOKIE)
+                .map(|cookie| Credential::Session(cookie.value().to_owned()))
+                .ok_or_else(|| Error::parse_header(e))?,
+        };
+
+        Ok((db, hashed_api_token, spawner, credential))
+    }
+
+    fn app_data(req: &HttpRequest) -> Result<AppData, Error> {
+        let hashed_api_token = req.app_data::<Data<AdminConfig>>().cloned();
 
         let db = req
             .app_data::<Data<Db>>()
@@ -53,27 +98,178 @@ impl Admin {
             .ok_or_else(Error::missing_spawner)?
             .clone();
 
-        Ok((db, hashed_api_token, spawner, x_api_token))
+        Ok((db, hashed_api_token, spawner))
     }
 
+    /// Verify a token against the stored admin tokens, falling back to the configured API token
+    ///
+    /// Returns the id of the stored token, if it was one, along with its name and scopes.
     #[tracing::instrument(level = "debug", skip_all)]
     async fn verify(
-        hashed_api_token: Data<AdminConfig>,
+        db: &Db,
+        hashed_api_token: Option<Data<AdminConfig>>,
         spawner: Data<Spawner>,
         x_api_token: XApiToken,
-    ) -> Result<(), Error> {
+    ) -> Result<(Option<String>, String, BTreeSet<TokenScope>), Error> {
         let span = tracing::Span::current();
+
+        if let Some((id, secret)) = x_api_token.0.split_once('.') {
+            if let Some(token) = db.admin_token(id.to_owned()).await? {
+                let span = span.clone();
+                let secret = secret.to_owned();
+                let hashed_secret = token.hashed_secret.clone();
+
+                if spawner
+                    .spawn_blocking(move || {
+                        span.in_scope(|| bcrypt::verify(secret, &hashed_secret))
+                    })
+                    .await
+                    .map_err(Error::canceled)?
+                    .map_err(Error::bcrypt_verify)?
+                {
+                    db.touch_admin_token(token.id.clone()).await?;
+                    return Ok((Some(token.id), token.name, token.scopes));
+                }
+
+                return Err(Error::invalid());
+            }
+        }
+
+        let hashed_api_token = hashed_api_token.ok_or_else(Error::missing_config)?;
+
         if spawner
             .spawn_blocking(move || span.in_scope(|| hashed_api_token.verify(x_api_token)))
             .await
             .map_err(Error::canceled)??
         {
-            return Ok(());
+            return Ok((
+                None,
+                String::from("api token"),
+                BTreeSet::from([TokenScope::Admin]),
+            ));
         }
 
         Err(Error::invalid())
     }
 
+    /// Check a token submitted to the admin dashboard's login form, and start a session for it
+    ///
+    /// Returns the id of the new session.
+    pub(crate) async fn login(req: &HttpRequest, token: String) -> Result<String, Error> {
+        let (db, hashed_api_token, spawner) = Self::app_data(req)?;
+
+        let (token_id, name, scopes) =
+            Self::verify(&db, hashed_api_token, spawner, XApiToken(token)).await?;
+
+        let id = thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(48)
+            .map(char::from)
+            .collect::<String>();
+
+        db.add_admin_session(AdminSession {
+            id: id.clone(),
+            token_id,
+            name,
+            scopes,
+            expires_at: OffsetDateTime::now_utc() + SESSION_TTL,
+        })
+        .await?;
+
+        Ok(id)
+    }
+
+    /// End the dashboard session the request was made with, if any
+    pub(crate) async fn logout(req: &HttpRequest) -> Result<(), Error> {
+        let (db, _, _) = Self::app_data(req)?;
+
+        if let Some(cookie) = req.cookie(ADMIN_COOKIE) {
+            db.remove_admin_session(cookie.value().to_owned()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Create a new token, returning it along with the secret to hand to its holder
+    pub(crate) async fn generate_token(
+        spawner: &Spawner,
+        name: String,
+        scopes: BTreeSet<TokenScope>,
+    ) -> Result<(AdminToken, String), Error> {
+        let id = Uuid::new_v4().simple().to_string();
+        let secret = thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(48)
+            .map(char::from)
+            .collect::<String>();
+
+        let hashed_secret = {
+            let secret = secret.clone();
+            spawner
+                .spawn_blocking(move || bcrypt::hash(secret, DEFAULT_COST))
+                .await
+                .map_err(Error::canceled)?
+                .map_err(Error::bcrypt_hash)?
+        };
+
+        let token = AdminToken {
+            id: id.clone(),
+            name,
+            scopes,
+            hashed_secret,
+            created_at: OffsetDateTime::now_utc(),
+            last_used: None,
+        };
+
+        Ok((token, format!("{id}.{secret}")))
+    }
+
+    pub(crate) fn has_scope(&self, scope: TokenScope) -> bool {
+        self.scopes.contains(&TokenScope::Admin) || self.scopes.contains(&scope)
+    }
+
+    /// Refuse the request unless the token was granted `scope`
+    pub(crate) fn require(&self, scope: TokenScope) -> Result<(), Error> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(Error::from(ErrorKind::MissingScope(scope.as_str())))
+        }
+    }
+
+    /// Apply `change` on behalf of this token, recording it in the audit log unless it had no
+    /// targets
+    ///
+    /// The entry is written before the change runs, so nothing can be changed without leaving
+    /// an entry behind. The notifier only hears about it once the change succeeded.
+    pub(crate) async fn audited<T>(
+        &self,
+        action: AuditAction,
+        targets: Vec<String>,
+        reason: Option<String>,
+        change: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        if targets.is_empty() {
+            return change.await;
+        }
+
+        let entry = AuditEntry::new(self.name.clone(), action, targets, reason);
+
+        self.db.audit(entry.clone()).await?;
+
+        let output = change.await?;
+
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(Event::AdminAction { entry });
+        }
+
+        Ok(output)
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
     pub(crate) fn db_ref(&self) -> &Db {
         &self.db
     }
@@ -138,6 +334,9 @@ pub(crate) enum ErrorKind {
 
     #[error("Parse Header")]
     ParseHeader(#[source] ParseError),
+
+    #[error("Token is missing the {0} scope")]
+    MissingScope(&'static str),
 }
 
 impl FromRequest for Admin {
@@ -147,12 +346,27 @@ impl FromRequest for Admin {
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
         let now = Instant::now();
         let res = Self::prepare_verify(req);
+        let notifier = req.app_data::<Data<Notifier>>().cloned();
         Box::pin(async move {
-            let (db, c, s, t) = res?;
-            Self::verify(c, s, t).await?;
+            let (db, c, s, credential) = res?;
+            let (name, scopes) = match credential {
+                Credential::Token(t) => {
+                    let (_, name, scopes) = Self::verify(&db, c, s, t).await?;
+                    (name, scopes)
+                }
+                Credential::Session(id) => {
+                    let session = db.admin_session(id).await?.ok_or_else(Error::invalid)?;
+                    (session.name, session.scopes)
+                }
+            };
             metrics::histogram!("relay.admin.verify")
                 .record(now.elapsed().as_micros() as f64 / 1_000_000_f64);
-            Ok(Admin { db })
+            Ok(Admin {
+                db,
+                notifier,
+                name,
+                scopes,
+            })
         })
     }
 }
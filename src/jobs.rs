@@ -1,11 +1,15 @@
 pub mod apub;
+mod check_backlog;
 mod contact;
 mod deliver;
 mod deliver_many;
+mod expire_relayed_objects;
 mod instance;
 mod nodeinfo;
 mod process_listeners;
+mod record_breakers;
 mod record_last_online;
+mod sync_blocklists;
 
 pub(crate) use self::{
     contact::QueryContact, deliver::Deliver, deliver_many::DeliverMany, instance::QueryInstance,
@@ -14,17 +18,26 @@ pub(crate) use self::{
 
 use crate::{
     config::Config,
-    data::{ActorCache, MediaCache, State},
+    data::{ActorCache, DeliverQueues, MediaCache, State},
+    db::Db,
     error::{Error, ErrorKind},
-    jobs::{process_listeners::Listeners, record_last_online::RecordLastOnline},
+    jobs::{
+        check_backlog::CheckBacklog, expire_relayed_objects::ExpireRelayedObjects,
+        process_listeners::Listeners, record_breakers::RecordBreakers,
+        record_last_online::RecordLastOnline, sync_blocklists::SyncBlocklists,
+    },
+    notify::Notifier,
 };
 use background_jobs::{
-    memory_storage::{Storage, TokioTimer},
     metrics::MetricsStorage,
+    sled::Storage,
     tokio::{JobQueue, WorkerConfig},
     Job,
 };
-use std::time::Duration;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 fn debug_object(activity: &serde_json::Value) -> &serde_json::Value {
     let mut object = &activity["object"]["type"];
@@ -40,26 +53,41 @@ fn debug_object(activity: &serde_json::Value) -> &serde_json::Value {
     object
 }
 
-pub(crate) fn build_storage() -> MetricsStorage<Storage<TokioTimer>> {
-    MetricsStorage::wrap(Storage::new(TokioTimer))
+pub(crate) fn build_storage(db: &Db) -> MetricsStorage<Storage> {
+    MetricsStorage::wrap(db.job_storage())
+}
+
+/// Count the delivery backlog saved by the last run toward each domain's queue
+pub(crate) async fn restore_deliver_backlog(state: &State) -> Result<(), Error> {
+    let backlog = state.db.deliver_backlog().await?;
+
+    state.deliver_queues.restore(backlog);
+
+    Ok(())
 }
 
 pub(crate) fn create_workers(
-    storage: MetricsStorage<Storage<TokioTimer>>,
+    storage: MetricsStorage<Storage>,
     state: State,
     actors: ActorCache,
     media: MediaCache,
     config: Config,
+    notifier: Notifier,
 ) -> std::io::Result<JobServer> {
     let deliver_concurrency = config.deliver_concurrency();
+    let check_backlog = config.deliver_backlog_alert() > 0;
+    let deliver_queues = state.deliver_queues.clone();
+    let sync_blocklists = !config.blocklist_urls().is_empty();
+    let blocklist_sync_interval = config.blocklist_sync_interval();
 
     let queue_handle = WorkerConfig::new(storage, move |queue_handle| {
         JobState::new(
             state.clone(),
             actors.clone(),
-            JobServer::new(queue_handle),
+            JobServer::new(queue_handle, state.deliver_queues.clone()),
             media.clone(),
             config.clone(),
+            notifier.clone(),
         )
     })
     .register::<Deliver>()
@@ -69,11 +97,18 @@ pub(crate) fn create_workers(
     .register::<Listeners>()
     .register::<QueryContact>()
     .register::<RecordLastOnline>()
+    .register::<RecordBreakers>()
+    .register::<CheckBacklog>()
+    .register::<ExpireRelayedObjects>()
+    .register::<SyncBlocklists>()
     .register::<apub::Announce>()
     .register::<apub::Follow>()
     .register::<apub::Forward>()
     .register::<apub::Reject>()
+    .register::<apub::RejectFollow>()
     .register::<apub::Undo>()
+    .register::<apub::UndoAnnounce>()
+    .register::<apub::UpdateActor>()
     .set_worker_count("maintenance", 2)
     .set_worker_count("apub", 2)
     .set_worker_count("deliver", deliver_concurrency)
@@ -81,8 +116,43 @@ pub(crate) fn create_workers(
 
     queue_handle.every(Duration::from_secs(60 * 5), Listeners)?;
     queue_handle.every(Duration::from_secs(60 * 10), RecordLastOnline)?;
+    queue_handle.every(Duration::from_secs(60), RecordBreakers)?;
+    queue_handle.every(Duration::from_secs(60 * 60), ExpireRelayedObjects)?;
 
-    Ok(JobServer::new(queue_handle))
+    if check_backlog {
+        queue_handle.every(Duration::from_secs(60), CheckBacklog)?;
+    }
+
+    if sync_blocklists {
+        queue_handle.every(blocklist_sync_interval, SyncBlocklists)?;
+    }
+
+    Ok(JobServer::new(queue_handle, deliver_queues))
+}
+
+/// Create a job server that only queues jobs, leaving them to the workers from `create_workers`
+pub(crate) fn create_queue(
+    storage: MetricsStorage<Storage>,
+    state: State,
+    actors: ActorCache,
+    media: MediaCache,
+    config: Config,
+) -> std::io::Result<JobServer> {
+    let deliver_queues = state.deliver_queues.clone();
+
+    let queue_handle = WorkerConfig::new(storage, move |queue_handle| {
+        JobState::new(
+            state.clone(),
+            actors.clone(),
+            JobServer::new(queue_handle, state.deliver_queues.clone()),
+            media.clone(),
+            config.clone(),
+            Notifier::disabled(),
+        )
+    })
+    .start()?;
+
+    Ok(JobServer::new(queue_handle, deliver_queues))
 }
 
 #[derive(Clone, Debug)]
@@ -92,11 +162,13 @@ pub(crate) struct JobState {
     config: Config,
     media: MediaCache,
     job_server: JobServer,
+    notifier: Notifier,
 }
 
 #[derive(Clone)]
 pub(crate) struct JobServer {
     remote: JobQueue,
+    deliver_queues: Arc<DeliverQueues>,
 }
 
 impl std::fmt::Debug for JobServer {
@@ -114,6 +186,7 @@ impl JobState {
         job_server: JobServer,
         media: MediaCache,
         config: Config,
+        notifier: Notifier,
     ) -> Self {
         JobState {
             state,
@@ -121,17 +194,36 @@ impl JobState {
             config,
             media,
             job_server,
+            notifier,
         }
     }
 }
 
 impl JobServer {
-    fn new(remote_handle: JobQueue) -> Self {
+    fn new(remote_handle: JobQueue, deliver_queues: Arc<DeliverQueues>) -> Self {
         JobServer {
             remote: remote_handle,
+            deliver_queues,
         }
     }
 
+    pub(crate) async fn deliver(&self, deliver: Deliver) -> Result<(), Error> {
+        self.deliver_queues.enqueued(deliver.inbox());
+
+        self.queue(deliver).await
+    }
+
+    pub(crate) async fn schedule<J>(&self, job: J, after: Duration) -> Result<(), Error>
+    where
+        J: Job,
+    {
+        self.remote
+            .schedule(job, SystemTime::now() + after)
+            .await
+            .map_err(ErrorKind::Queue)
+            .map_err(Into::into)
+    }
+
     pub(crate) async fn queue<J>(&self, job: J) -> Result<(), Error>
     where
         J: Job,
@@ -1,26 +1,33 @@
 use crate::{
     config::{Config, UrlKind},
     data::State,
-    db::Actor,
+    db::{Actor, Db},
     error::{Error, ErrorKind},
+    jobs::JobServer,
 };
 use activitystreams::{
-    activity::{Follow as AsFollow, Undo as AsUndo},
+    activity::{Announce as AsAnnounce, Follow as AsFollow, Undo as AsUndo},
     context,
     iri_string::types::IriString,
     prelude::*,
     security,
 };
+use serde_json::Value;
 use std::convert::TryInto;
 
 mod announce;
 mod follow;
 mod forward;
 mod reject;
+mod reject_follow;
 mod undo;
+mod undo_announce;
+mod update_actor;
 
 pub(crate) use self::{
-    announce::Announce, follow::Follow, forward::Forward, reject::Reject, undo::Undo,
+    announce::Announce, follow::Follow, forward::Forward, reject::Reject,
+    reject_follow::RejectFollow, undo::Undo, undo_announce::UndoAnnounce,
+    update_actor::UpdateActor,
 };
 
 async fn get_inboxes(
@@ -36,6 +43,81 @@ async fn get_inboxes(
     state.inboxes_without(&actor.inbox, &authority).await
 }
 
+/// Accept a pending follow request, allowing the requesting actor's domain
+///
+/// Returns false if there was no pending request from the actor
+pub(crate) async fn approve_follow(
+    db: &Db,
+    job_server: &JobServer,
+    actor_id: IriString,
+) -> Result<bool, Error> {
+    let Some(pending) = db.take_pending_follow(actor_id).await? else {
+        return Ok(false);
+    };
+
+    let authority = pending
+        .actor
+        .id
+        .authority_str()
+        .ok_or(ErrorKind::MissingDomain)?
+        .to_owned();
+
+    db.add_allows(vec![authority]).await?;
+    job_server
+        .queue(Follow::new(pending.input, pending.actor))
+        .await?;
+
+    Ok(true)
+}
+
+/// Refuse a pending follow request
+///
+/// Returns false if there was no pending request from the actor
+pub(crate) async fn reject_follow(
+    db: &Db,
+    job_server: &JobServer,
+    actor_id: IriString,
+) -> Result<bool, Error> {
+    let Some(pending) = db.take_pending_follow(actor_id).await? else {
+        return Ok(false);
+    };
+
+    job_server
+        .queue(RejectFollow::new(pending.input, pending.actor))
+        .await?;
+
+    Ok(true)
+}
+
+/// The ids in an object's `attributedTo`, whether given as strings or embedded actors
+pub(crate) fn attributed_to(object: &Value) -> Vec<IriString> {
+    let authors = match object.get("attributedTo") {
+        Some(Value::Array(authors)) => authors.iter().collect(),
+        Some(author) => vec![author],
+        None => Vec::new(),
+    };
+
+    authors
+        .into_iter()
+        .filter_map(|author| match author {
+            Value::String(id) => Some(id.as_str()),
+            author => author.get("id").and_then(Value::as_str),
+        })
+        .filter_map(|id| id.parse::<IriString>().ok())
+        .collect()
+}
+
+/// Check whether an object is attributed to an actor that has been blocked
+async fn is_author_blocked(state: &State, object: &Value) -> Result<bool, Error> {
+    for author_id in attributed_to(object) {
+        if state.db.is_actor_blocked(author_id).await? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
 fn prepare_activity<T, U, V>(
     mut t: T,
     id: impl TryInto<IriString, Error = U>,
@@ -51,6 +133,26 @@ where
     Ok(t)
 }
 
+// Generate a type that says "Never mind, don't look at this object"
+fn generate_undo_announce(
+    config: &Config,
+    activity_id: &IriString,
+    object_id: &IriString,
+) -> Result<AsUndo, Error> {
+    let my_id = config.generate_url(UrlKind::Actor);
+    let mut announce = AsAnnounce::new(my_id.clone(), object_id.clone());
+
+    announce.set_id(activity_id.clone());
+
+    let undo = AsUndo::new(my_id, announce.into_any_base()?);
+
+    prepare_activity(
+        undo,
+        config.generate_url(UrlKind::Activity),
+        config.generate_url(UrlKind::Followers),
+    )
+}
+
 // Generate a type that says "I want to stop following you"
 fn generate_undo_follow(
     config: &Config,
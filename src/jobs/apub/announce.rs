@@ -1,11 +1,13 @@
 use crate::{
     config::{Config, UrlKind},
+    data::Visibility,
     db::Actor,
     error::Error,
     jobs::{
-        apub::{get_inboxes, prepare_activity},
+        apub::{attributed_to, get_inboxes, is_author_blocked, prepare_activity},
         DeliverMany, JobState,
     },
+    requests::BreakerStrategy,
 };
 use activitystreams::{activity::Announce as AsAnnounce, iri_string::types::IriString};
 use background_jobs::Job;
@@ -14,6 +16,8 @@ use background_jobs::Job;
 pub(crate) struct Announce {
     object_id: IriString,
     actor: Actor,
+    #[serde(default)]
+    check_visibility: bool,
 }
 
 impl std::fmt::Debug for Announce {
@@ -21,13 +25,18 @@ impl std::fmt::Debug for Announce {
         f.debug_struct("Announce")
             .field("object_id", &self.object_id.to_string())
             .field("actor_id", &self.actor.id)
+            .field("check_visibility", &self.check_visibility)
             .finish()
     }
 }
 
 impl Announce {
-    pub fn new(object_id: IriString, actor: Actor) -> Self {
-        Announce { object_id, actor }
+    pub fn new(object_id: IriString, actor: Actor, check_visibility: bool) -> Self {
+        Announce {
+            object_id,
+            actor,
+            check_visibility,
+        }
     }
 }
 
@@ -46,6 +55,22 @@ fn generate_announce(
     )
 }
 
+/// Whether a fetched object is the one asked for, and is attributed to someone on the sender's server
+fn belongs_to(object: &serde_json::Value, object_id: &IriString, actor_id: &IriString) -> bool {
+    let id_matches = object
+        .get("id")
+        .and_then(serde_json::Value::as_str)
+        .is_some_and(|id| id == object_id.as_str());
+
+    let authors = attributed_to(object);
+
+    id_matches
+        && !authors.is_empty()
+        && authors
+            .iter()
+            .all(|author| author.authority_str() == actor_id.authority_str())
+}
+
 impl Job for Announce {
     type State = JobState;
     type Error = Error;
@@ -55,16 +80,145 @@ impl Job for Announce {
 
     #[tracing::instrument(name = "Announce", skip(state))]
     async fn run(self, state: Self::State) -> Result<(), Self::Error> {
+        let filters = state.state.content_filters();
+        let has_actor_blocks = state.state.db.has_actor_blocks().await?;
+
+        // Objects on the sender's own server can't have been taken from another server
+        let check_origin = state.config.validate_object_origin()
+            && self.object_id.authority_str() != self.actor.id.authority_str();
+
+        // Unless the object is fetched anyway, take the sender of the `Create` to be its author
+        let mut authors = vec![self.actor.id.clone()];
+
+        if check_origin || self.check_visibility || !filters.is_empty() || has_actor_blocks {
+            // Without the object, none of the checks it's fetched for can be made
+            let object: serde_json::Value = match state
+                .state
+                .requests
+                .fetch(&self.object_id, BreakerStrategy::Require2XX)
+                .await
+            {
+                Ok(object) => object,
+                Err(e) => {
+                    tracing::warn!(
+                        "Dropping object {}, it couldn't be fetched: {e}",
+                        self.object_id
+                    );
+                    metrics::counter!("relay.announce.fetch-failed").increment(1);
+                    return Ok(());
+                }
+            };
+
+            if check_origin && !belongs_to(&object, &self.object_id, &self.actor.id) {
+                tracing::warn!(
+                    "Dropping object {}, {} announced it from another server",
+                    self.object_id,
+                    self.actor.id
+                );
+                metrics::counter!("relay.origin-check.rejected").increment(1);
+                return Ok(());
+            }
+
+            let attributed = attributed_to(&object);
+            if !attributed.is_empty() {
+                authors = attributed;
+            }
+
+            if self.check_visibility
+                && !state
+                    .config
+                    .relay_visibility()
+                    .allows(Visibility::of(&object))
+            {
+                tracing::info!(
+                    "Dropping object {}, it isn't addressed publicly",
+                    self.object_id
+                );
+                metrics::counter!("relay.visibility.dropped").increment(1);
+                return Ok(());
+            }
+
+            if filters.matches(&object) {
+                tracing::info!("Dropping filtered object {}", self.object_id);
+                metrics::counter!("relay.content-filter.dropped").increment(1);
+                return Ok(());
+            }
+
+            if has_actor_blocks && is_author_blocked(&state.state, &object).await? {
+                tracing::info!("Dropping object {} from blocked actor", self.object_id);
+                return Ok(());
+            }
+        }
+
+        for author_id in &authors {
+            let author = state
+                .actors
+                .get(author_id, &state.state.requests)
+                .await?
+                .into_inner();
+
+            if author.opted_out {
+                tracing::info!("Dropping object {}, its author opted out", self.object_id);
+                metrics::counter!("relay.opt-out.dropped").increment(1);
+                return Ok(());
+            }
+        }
+
         let activity_id = state.config.generate_url(UrlKind::Activity);
 
         let announce = generate_announce(&state.config, &activity_id, &self.object_id)?;
         let inboxes = get_inboxes(&state.state, &self.actor, &self.object_id).await?;
         state
             .job_server
-            .queue(DeliverMany::new(inboxes, announce)?)
+            .queue(DeliverMany::new(inboxes, &announce)?)
             .await?;
 
-        state.state.cache(self.object_id, activity_id);
-        Ok(())
+        state
+            .state
+            .db
+            .save_announce(serde_json::to_value(announce)?)
+            .await?;
+
+        state
+            .state
+            .cache(self.object_id, activity_id, self.actor.id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::belongs_to;
+    use activitystreams::iri_string::types::IriString;
+
+    fn iri(s: &str) -> IriString {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn objects_must_be_attributed_to_the_sender() {
+        let object_id = iri("https://media.example.com/objects/1");
+        let actor_id = iri("https://example.com/users/alice");
+
+        let object = serde_json::json!({
+            "id": "https://media.example.com/objects/1",
+            "attributedTo": [{ "id": "https://example.com/users/alice" }],
+        });
+        assert!(belongs_to(&object, &object_id, &actor_id));
+
+        let foreign = serde_json::json!({
+            "id": "https://media.example.com/objects/1",
+            "attributedTo": "https://elsewhere.example/users/bob",
+        });
+        assert!(!belongs_to(&foreign, &object_id, &actor_id));
+
+        let unattributed = serde_json::json!({ "id": "https://media.example.com/objects/1" });
+        assert!(!belongs_to(&unattributed, &object_id, &actor_id));
+
+        let swapped = serde_json::json!({
+            "id": "https://media.example.com/objects/2",
+            "attributedTo": "https://example.com/users/alice",
+        });
+        assert!(!belongs_to(&swapped, &object_id, &actor_id));
     }
 }
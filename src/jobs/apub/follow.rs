@@ -4,6 +4,7 @@ use crate::{
     db::Actor,
     error::{Error, ErrorKind},
     jobs::{apub::prepare_activity, Deliver, JobState, QueryInstance, QueryNodeinfo},
+    notify::Event,
 };
 use activitystreams::{
     activity::{Accept as AsAccept, Follow as AsFollow},
@@ -79,19 +80,25 @@ impl Job for Follow {
     async fn run(self, state: Self::State) -> Result<(), Self::Error> {
         let my_id = state.config.generate_url(UrlKind::Actor);
 
+        let was_connected = state.state.db.is_connected(self.actor.id.clone()).await?;
+
         // if following relay directly, not just following 'public', followback
-        if self.input.object_is(&my_id)
-            && !state.state.db.is_connected(self.actor.id.clone()).await?
-        {
+        if self.input.object_is(&my_id) && !was_connected {
             let follow = generate_follow(&state.config, &self.actor.id, &my_id)?;
             state
                 .job_server
-                .queue(Deliver::new(self.actor.inbox.clone(), follow)?)
+                .deliver(Deliver::new(self.actor.inbox.clone(), follow)?)
                 .await?;
         }
 
         state.actors.add_connection(self.actor.clone()).await?;
 
+        if !was_connected {
+            state.notifier.notify(Event::Subscribed {
+                actor_id: self.actor.id.clone(),
+            });
+        }
+
         let accept = generate_accept_follow(
             &state.config,
             &self.actor.id,
@@ -101,7 +108,7 @@ impl Job for Follow {
 
         state
             .job_server
-            .queue(Deliver::new(self.actor.inbox, accept)?)
+            .deliver(Deliver::new(self.actor.inbox, accept)?)
             .await?;
 
         state
@@ -2,7 +2,10 @@ use crate::{
     apub::AcceptedActivities,
     db::Actor,
     error::{Error, ErrorKind},
-    jobs::{apub::get_inboxes, DeliverMany, JobState},
+    jobs::{
+        apub::{get_inboxes, is_author_blocked},
+        DeliverMany, JobState,
+    },
 };
 use activitystreams::prelude::*;
 use background_jobs::Job;
@@ -43,6 +46,14 @@ impl Job for Forward {
             .as_single_id()
             .ok_or(ErrorKind::MissingId)?;
 
+        let input = serde_json::to_value(&self.input)?;
+        if let Some(object) = input.get("object") {
+            if is_author_blocked(&state.state, object).await? {
+                tracing::info!("Not forwarding object {object_id} from blocked actor");
+                return Ok(());
+            }
+        }
+
         let inboxes = get_inboxes(&state.state, &self.actor, object_id).await?;
 
         state
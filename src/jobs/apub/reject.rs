@@ -1,8 +1,9 @@
 use crate::{
     config::UrlKind,
-    db::Actor,
+    db::{Actor, AuditAction, AuditEntry},
     error::Error,
     jobs::{apub::generate_undo_follow, Deliver, JobState},
+    notify::Event,
 };
 use background_jobs::Job;
 
@@ -24,14 +25,28 @@ impl Job for Reject {
 
     #[tracing::instrument(name = "Reject", skip(state))]
     async fn run(self, state: Self::State) -> Result<(), Self::Error> {
+        state
+            .state
+            .db
+            .audit(AuditEntry::new(
+                self.0.id.to_string(),
+                AuditAction::Disconnect,
+                vec![self.0.id.to_string()],
+                Some(String::from("Rejected the relay's follow")),
+            ))
+            .await?;
         state.actors.remove_connection(&self.0).await?;
 
+        state.notifier.notify(Event::Unsubscribed {
+            actor_id: self.0.id.clone(),
+        });
+
         let my_id = state.config.generate_url(UrlKind::Actor);
         let undo = generate_undo_follow(&state.config, &self.0.id, &my_id)?;
 
         state
             .job_server
-            .queue(Deliver::new(self.0.inbox, undo)?)
+            .deliver(Deliver::new(self.0.inbox, undo)?)
             .await?;
 
         Ok(())
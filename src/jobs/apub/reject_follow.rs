@@ -0,0 +1,81 @@
+use crate::{
+    apub::AcceptedActivities,
+    config::{Config, UrlKind},
+    db::Actor,
+    error::{Error, ErrorKind},
+    jobs::{apub::prepare_activity, Deliver, JobState},
+};
+use activitystreams::{
+    activity::{Follow as AsFollow, Reject as AsReject},
+    iri_string::types::IriString,
+    prelude::*,
+};
+use background_jobs::Job;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub(crate) struct RejectFollow {
+    input: AcceptedActivities,
+    actor: Actor,
+}
+
+impl std::fmt::Debug for RejectFollow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RejectFollow")
+            .field("input", &self.input.id_unchecked())
+            .field("actor", &self.actor.id)
+            .finish()
+    }
+}
+
+impl RejectFollow {
+    pub fn new(input: AcceptedActivities, actor: Actor) -> Self {
+        RejectFollow { input, actor }
+    }
+}
+
+// Generate a type that says "I reject your follow request"
+fn generate_reject_follow(
+    config: &Config,
+    actor_id: &IriString,
+    input_id: &IriString,
+    my_id: &IriString,
+) -> Result<AsReject, Error> {
+    let mut follow = AsFollow::new(actor_id.clone(), my_id.clone());
+
+    follow.set_id(input_id.clone());
+
+    let reject = AsReject::new(my_id.clone(), follow.into_any_base()?);
+
+    prepare_activity(
+        reject,
+        config.generate_url(UrlKind::Activity),
+        actor_id.clone(),
+    )
+}
+
+impl Job for RejectFollow {
+    type State = JobState;
+    type Error = Error;
+
+    const NAME: &'static str = "relay::jobs::apub::RejectFollow";
+    const QUEUE: &'static str = "apub";
+
+    #[tracing::instrument(name = "Reject follow", skip(state))]
+    async fn run(self, state: Self::State) -> Result<(), Self::Error> {
+        let my_id = state.config.generate_url(UrlKind::Actor);
+
+        let reject = generate_reject_follow(
+            &state.config,
+            &self.actor.id,
+            self.input.id_unchecked().ok_or(ErrorKind::MissingId)?,
+            &my_id,
+        )?;
+
+        state
+            .job_server
+            .deliver(Deliver::new(self.actor.inbox, reject)?)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -1,9 +1,10 @@
 use crate::{
     apub::AcceptedActivities,
     config::UrlKind,
-    db::Actor,
+    db::{Actor, AuditAction, AuditEntry},
     error::Error,
     jobs::{apub::generate_undo_follow, Deliver, JobState},
+    notify::Event,
 };
 use activitystreams::prelude::BaseExt;
 use background_jobs::Job;
@@ -40,14 +41,31 @@ impl Job for Undo {
     async fn run(self, state: Self::State) -> Result<(), Self::Error> {
         let was_following = state.state.db.is_connected(self.actor.id.clone()).await?;
 
+        if was_following {
+            state
+                .state
+                .db
+                .audit(AuditEntry::new(
+                    self.actor.id.to_string(),
+                    AuditAction::Disconnect,
+                    vec![self.actor.id.to_string()],
+                    Some(String::from("Unfollowed the relay")),
+                ))
+                .await?;
+        }
+
         state.actors.remove_connection(&self.actor).await?;
 
         if was_following {
+            state.notifier.notify(Event::Unsubscribed {
+                actor_id: self.actor.id.clone(),
+            });
+
             let my_id = state.config.generate_url(UrlKind::Actor);
             let undo = generate_undo_follow(&state.config, &self.actor.id, &my_id)?;
             state
                 .job_server
-                .queue(Deliver::new(self.actor.inbox, undo)?)
+                .deliver(Deliver::new(self.actor.inbox, undo)?)
                 .await?;
         }
 
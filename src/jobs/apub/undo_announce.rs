@@ -0,0 +1,76 @@
+use crate::{
+    db::Actor,
+    error::Error,
+    jobs::{
+        apub::{generate_undo_announce, get_inboxes},
+        DeliverMany, JobState,
+    },
+};
+use activitystreams::iri_string::types::IriString;
+use background_jobs::Job;
+
+/// Take back the relay's Announce of an object that was deleted, or whose Announce was undone
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub(crate) struct UndoAnnounce {
+    object_id: IriString,
+    actor: Actor,
+}
+
+impl std::fmt::Debug for UndoAnnounce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UndoAnnounce")
+            .field("object_id", &self.object_id.to_string())
+            .field("actor_id", &self.actor.id)
+            .finish()
+    }
+}
+
+impl UndoAnnounce {
+    pub fn new(object_id: IriString, actor: Actor) -> Self {
+        UndoAnnounce { object_id, actor }
+    }
+}
+
+impl Job for UndoAnnounce {
+    type State = JobState;
+    type Error = Error;
+
+    const NAME: &'static str = "relay::jobs::apub::UndoAnnounce";
+    const QUEUE: &'static str = "apub";
+
+    #[tracing::instrument(name = "Undo announce", skip(state))]
+    async fn run(self, state: Self::State) -> Result<(), Self::Error> {
+        let Some(relayed) = state
+            .state
+            .db
+            .relayed_object(self.object_id.clone())
+            .await?
+        else {
+            return Ok(());
+        };
+
+        // Only the actor that got the object relayed, or the object's own server, may take it back
+        if relayed.actor_id != self.actor.id
+            && self.object_id.authority_str() != self.actor.id.authority_str()
+        {
+            tracing::info!(
+                "Not undoing announce of {} for unrelated actor",
+                self.object_id
+            );
+            return Ok(());
+        }
+
+        let undo = generate_undo_announce(&state.config, &relayed.activity_id, &self.object_id)?;
+        let inboxes = get_inboxes(&state.state, &self.actor, &self.object_id).await?;
+
+        state
+            .job_server
+            .queue(DeliverMany::new(inboxes, &undo)?)
+            .await?;
+
+        state.state.uncache(&self.object_id).await?;
+        metrics::counter!("relay.announce.undone").increment(1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{
+    apub::{relay_actor, RelayActor},
+    config::{Config, UrlKind},
+    error::Error,
+    jobs::{apub::prepare_activity, DeliverMany, JobState},
+};
+use activitystreams::{activity::Update as AsUpdate, prelude::*};
+use background_jobs::Job;
+
+/// Tell every subscriber the relay's actor changed, so they fetch its new key
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct UpdateActor;
+
+// Generate a type that says "Here's what I look like now"
+fn generate_update(config: &Config, actor: RelayActor) -> Result<AsUpdate, Error> {
+    let update = AsUpdate::new(config.generate_url(UrlKind::Actor), actor.into_any_base()?);
+
+    prepare_activity(
+        update,
+        config.generate_url(UrlKind::Activity),
+        config.generate_url(UrlKind::Followers),
+    )
+}
+
+impl Job for UpdateActor {
+    type State = JobState;
+    type Error = Error;
+
+    const NAME: &'static str = "relay::jobs::apub::UpdateActor";
+    const QUEUE: &'static str = "apub";
+
+    #[tracing::instrument(name = "Update actor", skip(state))]
+    async fn run(self, state: Self::State) -> Result<(), Self::Error> {
+        let retired = state
+            .state
+            .retired_keys(state.config.key_rotation_grace())
+            .await?;
+        let actor = relay_actor(&state.config, &state.state.requests, &retired)?;
+        let update = generate_update(&state.config, actor)?;
+
+        let mut inboxes = state.state.db.inboxes().await?;
+        inboxes.sort();
+        inboxes.dedup();
+
+        state
+            .job_server
+            .queue(DeliverMany::new(inboxes, &update)?)
+            .await
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{error::Error, jobs::JobState, notify::Event};
+use background_jobs::{Backoff, Job};
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct CheckBacklog;
+
+impl Job for CheckBacklog {
+    type State = JobState;
+    type Error = Error;
+
+    const NAME: &'static str = "relay::jobs::CheckBacklog";
+    const QUEUE: &'static str = "maintenance";
+    const BACKOFF: Backoff = Backoff::Linear(1);
+
+    #[tracing::instrument(skip(state))]
+    async fn run(self, state: Self::State) -> Result<(), Self::Error> {
+        let threshold = state.config.deliver_backlog_alert();
+
+        if let Some(queued) = state.state.deliver_queues.check_backlog(threshold) {
+            state.notifier.notify(Event::QueueBackedUp { queued });
+        }
+
+        Ok(())
+    }
+}
@@ -23,6 +23,10 @@ impl std::fmt::Debug for Deliver {
 }
 
 impl Deliver {
+    pub(crate) fn inbox(&self) -> &IriString {
+        &self.to
+    }
+
     pub(crate) fn new<T>(to: IriString, data: T) -> Result<Self, Error>
     where
         T: serde::ser::Serialize,
@@ -44,12 +48,24 @@ impl Job for Deliver {
 
     #[tracing::instrument(name = "Deliver", skip(state))]
     async fn run(self, state: Self::State) -> Result<(), Self::Error> {
+        let _permit = match state.state.deliver_queues.try_start(&self.to) {
+            Ok(permit) => permit,
+            Err(wait) => {
+                tracing::debug!("Too many deliveries in flight for domain, deferring");
+                return state.job_server.schedule(self, wait).await;
+            }
+        };
+
         if let Err(e) = state
             .state
             .requests
             .deliver(&self.to, &self.data, BreakerStrategy::Allow401AndBelow)
             .await
         {
+            if let Some(wait) = state.state.requests.paused_for(&self.to) {
+                tracing::debug!("Domain asked us to hold off, deferring");
+                return state.job_server.schedule(self, wait).await;
+            }
             if e.is_breaker() {
                 tracing::debug!("Not trying due to failed breaker");
                 return Ok(());
@@ -44,7 +44,7 @@ impl Job for DeliverMany {
         for inbox in self.to {
             state
                 .job_server
-                .queue(Deliver::new(inbox, self.data.clone())?)
+                .deliver(Deliver::new(inbox, self.data.clone())?)
                 .await?;
         }
 
@@ -0,0 +1,25 @@
+use crate::{error::Error, jobs::JobState};
+use background_jobs::{Backoff, Job};
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct ExpireRelayedObjects;
+
+impl Job for ExpireRelayedObjects {
+    type State = JobState;
+    type Error = Error;
+
+    const NAME: &'static str = "relay::jobs::ExpireRelayedObjects";
+    const QUEUE: &'static str = "maintenance";
+    const BACKOFF: Backoff = Backoff::Linear(1);
+
+    #[tracing::instrument(skip(state))]
+    async fn run(self, state: Self::State) -> Result<(), Self::Error> {
+        let cutoff = OffsetDateTime::now_utc() - state.config.announce_cache_ttl();
+        let expired = state.state.db.expire_relayed_objects(cutoff).await?;
+
+        tracing::debug!("Expired {expired} relayed objects");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{error::Error, jobs::JobState, notify::Event};
+use background_jobs::{Backoff, Job};
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct RecordBreakers;
+
+impl Job for RecordBreakers {
+    type State = JobState;
+    type Error = Error;
+
+    const NAME: &'static str = "relay::jobs::RecordBreakers";
+    const QUEUE: &'static str = "maintenance";
+    const BACKOFF: Backoff = Backoff::Linear(1);
+
+    #[tracing::instrument(skip(state))]
+    async fn run(self, state: Self::State) -> Result<(), Self::Error> {
+        let breakers = state.state.breakers.take_dirty();
+
+        state.state.db.save_breakers(breakers).await?;
+
+        let signature_schemes = state.state.signature_schemes.take_dirty();
+
+        if !signature_schemes.is_empty() {
+            state
+                .state
+                .db
+                .save_signature_schemes(signature_schemes)
+                .await?;
+        }
+
+        let backlog = state.state.deliver_queues.backlog();
+
+        state.state.db.save_deliver_backlog(backlog).await?;
+
+        for domain in state.state.breakers.take_tripped() {
+            state.notifier.notify(Event::BreakerTripped { domain });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{
+    data::blocklist,
+    db::{AuditAction, AuditEntry},
+    error::Error,
+    jobs::JobState,
+    notify::Event,
+    requests::BreakerStrategy,
+    stream::{aggregate, limit_stream},
+};
+use activitystreams::iri_string::types::IriString;
+use background_jobs::{Backoff, Job};
+
+// 8 MB
+const BLOCKLIST_SIZE_LIMIT: usize = 8 * 1024 * 1024;
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct SyncBlocklists;
+
+impl SyncBlocklists {
+    async fn sync(state: &JobState, url: &IriString) -> Result<(), Error> {
+        let res = state
+            .state
+            .requests
+            .fetch_response(url, BreakerStrategy::Require2XX)
+            .await?;
+
+        let bytes = aggregate(limit_stream(res.bytes_stream(), BLOCKLIST_SIZE_LIMIT)).await?;
+        let blocks = blocklist::parse(&bytes)?;
+
+        let (added, removed) = state.state.db.sync_blocks(url.to_string(), blocks).await?;
+
+        tracing::info!("Synced blocklist {url}, {added} added or updated, {removed} removed");
+
+        if added > 0 || removed > 0 {
+            let entry = AuditEntry::new(
+                String::from("blocklist sync"),
+                AuditAction::SyncBlocks,
+                vec![url.to_string()],
+                Some(format!("{added} added or updated, {removed} removed")),
+            );
+
+            state.state.db.audit(entry.clone()).await?;
+            state.notifier.notify(Event::AdminAction { entry });
+        }
+
+        Ok(())
+    }
+}
+
+impl Job for SyncBlocklists {
+    type State = JobState;
+    type Error = Error;
+
+    const NAME: &'static str = "relay::jobs::SyncBlocklists";
+    const QUEUE: &'static str = "maintenance";
+    const BACKOFF: Backoff = Backoff::Linear(1);
+
+    #[tracing::instrument(skip(state))]
+    async fn run(self, state: Self::State) -> Result<(), Self::Error> {
+        for url in state.config.blocklist_urls() {
+            if let Err(e) = Self::sync(&state, url).await {
+                tracing::warn!("Failed to sync blocklist {url}: {e}");
+                metrics::counter!("relay.blocklist-sync.failed", "url" => url.to_string())
+                    .increment(1);
+            }
+        }
+
+        Ok(())
+    }
+}
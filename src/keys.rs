@@ -0,0 +1,119 @@
+use crate::error::{Error, ErrorKind};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::signature::{
+    UnparsedPublicKey, VerificationAlgorithm, ED25519, RSA_PKCS1_2048_8192_SHA256,
+    RSA_PSS_2048_8192_SHA512,
+};
+use rsa::{pkcs1::EncodeRsaPublicKey, pkcs8::DecodePublicKey, RsaPublicKey};
+
+// The DER SubjectPublicKeyInfo header of an Ed25519 key, followed by the 32 key bytes
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+// The multicodec header of an Ed25519 public key
+const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+
+/// A remote actor's public key, in a form ring can verify signatures with
+#[derive(Clone, Debug)]
+pub(crate) enum VerifyingKey {
+    /// A PKCS#1 DER encoded RSA key
+    Rsa(Vec<u8>),
+    Ed25519([u8; 32]),
+}
+
+impl VerifyingKey {
+    /// Read a `publicKeyPem`, which may hold either an RSA or an Ed25519 key
+    pub(crate) fn from_pem(pem: &str) -> Result<Self, Error> {
+        let rsa_error = match RsaPublicKey::from_public_key_pem(pem.trim()) {
+            Ok(key) => {
+                let der = key.to_pkcs1_der().map_err(|_| ErrorKind::DerEncode)?;
+                return Ok(VerifyingKey::Rsa(der.as_bytes().to_vec()));
+            }
+            Err(e) => e,
+        };
+
+        let encoded = pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .map(str::trim)
+            .collect::<String>();
+
+        STANDARD
+            .decode(encoded)
+            .ok()
+            .and_then(|der| {
+                der.strip_prefix(&ED25519_SPKI_PREFIX)
+                    .and_then(|key| key.try_into().ok())
+            })
+            .map(VerifyingKey::Ed25519)
+            .ok_or_else(|| rsa_error.into())
+    }
+
+    /// Read the `publicKeyMultibase` of an Ed25519 `Multikey`
+    pub(crate) fn from_multibase(multibase: &str) -> Option<Self> {
+        let bytes = bs58::decode(multibase.strip_prefix('z')?).into_vec().ok()?;
+        let key = bytes.strip_prefix(&ED25519_MULTICODEC)?;
+
+        Some(VerifyingKey::Ed25519(key.try_into().ok()?))
+    }
+
+    /// Verify a signature made with the named algorithm, or the key's usual one if none is named
+    pub(crate) fn verify(
+        &self,
+        alg: Option<&str>,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        let (algorithm, key): (&'static dyn VerificationAlgorithm, &[u8]) = match (self, alg) {
+            (VerifyingKey::Rsa(der), None | Some("rsa-v1_5-sha256")) => {
+                (&RSA_PKCS1_2048_8192_SHA256, der)
+            }
+            (VerifyingKey::Rsa(der), Some("rsa-pss-sha512")) => (&RSA_PSS_2048_8192_SHA512, der),
+            (VerifyingKey::Ed25519(key), None | Some("ed25519")) => (&ED25519, key),
+            (_, Some(alg)) => return Err(ErrorKind::Algorithm(alg.to_owned()).into()),
+        };
+
+        UnparsedPublicKey::new(algorithm, key)
+            .verify(message, signature)
+            .map_err(|_| ErrorKind::VerifySignature.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VerifyingKey;
+    use ring::{
+        rand::SystemRandom,
+        signature::{Ed25519KeyPair, KeyPair},
+    };
+
+    #[test]
+    fn reads_ed25519_keys() {
+        // From RFC 9421, appendix B.1.4
+        let pem = "-----BEGIN PUBLIC KEY-----\nMCowBQYDK2VwAyEAJrQLj5P/89iXES9+vFgrIy29clF9CC/oPPsw3c5D0bs=\n-----END PUBLIC KEY-----\n";
+        assert!(matches!(
+            VerifyingKey::from_pem(pem),
+            Ok(VerifyingKey::Ed25519(_))
+        ));
+
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+
+        let mut multikey = vec![0xed, 0x01];
+        multikey.extend_from_slice(pair.public_key().as_ref());
+        let multibase = format!("z{}", bs58::encode(multikey).into_string());
+
+        let key = VerifyingKey::from_multibase(&multibase).unwrap();
+        let signature = pair.sign(b"hello");
+
+        assert!(key.verify(None, b"hello", signature.as_ref()).is_ok());
+        assert!(key
+            .verify(Some("ed25519"), b"hello", signature.as_ref())
+            .is_ok());
+        assert!(key.verify(None, b"goodbye", signature.as_ref()).is_err());
+        assert!(key
+            .verify(Some("rsa-v1_5-sha256"), b"hello", signature.as_ref())
+            .is_err());
+    }
+}
@@ -29,14 +29,18 @@ mod args;
 mod collector;
 mod config;
 mod data;
+mod data_integrity;
 mod db;
 mod error;
 mod extractors;
 mod future;
 mod http1;
 mod jobs;
+mod keys;
 mod middleware;
+mod notify;
 mod requests;
+mod rfc9421;
 mod routes;
 mod spawner;
 mod stream;
@@ -50,8 +54,12 @@ use self::{
     data::{ActorCache, MediaCache, State},
     db::Db,
     jobs::create_workers,
-    middleware::{DebugPayload, MyVerify, Timings},
-    routes::{actor, healthz, inbox, index, nodeinfo, nodeinfo_meta, statics, webfinger},
+    middleware::{DebugPayload, InboxLimits, MyVerify, Timings, VerifyMessageSignature},
+    notify::Notifier,
+    routes::{
+        actor, actor_key, followers, following, healthz, inbox, index, nodeinfo, nodeinfo_meta,
+        outbox, statics, webfinger,
+    },
     spawner::Spawner,
 };
 
@@ -206,12 +214,90 @@ async fn do_client_main(config: Config, args: Args) -> color_eyre::Result<()> {
             admin::client::unblock(&client, &config, args.blocks().to_vec()).await?;
             admin::client::disallow(&client, &config, args.allowed().to_vec()).await?;
         } else {
-            admin::client::block(&client, &config, args.blocks().to_vec()).await?;
+            admin::client::block(
+                &client,
+                &config,
+                args.blocks().to_vec(),
+                args.severity(),
+                args.reason(),
+            )
+            .await?;
             admin::client::allow(&client, &config, args.allowed().to_vec()).await?;
         }
         println!("Updated lists");
     }
 
+    if let Some(path) = args.import_blocks() {
+        let csv = std::fs::read_to_string(path)?;
+        let count = admin::client::import_blocks(&client, &config, &csv).await?;
+        println!("Imported {count} blocks");
+    }
+
+    if let Some(path) = args.export_blocks() {
+        let csv = admin::client::export_blocks(&client, &config).await?;
+        std::fs::write(path, csv)?;
+        println!("Exported blocks to {}", path.display());
+    }
+
+    if !args.approve().is_empty() {
+        admin::client::approve_follows(&client, &config, args.approve().to_vec()).await?;
+        println!("Approved follow requests");
+    }
+
+    if !args.reject().is_empty() {
+        admin::client::reject_follows(&client, &config, args.reject().to_vec()).await?;
+        println!("Rejected follow requests");
+    }
+
+    if args.pending() {
+        let pending = admin::client::pending_follows(&client, &config).await?;
+
+        let mut report = String::from("Pending follow requests:\n");
+
+        for pending in pending.pending_follows {
+            report += "\t";
+            report += pending.actor_id.as_str();
+            report += " - requested ";
+            report += &pending.requested_at.to_string();
+            report += "\n";
+        }
+
+        println!("{report}");
+    }
+
+    if !args.block_actors().is_empty() {
+        if args.undo() {
+            admin::client::unblock_actors(&client, &config, args.block_actors().to_vec()).await?;
+        } else {
+            admin::client::block_actors(&client, &config, args.block_actors().to_vec()).await?;
+        }
+        println!("Updated actor blocks");
+    }
+
+    let filters = args.content_filters();
+    if !filters.is_empty() {
+        if args.undo() {
+            admin::client::unfilter(&client, &config, filters).await?;
+        } else {
+            admin::client::filter(&client, &config, filters).await?;
+        }
+        println!("Updated filters");
+    }
+
+    if args.filters() {
+        let filters = admin::client::filters(&client, &config).await?;
+
+        let mut report = String::from("Filters:\n");
+
+        for filter in filters.filters {
+            report += "\t";
+            report += &filter.to_string();
+            report += "\n";
+        }
+
+        println!("{report}");
+    }
+
     if args.contacted() {
         let last_seen = admin::client::last_seen(&client, &config).await?;
 
@@ -246,8 +332,9 @@ async fn do_client_main(config: Config, args: Args) -> color_eyre::Result<()> {
     }
 
     if args.list() {
-        let (blocked, allowed, connected) = tokio::try_join!(
+        let (blocked, blocked_actors, allowed, connected) = tokio::try_join!(
             admin::client::blocked(&client, &config),
+            admin::client::blocked_actors(&client, &config),
             admin::client::allowed(&client, &config),
             admin::client::connected(&client, &config)
         )?;
@@ -257,9 +344,28 @@ async fn do_client_main(config: Config, args: Args) -> color_eyre::Result<()> {
             report += "\nAllowed\n\t";
             report += &allowed.allowed_domains.join("\n\t");
         }
-        if !blocked.blocked_domains.is_empty() {
-            report += "\n\nBlocked\n\t";
-            report += &blocked.blocked_domains.join("\n\t");
+        if !blocked.blocks.is_empty() {
+            report += "\n\nBlocked";
+            for block in &blocked.blocks {
+                report += "\n\t";
+                report += &block.domain;
+                report += " (";
+                report += block.severity.as_str();
+                report += ")";
+                if let Some(reason) = &block.reason {
+                    report += " - ";
+                    report += reason;
+                }
+                if let Some(source) = &block.source {
+                    report += " [from ";
+                    report += source;
+                    report += "]";
+                }
+            }
+        }
+        if !blocked_actors.blocked_actors.is_empty() {
+            report += "\n\nBlocked actors\n\t";
+            report += &blocked_actors.blocked_actors.join("\n\t");
         }
         if !connected.connected_actors.is_empty() {
             report += "\n\nConnected\n\t";
@@ -274,6 +380,124 @@ async fn do_client_main(config: Config, args: Args) -> color_eyre::Result<()> {
         stats.present();
     }
 
+    if !args.reset_breakers().is_empty() {
+        admin::client::reset_breakers(&client, &config, args.reset_breakers().to_vec()).await?;
+        println!("Reset breakers");
+    }
+
+    if args.breakers() {
+        let breakers = admin::client::breakers(&client, &config).await?;
+
+        let mut report = String::from("Breakers:\n");
+
+        for breaker in breakers.breakers {
+            report += "\t";
+            report += &breaker.domain;
+            report += " - failures: ";
+            report += &breaker.failures.to_string();
+            report += ", last attempt: ";
+            report += &breaker.last_attempt.to_string();
+            report += ", last success: ";
+            report += &breaker.last_success.to_string();
+            if let Some(paused_until) = breaker.paused_until {
+                report += " (paused until ";
+                report += &paused_until.to_string();
+                report += ")";
+            } else if !breaker.should_try {
+                report += " (tripped)";
+            }
+            report += "\n";
+        }
+
+        println!("{report}");
+    }
+
+    if args.rotate_key() {
+        let rotated = admin::client::rotate_key(&client, &config).await?;
+        println!("Rotated key, now signing with {}", rotated.key_id);
+    }
+
+    if let Some(name) = args.create_token() {
+        let scopes = args.scopes().iter().copied().collect();
+        let created =
+            admin::client::create_token(&client, &config, name.to_owned(), scopes).await?;
+
+        println!("Created token {}, it won't be shown again:", created.id);
+        println!("{}", created.token);
+    }
+
+    if !args.revoke_tokens().is_empty() {
+        admin::client::revoke_tokens(&client, &config, args.revoke_tokens().to_vec()).await?;
+        println!("Revoked tokens");
+    }
+
+    if args.tokens() {
+        let tokens = admin::client::tokens(&client, &config).await?;
+
+        let mut report = String::from("Tokens:\n");
+
+        for token in tokens.tokens {
+            let scopes = token
+                .scopes
+                .iter()
+                .map(|scope| scope.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            report += "\t";
+            report += &token.id;
+            report += " - ";
+            report += &token.name;
+            report += " (";
+            report += &scopes;
+            report += "), created ";
+            report += &token.created_at.to_string();
+            report += ", last used ";
+            if let Some(last_used) = token.last_used {
+                report += &last_used.to_string();
+            } else {
+                report += "never";
+            }
+            report += "\n";
+        }
+
+        println!("{report}");
+    }
+
+    if args.audit_log() {
+        let audit_log =
+            admin::client::audit_log(&client, &config, args.before(), args.limit()).await?;
+
+        let mut report = String::from("Audit log:\n");
+
+        for entry in audit_log.entries {
+            report += "\t";
+            report += &entry.at.to_string();
+            report += " - ";
+            report += &entry.actor;
+            report += " ";
+            report += entry.action.as_str();
+            if !entry.targets.is_empty() {
+                report += " ";
+                report += &entry.targets.join(", ");
+            }
+            if let Some(reason) = entry.reason {
+                report += " (";
+                report += &reason;
+                report += ")";
+            }
+            report += "\n";
+        }
+
+        if let Some(next) = audit_log.next {
+            report += "Older entries can be listed with --before ";
+            report += &next.to_string();
+            report += "\n";
+        }
+
+        println!("{report}");
+    }
+
     Ok(())
 }
 
@@ -309,12 +533,47 @@ async fn server_main(
     let sign_spawner = Spawner::build("sign-cpu", signature_threads.try_into()?)?;
 
     let key_id = config.generate_url(UrlKind::MainKey).to_string();
-    let state = State::build(db.clone(), key_id, sign_spawner.clone(), client).await?;
+    let state = State::build(
+        db.clone(),
+        key_id,
+        sign_spawner.clone(),
+        client.clone(),
+        config.deliver_domain_concurrency().try_into()?,
+        collector.clone(),
+        config.inbox_limits(),
+        config.breaker_schedule(),
+    )
+    .await?;
+
+    jobs::restore_deliver_backlog(&state).await?;
+
+    let job_store = jobs::build_storage(&db);
 
-    if let Some((token, admin_handle)) = config.telegram_info() {
+    let notifier = Notifier::build(&config, client.clone())?;
+
+    let notifier = if let Some((token, access)) = config.telegram_info() {
         tracing::info!("Creating telegram handler");
-        telegram::start(admin_handle.to_owned(), db.clone(), token);
-    }
+        let job_server = jobs::create_queue(
+            job_store.clone(),
+            state.clone(),
+            actors.clone(),
+            media.clone(),
+            config.clone(),
+        )?;
+
+        let telegram = telegram::start(
+            access.clone(),
+            state.clone(),
+            job_server,
+            collector.clone(),
+            notifier.clone(),
+            token,
+        );
+
+        notifier.with_telegram(telegram, config.telegram_events().clone())
+    } else {
+        notifier
+    };
 
     let cert_resolver = config
         .open_keys()
@@ -324,8 +583,8 @@ async fn server_main(
     let bind_address = config.bind_address();
     let sign_spawner2 = sign_spawner.clone();
     let verify_spawner2 = verify_spawner.clone();
+    let state2 = state.clone();
     let config2 = config.clone();
-    let job_store = jobs::build_storage();
     let server = HttpServer::new(move || {
         let job_server = create_workers(
             job_store.clone(),
@@ -333,6 +592,7 @@ async fn server_main(
             actors.clone(),
             media.clone(),
             config.clone(),
+            notifier.clone(),
         )
         .expect("Failed to create job server");
 
@@ -347,6 +607,7 @@ async fn server_main(
             .app_data(web::Data::new(job_server))
             .app_data(web::Data::new(media.clone()))
             .app_data(web::Data::new(collector.clone()))
+            .app_data(web::Data::new(notifier.clone()))
             .app_data(web::Data::new(verify_spawner.clone()));
 
         let app = if let Some(data) = config.admin_config() {
@@ -374,9 +635,23 @@ async fn server_main(
                         http_signature_normalization_actix::Config::new(),
                     ))
                     .wrap(DebugPayload(config.debug()))
+                    .wrap(VerifyMessageSignature::new(
+                        MyVerify(
+                            state.requests.clone().spawner(verify_spawner.clone()),
+                            actors.clone(),
+                            state.clone(),
+                            verify_spawner.clone(),
+                        ),
+                        &config,
+                    ))
+                    .wrap(InboxLimits::new(state.clone(), &config))
                     .route(web::post().to(inbox)),
             )
             .service(web::resource("/actor").route(web::get().to(actor)))
+            .service(web::resource("/actor/keys/{uuid}").route(web::get().to(actor_key)))
+            .service(web::resource("/outbox").route(web::get().to(outbox)))
+            .service(web::resource("/followers").route(web::get().to(followers)))
+            .service(web::resource("/following").route(web::get().to(following)))
             .service(web::resource("/nodeinfo/2.0.json").route(web::get().to(nodeinfo)))
             .service(
                 web::scope("/.well-known")
@@ -384,6 +659,17 @@ async fn server_main(
                     .service(web::resource("/nodeinfo").route(web::get().to(nodeinfo_meta))),
             )
             .service(web::resource("/static/{filename}").route(web::get().to(statics)))
+            .service(
+                web::scope("/admin")
+                    .route("", web::get().to(admin::dashboard::dashboard))
+                    .route("/login", web::get().to(admin::dashboard::login_page))
+                    .route("/login", web::post().to(admin::dashboard::login))
+                    .route("/logout", web::post().to(admin::dashboard::logout))
+                    .route("/block", web::post().to(admin::dashboard::block))
+                    .route("/unblock", web::post().to(admin::dashboard::unblock))
+                    .route("/allow", web::post().to(admin::dashboard::allow))
+                    .route("/disallow", web::post().to(admin::dashboard::disallow)),
+            )
             .service(
                 web::scope("/api/v1").service(
                     web::scope("/admin")
@@ -393,9 +679,53 @@ async fn server_main(
                         .route("/unblock", web::post().to(admin::routes::unblock))
                         .route("/allowed", web::get().to(admin::routes::allowed))
                         .route("/blocked", web::get().to(admin::routes::blocked))
+                        .route(
+                            "/import_blocks",
+                            web::post().to(admin::routes::import_blocks),
+                        )
+                        .route(
+                            "/pending_follows",
+                            web::get().to(admin::routes::pending_follows),
+                        )
+                        .route(
+                            "/pending_follows/approve",
+                            web::post().to(admin::routes::approve_follows),
+                        )
+                        .route(
+                            "/pending_follows/reject",
+                            web::post().to(admin::routes::reject_follows),
+                        )
+                        .route("/block_actors", web::post().to(admin::routes::block_actors))
+                        .route(
+                            "/unblock_actors",
+                            web::post().to(admin::routes::unblock_actors),
+                        )
+                        .route(
+                            "/blocked_actors",
+                            web::get().to(admin::routes::blocked_actors),
+                        )
+                        .route("/filter", web::post().to(admin::routes::filter))
+                        .route("/unfilter", web::post().to(admin::routes::unfilter))
+                        .route("/filters", web::get().to(admin::routes::filters))
                         .route("/connected", web::get().to(admin::routes::connected))
                         .route("/stats", web::get().to(admin::routes::stats))
-                        .route("/last_seen", web::get().to(admin::routes::last_seen)),
+                        .route("/last_seen", web::get().to(admin::routes::last_seen))
+                        .route("/breakers", web::get().to(admin::routes::breakers))
+                        .route(
+                            "/breakers/reset",
+                            web::post().to(admin::routes::reset_breakers),
+                        )
+                        .route("/rotate_key", web::post().to(admin::routes::rotate_key))
+                        .route("/tokens", web::get().to(admin::routes::tokens))
+                        .route(
+                            "/tokens/create",
+                            web::post().to(admin::routes::create_token),
+                        )
+                        .route(
+                            "/tokens/revoke",
+                            web::post().to(admin::routes::revoke_tokens),
+                        )
+                        .route("/audit_log", web::get().to(admin::routes::audit_log)),
                 ),
             )
     });
@@ -435,6 +765,11 @@ async fn server_main(
     sign_spawner2.close().await;
     verify_spawner2.close().await;
 
+    state2
+        .db
+        .save_deliver_backlog(state2.deliver_queues.backlog())
+        .await?;
+
     tracing::info!("Server closed");
 
     Ok(())
@@ -1,7 +1,11 @@
+mod inbox_limits;
+mod message_signature;
 mod payload;
 mod timings;
 mod verifier;
 
+pub(crate) use inbox_limits::InboxLimits;
+pub(crate) use message_signature::{MessageSignatureVerified, VerifyMessageSignature};
 pub(crate) use payload::DebugPayload;
 pub(crate) use timings::Timings;
 pub(crate) use verifier::MyVerify;
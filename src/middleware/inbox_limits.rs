@@ -0,0 +1,109 @@
+use crate::{
+    config::Config,
+    data::{client_key, retry_after, State, TrustedProxies},
+    error::{Error, ErrorKind},
+};
+use actix_web::dev::{Service, ServiceRequest, Transform};
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+// How long shed requests are asked to wait before trying again
+const SHED_RETRY_AFTER: u64 = 60;
+
+/// Turns inbox requests away before any work is done on them
+///
+/// Every request is refused while the delivery backlog is past `INBOX_SHED_BACKLOG`, and each
+/// client address is held to its own rate limit. The limit per sending domain needs a verified
+/// signature, so it's applied in the inbox route instead.
+#[derive(Clone, Debug)]
+pub(crate) struct InboxLimits {
+    state: State,
+    trusted_proxies: TrustedProxies,
+    shed_backlog: usize,
+}
+
+#[doc(hidden)]
+pub(crate) struct InboxLimitsMiddleware<S> {
+    inner: S,
+    limits: InboxLimits,
+}
+
+impl InboxLimits {
+    pub(crate) fn new(state: State, config: &Config) -> Self {
+        InboxLimits {
+            state,
+            trusted_proxies: config.trusted_proxies().clone(),
+            shed_backlog: config.inbox_shed_backlog(),
+        }
+    }
+
+    fn check(&self, req: &ServiceRequest) -> Result<(), Error> {
+        if self.shed_backlog > 0 && self.state.deliver_queues.queued() >= self.shed_backlog {
+            metrics::counter!("relay.inbox.shed").increment(1);
+            return Err(ErrorKind::Overloaded(SHED_RETRY_AFTER).into());
+        }
+
+        let (Some(limiter), Some(peer)) = (
+            &self.state.inbox_limits.addresses,
+            req.peer_addr().map(|addr| addr.ip()),
+        ) else {
+            return Ok(());
+        };
+
+        let forwarded_for = req
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok());
+        let client = self.trusted_proxies.client_ip(peer, forwarded_for);
+
+        limiter.check(&client_key(client)).map_err(|wait| {
+            metrics::counter!("relay.inbox.rate-limited", "key" => "address").increment(1);
+            ErrorKind::RateLimited(retry_after(wait)).into()
+        })
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for InboxLimits
+where
+    S: Service<ServiceRequest, Error = actix_web::Error>,
+    S::Future: 'static,
+    S::Response: 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = InboxLimitsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(InboxLimitsMiddleware {
+            inner: service,
+            limits: self.clone(),
+        }))
+    }
+}
+
+impl<S> Service<ServiceRequest> for InboxLimitsMiddleware<S>
+where
+    S: Service<ServiceRequest, Error = actix_web::Error>,
+    S::Future: 'static,
+    S::Response: 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        match self.limits.check(&req) {
+            Ok(()) => Box::pin(self.inner.call(req)),
+            Err(e) => Box::pin(ready(Err(e.into()))),
+        }
+    }
+}
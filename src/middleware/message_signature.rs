@@ -0,0 +1,265 @@
+use crate::{
+    config::{Config, UrlKind},
+    error::{Error, ErrorKind},
+    middleware::{verifier::Signed, MyVerify},
+    rfc9421::{parse_signature_input, parse_signatures, verify_content_digest},
+};
+use actix_web::{
+    dev::{Payload, Service, ServiceRequest, Transform},
+    web::{Bytes, BytesMut},
+    FromRequest, HttpMessage, HttpRequest,
+};
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::SystemTime,
+};
+use streem::IntoStreamer;
+
+// Matches the default limit of actix-web's Json extractor
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Verifies RFC 9421 `Signature-Input`/`Signature` headers on incoming requests
+///
+/// Requests signed this way have their signature headers removed once verified, so the
+/// draft-cavage middleware further in lets them through untouched. Requests without a
+/// `Signature-Input` header are passed along as they are.
+#[derive(Clone, Debug)]
+pub(crate) struct VerifyMessageSignature {
+    verify: MyVerify,
+    scheme: String,
+    authority: String,
+}
+
+/// The key id an RFC 9421 signature on the request was verified with
+///
+/// The signature always covers the `Content-Digest` of a request with a body, so this also
+/// vouches for the body.
+#[derive(Clone, Debug)]
+pub(crate) struct MessageSignatureVerified {
+    key_id: String,
+}
+
+#[doc(hidden)]
+pub(crate) struct VerifyMessageSignatureMiddleware<S> {
+    inner: Rc<S>,
+    config: Rc<VerifyMessageSignature>,
+}
+
+impl VerifyMessageSignature {
+    pub(crate) fn new(verify: MyVerify, config: &Config) -> Self {
+        let base = config.generate_url(UrlKind::Index);
+
+        VerifyMessageSignature {
+            verify,
+            scheme: base.scheme_str().to_owned(),
+            authority: base.authority_str().unwrap_or_default().to_lowercase(),
+        }
+    }
+
+    /// The value of a covered component, derived from the request or read from its headers
+    fn component(&self, req: &ServiceRequest, name: &str) -> Option<String> {
+        let path_and_query = match req.query_string() {
+            "" => req.path().to_owned(),
+            query => format!("{}?{query}", req.path()),
+        };
+
+        match name {
+            "@method" => Some(req.method().as_str().to_owned()),
+            "@target-uri" => Some(format!(
+                "{}://{}{path_and_query}",
+                self.scheme, self.authority
+            )),
+            "@authority" => Some(self.authority.clone()),
+            "@scheme" => Some(self.scheme.clone()),
+            "@request-target" => Some(path_and_query),
+            "@path" => Some(req.path().to_owned()),
+            "@query" => Some(format!("?{}", req.query_string())),
+            name if name.starts_with('@') => None,
+            name => {
+                let values = req
+                    .headers()
+                    .get_all(name)
+                    .map(|value| value.to_str().map(str::trim))
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()?;
+
+                if values.is_empty() {
+                    None
+                } else {
+                    Some(values.join(", "))
+                }
+            }
+        }
+    }
+
+    async fn verify(
+        &self,
+        req: &ServiceRequest,
+        body: &[u8],
+    ) -> Result<MessageSignatureVerified, Error> {
+        let inputs = parse_signature_input(&joined_header(req, "signature-input")?)?;
+        let signatures = parse_signatures(&joined_header(req, "signature")?)?;
+
+        let (input, signature) = inputs
+            .into_iter()
+            .filter(|input| input.key_id.is_some())
+            .find_map(|input| {
+                let signature = signatures
+                    .iter()
+                    .find(|(label, _)| *label == input.label)?
+                    .1
+                    .clone();
+
+                Some((input, signature))
+            })
+            .ok_or(ErrorKind::MessageSignature("no signature with a key id"))?;
+
+        input.check_times(SystemTime::now())?;
+
+        if !input.covers("@method")
+            || !["@target-uri", "@request-target", "@path"]
+                .into_iter()
+                .any(|component| input.covers(component))
+        {
+            return Err(ErrorKind::MessageSignature("method and target must be signed").into());
+        }
+
+        if !body.is_empty() {
+            if !input.covers("content-digest") {
+                return Err(ErrorKind::MessageSignature("content digest must be signed").into());
+            }
+
+            verify_content_digest(&joined_header(req, "content-digest")?, body)?;
+        }
+
+        let signature_base = input.signature_base(|name| self.component(req, name))?;
+        let key_id = input.key_id.clone().unwrap_or_default();
+
+        let signed = Signed::Message {
+            alg: input.alg,
+            signature,
+            signature_base,
+        };
+
+        if self.verify.verify(key_id.clone(), signed).await? {
+            Ok(MessageSignatureVerified { key_id })
+        } else {
+            Err(ErrorKind::MessageSignature("the signing key is gone").into())
+        }
+    }
+}
+
+impl MessageSignatureVerified {
+    pub(crate) fn key_id(&self) -> &str {
+        &self.key_id
+    }
+}
+
+fn joined_header(req: &ServiceRequest, name: &str) -> Result<String, Error> {
+    let values = req
+        .headers()
+        .get_all(name)
+        .map(|value| value.to_str())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ErrorKind::MessageSignature("header isn't valid text"))?;
+
+    if values.is_empty() {
+        return Err(ErrorKind::MessageSignature("missing header").into());
+    }
+
+    Ok(values.join(", "))
+}
+
+async fn read_body(req: &mut ServiceRequest) -> Result<Bytes, actix_web::Error> {
+    let mut payload = req.take_payload().into_streamer();
+    let mut body = BytesMut::new();
+
+    while let Some(bytes) = payload.try_next().await? {
+        if body.len() + bytes.len() > BODY_LIMIT {
+            return Err(Error::from(ErrorKind::MessageSignature("body is too large")).into());
+        }
+
+        body.extend(bytes);
+    }
+
+    Ok(body.freeze())
+}
+
+impl FromRequest for MessageSignatureVerified {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<Self>()
+                .cloned()
+                .ok_or_else(|| ErrorKind::NoSignature(None).into()),
+        )
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for VerifyMessageSignature
+where
+    S: Service<ServiceRequest, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = VerifyMessageSignatureMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(VerifyMessageSignatureMiddleware {
+            inner: Rc::new(service),
+            config: Rc::new(self.clone()),
+        }))
+    }
+}
+
+impl<S> Service<ServiceRequest> for VerifyMessageSignatureMiddleware<S>
+where
+    S: Service<ServiceRequest, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        if !req.headers().contains_key("signature-input") {
+            return Box::pin(self.inner.call(req));
+        }
+
+        let inner = Rc::clone(&self.inner);
+        let config = Rc::clone(&self.config);
+
+        Box::pin(async move {
+            let body = read_body(&mut req).await?;
+            let verified = config.verify(&req, &body).await?;
+
+            metrics::counter!("relay.signature.rfc9421.verified").increment(1);
+
+            req.headers_mut().remove("signature");
+            req.headers_mut().remove("signature-input");
+            req.extensions_mut().insert(verified);
+            req.set_payload(Payload::Stream {
+                payload: Box::pin(streem::try_from_fn(|yielder| async move {
+                    yielder.yield_ok(body).await;
+
+                    Ok(())
+                })),
+            });
+
+            inner.call(req).await
+        })
+    }
+}
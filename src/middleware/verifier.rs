@@ -1,28 +1,59 @@
 use crate::{
     apub::AcceptedActors,
     data::{ActorCache, State},
+    db::Actor,
     error::{Error, ErrorKind},
+    keys::VerifyingKey,
     requests::{BreakerStrategy, Requests},
     spawner::Spawner,
 };
 use activitystreams::{base::BaseExt, iri, iri_string::types::IriString};
 use base64::{engine::general_purpose::STANDARD, Engine};
 use http_signature_normalization_actix::{prelude::*, verify::DeprecatedAlgorithm, Spawn};
-use rsa::{pkcs1::EncodeRsaPublicKey, pkcs8::DecodePublicKey, RsaPublicKey};
 use std::{future::Future, pin::Pin};
 
 #[derive(Clone, Debug)]
 pub(crate) struct MyVerify(pub Requests, pub ActorCache, pub State, pub Spawner);
 
-impl MyVerify {
-    #[tracing::instrument("Verify request", skip(self, signature, signing_string))]
-    async fn verify(
-        &self,
+/// A request signature, in one of the schemes the relay accepts
+#[derive(Clone)]
+pub(crate) enum Signed {
+    /// A draft-cavage `Signature` header
+    Cavage {
         algorithm: Option<Algorithm>,
-        key_id: String,
         signature: String,
         signing_string: String,
-    ) -> Result<bool, Error> {
+    },
+
+    /// An RFC 9421 signature over the base its `Signature-Input` describes
+    Message {
+        alg: Option<String>,
+        signature: Vec<u8>,
+        signature_base: String,
+    },
+}
+
+impl Signed {
+    fn check_algorithm(&self) -> Result<(), Error> {
+        match self {
+            Signed::Cavage {
+                algorithm:
+                    Some(Algorithm::Hs2019 | Algorithm::Deprecated(DeprecatedAlgorithm::RsaSha256))
+                    | None,
+                ..
+            }
+            | Signed::Message { .. } => Ok(()),
+            Signed::Cavage {
+                algorithm: Some(other),
+                ..
+            } => Err(ErrorKind::Algorithm(other.to_string()).into()),
+        }
+    }
+}
+
+impl MyVerify {
+    #[tracing::instrument("Verify request", skip(self, signed))]
+    pub(crate) async fn verify(&self, key_id: String, signed: Signed) -> Result<bool, Error> {
         let public_key_id = iri!(key_id);
 
         // receiving an activity from a domain indicates it is probably online
@@ -34,7 +65,9 @@ impl MyVerify {
             .actor_id_from_public_key_id(public_key_id.clone())
             .await?
         {
-            if !self.2.db.is_allowed(actor_id.clone()).await? {
+            // In restricted mode, follow requests from domains that aren't allowed yet still need to
+            // reach the inbox, which decides whether the activity is accepted
+            if self.2.db.is_blocked(actor_id.clone()).await? {
                 return Err(ErrorKind::NotAllowed(key_id).into());
             }
 
@@ -53,22 +86,9 @@ impl MyVerify {
             let was_cached = actor.is_cached();
             let actor = actor.into_inner();
 
-            match algorithm {
-                Some(Algorithm::Hs2019) => (),
-                Some(Algorithm::Deprecated(DeprecatedAlgorithm::RsaSha256)) => (),
-                Some(other) => {
-                    return Err(ErrorKind::Algorithm(other.to_string()).into());
-                }
-                None => (),
-            };
+            signed.check_algorithm()?;
 
-            let res = do_verify(
-                &self.3,
-                &actor.public_key,
-                signature.clone(),
-                signing_string.clone(),
-            )
-            .await;
+            let res = do_verify(&self.3, &actor, &key_id, signed.clone()).await;
 
             if let Err(e) = res {
                 if !was_cached {
@@ -109,11 +129,13 @@ impl MyVerify {
             Ok(actor) => actor,
             Err(_) => {
                 // Fallback to fragment-stripped URI for backward compatibility
-                self.1.get_no_cache(&actor_without_fragment, &self.0).await?
+                self.1
+                    .get_no_cache(&actor_without_fragment, &self.0)
+                    .await?
             }
         };
 
-        do_verify(&self.3, &actor.public_key, signature, signing_string).await?;
+        do_verify(&self.3, &actor, &key_id, signed).await?;
 
         Ok(true)
     }
@@ -130,6 +152,13 @@ enum PublicKeyResponse {
         #[allow(dead_code)]
         public_key_pem: String,
     },
+    Multikey {
+        #[allow(dead_code)]
+        id: IriString,
+        controller: IriString,
+        #[allow(dead_code)]
+        public_key_multibase: String,
+    },
     Actor(Box<AcceptedActors>),
 }
 
@@ -137,38 +166,58 @@ impl PublicKeyResponse {
     fn actor_id(&self) -> Option<IriString> {
         match self {
             PublicKeyResponse::PublicKey { owner, .. } => Some(owner.clone()),
+            PublicKeyResponse::Multikey { controller, .. } => Some(controller.clone()),
             PublicKeyResponse::Actor(actor) => actor.id_unchecked().cloned(),
         }
     }
 }
 
-#[tracing::instrument("Verify signature")]
+/// Find the key the actor signed with
+///
+/// draft-cavage signatures are only ever checked against the actor's `publicKey`, while RFC 9421
+/// signatures may also use one of the Ed25519 keys from its `assertionMethod`.
+fn verifying_key(actor: &Actor, key_id: &str, signed: &Signed) -> Result<VerifyingKey, Error> {
+    if matches!(signed, Signed::Cavage { .. }) || actor.public_key_id.as_str() == key_id {
+        return VerifyingKey::from_pem(&actor.public_key);
+    }
+
+    actor
+        .assertion_keys
+        .iter()
+        .find(|key| key.id.as_str() == key_id)
+        .and_then(|key| VerifyingKey::from_multibase(&key.public_key_multibase))
+        .ok_or_else(|| {
+            ErrorKind::BadActor(actor.public_key_id.to_string(), key_id.to_owned()).into()
+        })
+}
+
+#[tracing::instrument("Verify signature", skip(signed))]
 async fn do_verify(
     spawner: &Spawner,
-    public_key: &str,
-    signature: String,
-    signing_string: String,
+    actor: &Actor,
+    key_id: &str,
+    signed: Signed,
 ) -> Result<(), Error> {
-    let public_key = RsaPublicKey::from_public_key_pem(public_key.trim())?;
-    let public_key_der = public_key
-        .to_pkcs1_der()
-        .map_err(|_| ErrorKind::DerEncode)?;
-    let public_key = ring::signature::UnparsedPublicKey::new(
-        &ring::signature::RSA_PKCS1_2048_8192_SHA256,
-        public_key_der,
-    );
+    let key = verifying_key(actor, key_id, &signed)?;
 
     let span = tracing::Span::current();
     spawner
         .spawn_blocking(move || {
-            span.in_scope(|| {
-                let decoded = STANDARD.decode(signature)?;
-
-                public_key
-                    .verify(signing_string.as_bytes(), decoded.as_slice())
-                    .map_err(|_| ErrorKind::VerifySignature)?;
+            span.in_scope(|| match signed {
+                Signed::Cavage {
+                    signature,
+                    signing_string,
+                    ..
+                } => {
+                    let decoded = STANDARD.decode(signature)?;
 
-                Ok(()) as Result<(), Error>
+                    key.verify(Some("rsa-v1_5-sha256"), signing_string.as_bytes(), &decoded)
+                }
+                Signed::Message {
+                    alg,
+                    signature,
+                    signature_base,
+                } => key.verify(alg.as_deref(), signature_base.as_bytes(), &signature),
             })
         })
         .await??;
@@ -190,8 +239,15 @@ impl SignatureVerify for MyVerify {
         let this = self.clone();
 
         Box::pin(async move {
-            this.verify(algorithm, key_id, signature, signing_string)
-                .await
+            this.verify(
+                key_id,
+                Signed::Cavage {
+                    algorithm,
+                    signature,
+                    signing_string,
+                },
+            )
+            .await
         })
     }
 }
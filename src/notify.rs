@@ -0,0 +1,388 @@
+use crate::{
+    config::Config,
+    db::AuditEntry,
+    error::{Error, ErrorKind},
+    telegram::Telegram,
+};
+use activitystreams::iri_string::types::IriString;
+use reqwest_middleware::ClientWithMiddleware;
+use std::{collections::BTreeSet, sync::Arc};
+use uuid::Uuid;
+
+mod email;
+mod matrix;
+mod webhook;
+
+use self::{email::Email, matrix::Matrix, webhook::Webhook};
+
+/// Something that happened on the relay that an admin should hear about
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum Event {
+    Subscribed { actor_id: IriString },
+    Unsubscribed { actor_id: IriString },
+    PendingFollow { id: Uuid, actor_id: IriString },
+    AdminAction { entry: AuditEntry },
+    BreakerTripped { domain: String },
+    QueueBackedUp { queued: usize },
+}
+
+impl Event {
+    pub(crate) fn kind(&self) -> EventKind {
+        match self {
+            Self::Subscribed { .. } => EventKind::Subscribed,
+            Self::Unsubscribed { .. } => EventKind::Unsubscribed,
+            Self::PendingFollow { .. } => EventKind::PendingFollow,
+            Self::AdminAction { .. } => EventKind::AdminAction,
+            Self::BreakerTripped { .. } => EventKind::BreakerTripped,
+            Self::QueueBackedUp { .. } => EventKind::QueueBackedUp,
+        }
+    }
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Subscribed { actor_id } => write!(f, "{actor_id} subscribed to the relay"),
+            Self::Unsubscribed { actor_id } => write!(f, "{actor_id} unsubscribed from the relay"),
+            Self::PendingFollow { actor_id, .. } => {
+                write!(f, "New follow request from {actor_id}")
+            }
+            Self::AdminAction { entry } => {
+                write!(
+                    f,
+                    "{} ran {} on {}",
+                    entry.actor,
+                    entry.action.as_str(),
+                    entry.targets.join(", ")
+                )?;
+
+                if let Some(reason) = &entry.reason {
+                    write!(f, " ({reason})")?;
+                }
+
+                Ok(())
+            }
+            Self::BreakerTripped { domain } => write!(
+                f,
+                "Deliveries to {domain} keep failing, they are paused until it recovers"
+            ),
+            Self::QueueBackedUp { queued } => write!(
+                f,
+                "{queued} deliveries are waiting to be sent, the relay is falling behind"
+            ),
+        }
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EventKind {
+    Subscribed,
+    Unsubscribed,
+    PendingFollow,
+    AdminAction,
+    BreakerTripped,
+    QueueBackedUp,
+}
+
+impl EventKind {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Subscribed => "subscribed",
+            Self::Unsubscribed => "unsubscribed",
+            Self::PendingFollow => "pending_follow",
+            Self::AdminAction => "admin_action",
+            Self::BreakerTripped => "breaker_tripped",
+            Self::QueueBackedUp => "queue_backed_up",
+        }
+    }
+}
+
+impl std::str::FromStr for EventKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "subscribed" => Ok(Self::Subscribed),
+            "unsubscribed" => Ok(Self::Unsubscribed),
+            "pending_follow" => Ok(Self::PendingFollow),
+            "admin_action" => Ok(Self::AdminAction),
+            "breaker_tripped" => Ok(Self::BreakerTripped),
+            "queue_backed_up" => Ok(Self::QueueBackedUp),
+            other => Err(ErrorKind::EventKind(other.to_owned()).into()),
+        }
+    }
+}
+
+/// Which events a backend should be sent
+#[derive(Clone, Debug, Default)]
+pub(crate) struct EventFilter {
+    /// `None` lets every event through
+    kinds: Option<BTreeSet<EventKind>>,
+}
+
+impl EventFilter {
+    /// Parse a comma-separated list of event kinds, where an empty list accepts everything
+    pub(crate) fn parse(input: Option<&str>) -> Result<Self, Error> {
+        let kinds = input
+            .into_iter()
+            .flat_map(|s| s.split(','))
+            .filter(|s| !s.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<BTreeSet<EventKind>, _>>()?;
+
+        Ok(EventFilter {
+            kinds: (!kinds.is_empty()).then_some(kinds),
+        })
+    }
+
+    pub(crate) fn accepts(&self, kind: EventKind) -> bool {
+        self.kinds
+            .as_ref()
+            .map(|kinds| kinds.contains(&kind))
+            .unwrap_or(true)
+    }
+}
+
+#[derive(Clone)]
+enum Backend {
+    Telegram(Telegram),
+    Webhook(Webhook),
+    Matrix(Matrix),
+    Email(Email),
+}
+
+impl Backend {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Telegram(_) => "telegram",
+            Self::Webhook(_) => "webhook",
+            Self::Matrix(_) => "matrix",
+            Self::Email(_) => "email",
+        }
+    }
+
+    async fn send(&self, event: &Event) -> Result<(), Error> {
+        match self {
+            Self::Telegram(telegram) => telegram.send(event).await,
+            Self::Webhook(webhook) => webhook.send(event).await,
+            Self::Matrix(matrix) => matrix.send(event).await,
+            Self::Email(email) => email.send(event).await,
+        }
+    }
+}
+
+/// Sends relay events to every configured backend whose filter accepts them
+#[derive(Clone, Default)]
+pub(crate) struct Notifier {
+    backends: Arc<[(EventFilter, Backend)]>,
+}
+
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notifier")
+            .field(
+                "backends",
+                &self
+                    .backends
+                    .iter()
+                    .map(|(_, backend)| backend.name())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Notifier {
+    pub(crate) fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Build the webhook, matrix and email backends from the config
+    ///
+    /// The telegram bot is added separately with `with_telegram`, since it is fed by this notifier.
+    pub(crate) fn build(config: &Config, client: ClientWithMiddleware) -> Result<Self, Error> {
+        let mut backends = Vec::new();
+
+        if let Some(webhook) = config.webhook_config() {
+            backends.push((
+                webhook.events.clone(),
+                Backend::Webhook(Webhook::new(
+                    client.clone(),
+                    webhook.urls.clone(),
+                    &webhook.secret,
+                )),
+            ));
+        }
+
+        if let Some(matrix) = config.matrix_config() {
+            backends.push((
+                matrix.events.clone(),
+                Backend::Matrix(Matrix::new(
+                    client,
+                    &matrix.homeserver,
+                    matrix.access_token.clone(),
+                    matrix.room_id.clone(),
+                )),
+            ));
+        }
+
+        if let Some(smtp) = config.smtp_config() {
+            backends.push((
+                smtp.events.clone(),
+                Backend::Email(Email::build(&smtp.url, &smtp.from, &smtp.to)?),
+            ));
+        }
+
+        Ok(Notifier {
+            backends: backends.into(),
+        })
+    }
+
+    pub(crate) fn with_telegram(self, telegram: Telegram, events: EventFilter) -> Self {
+        let mut backends = self.backends.to_vec();
+        backends.push((events, Backend::Telegram(telegram)));
+
+        Notifier {
+            backends: backends.into(),
+        }
+    }
+
+    /// Send an event in the background, so slow backends never hold up the caller
+    pub(crate) fn notify(&self, event: Event) {
+        let kind = event.kind();
+
+        for (_, backend) in self
+            .backends
+            .iter()
+            .filter(|(filter, _)| filter.accepts(kind))
+        {
+            let backend = backend.clone();
+            let event = event.clone();
+
+            tokio::spawn(async move {
+                let name = backend.name();
+
+                if let Err(e) = backend.send(&event).await {
+                    tracing::warn!("Failed to send {name} notification: {e}");
+                    metrics::counter!("relay.notify.failed", "backend" => name, "event" => kind.as_str())
+                        .increment(1);
+                } else {
+                    metrics::counter!("relay.notify.sent", "backend" => name, "event" => kind.as_str())
+                        .increment(1);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod stand_in {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::oneshot,
+    };
+
+    /// A captured HTTP request
+    pub(super) struct Request {
+        pub(super) head: String,
+        pub(super) body: Vec<u8>,
+    }
+
+    impl Request {
+        pub(super) fn header(&self, name: &str) -> Option<&str> {
+            self.head.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name).then_some(value.trim())
+            })
+        }
+    }
+
+    /// Serve a single HTTP request, answering 200 and handing the request back
+    pub(super) async fn http() -> (String, oneshot::Receiver<Request>) {
+        http_status("200 OK").await
+    }
+
+    /// Serve a single HTTP request, answering with `status` and handing the request back
+    pub(super) async fn http_status(status: &'static str) -> (String, oneshot::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 1024];
+
+            let (head, body_start) = loop {
+                let n = stream.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+
+                if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break (String::from_utf8_lossy(&buf[..end]).into_owned(), end + 4);
+                }
+            };
+
+            let mut request = Request {
+                head,
+                body: buf[body_start..].to_vec(),
+            };
+
+            let length = request
+                .header("content-length")
+                .and_then(|length| length.parse::<usize>().ok())
+                .unwrap_or(0);
+
+            while request.body.len() < length {
+                let n = stream.read(&mut chunk).await.unwrap();
+                request.body.extend_from_slice(&chunk[..n]);
+            }
+
+            let response = format!("HTTP/1.1 {status}\r\ncontent-length: 2\r\n\r\n{{}}");
+            stream.write_all(response.as_bytes()).await.unwrap();
+
+            let _ = tx.send(request);
+        });
+
+        (address, rx)
+    }
+
+    pub(super) fn client() -> reqwest_middleware::ClientWithMiddleware {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        reqwest_middleware::ClientBuilder::new(
+            reqwest::Client::builder()
+                .tls_backend_rustls()
+                .build()
+                .unwrap(),
+        )
+        .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventFilter, EventKind};
+
+    #[test]
+    fn empty_filter_accepts_everything() {
+        let filter = EventFilter::parse(None).unwrap();
+
+        assert!(filter.accepts(EventKind::Subscribed));
+        assert!(filter.accepts(EventKind::AdminAction));
+    }
+
+    #[test]
+    fn filter_accepts_listed_events() {
+        let filter = EventFilter::parse(Some("subscribed, breaker_tripped")).unwrap();
+
+        assert!(filter.accepts(EventKind::Subscribed));
+        assert!(filter.accepts(EventKind::BreakerTripped));
+        assert!(!filter.accepts(EventKind::AdminAction));
+        assert!(EventFilter::parse(Some("everything")).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{error::Error, notify::Event};
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::sync::Arc;
+
+/// Emails each event through an SMTP server
+#[derive(Clone)]
+pub(super) struct Email {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Arc<[Mailbox]>,
+}
+
+impl Email {
+    /// Connect to the server described by `url`
+    ///
+    /// `smtps://` uses implicit TLS, `smtp://` with `?tls=required` uses STARTTLS, and plain `smtp://`
+    /// sends in the clear.
+    pub(super) fn build(url: &str, from: &str, to: &[String]) -> Result<Self, Error> {
+        Ok(Email {
+            transport: AsyncSmtpTransport::<Tokio1Executor>::from_url(url)?.build(),
+            from: from.parse()?,
+            to: to
+                .iter()
+                .map(|to| to.parse())
+                .collect::<Result<Vec<Mailbox>, _>>()?
+                .into(),
+        })
+    }
+
+    pub(super) async fn send(&self, event: &Event) -> Result<(), Error> {
+        let text = event.to_string();
+
+        let message = self
+            .to
+            .iter()
+            .fold(Message::builder().from(self.from.clone()), |builder, to| {
+                builder.to(to.clone())
+            })
+            .subject(&text)
+            .header(ContentType::TEXT_PLAIN)
+            .body(text)?;
+
+        self.transport.send(message).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Email;
+    use crate::notify::Event;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        sync::oneshot,
+    };
+
+    /// Accept a single message, handing back its envelope recipients and data
+    async fn stand_in_smtp() -> (u16, oneshot::Receiver<(Vec<String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            let mut recipients = Vec::new();
+            let mut data = String::new();
+
+            write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let command = line.to_uppercase();
+
+                let reply: &[u8] = if command.starts_with("EHLO") {
+                    b"250 localhost\r\n"
+                } else if command.starts_with("RCPT TO:") {
+                    recipients.push(line["RCPT TO:".len()..].to_owned());
+                    b"250 OK\r\n"
+                } else if command.starts_with("DATA") {
+                    write.write_all(b"354 End with .\r\n").await.unwrap();
+
+                    while let Some(line) = lines.next_line().await.unwrap() {
+                        if line == "." {
+                            break;
+                        }
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+
+                    b"250 Queued\r\n"
+                } else if command.starts_with("QUIT") {
+                    write.write_all(b"221 Bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+
+                write.write_all(reply).await.unwrap();
+            }
+
+            let _ = tx.send((recipients, data));
+        });
+
+        (port, rx)
+    }
+
+    #[tokio::test]
+    async fn sends_mail() {
+        let (port, message) = stand_in_smtp().await;
+        let email = Email::build(
+            &format!("smtp://127.0.0.1:{port}"),
+            "Relay <relay@example.com>",
+            &[String::from("admin@example.com")],
+        )
+        .unwrap();
+
+        email
+            .send(&Event::QueueBackedUp { queued: 1200 })
+            .await
+            .unwrap();
+
+        let (recipients, data) = message.await.unwrap();
+        assert_eq!(recipients, ["<admin@example.com>"]);
+        assert!(data.contains("1200 deliveries are waiting to be sent"));
+        assert!(data.contains("From: Relay <relay@example.com>"));
+    }
+}
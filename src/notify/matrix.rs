@@ -0,0 +1,106 @@
+use crate::{
+    error::{Error, ErrorKind},
+    notify::Event,
+};
+use activitystreams::iri_string::types::IriString;
+use reqwest_middleware::ClientWithMiddleware;
+use uuid::Uuid;
+
+#[derive(serde::Serialize)]
+struct Notice {
+    msgtype: &'static str,
+    body: String,
+}
+
+/// Posts each event as a notice in a Matrix room, through the client-server API
+#[derive(Clone)]
+pub(super) struct Matrix {
+    client: ClientWithMiddleware,
+    /// The room's send endpoint, missing only the transaction id
+    send_url: String,
+    access_token: String,
+}
+
+impl Matrix {
+    pub(super) fn new(
+        client: ClientWithMiddleware,
+        homeserver: &IriString,
+        access_token: String,
+        room_id: String,
+    ) -> Self {
+        let room_id: String = url::form_urlencoded::byte_serialize(room_id.as_bytes()).collect();
+
+        Matrix {
+            client,
+            send_url: format!(
+                "{}/_matrix/client/v3/rooms/{room_id}/send/m.room.message",
+                homeserver.as_str().trim_end_matches('/')
+            ),
+            access_token,
+        }
+    }
+
+    pub(super) async fn send(&self, event: &Event) -> Result<(), Error> {
+        // Transaction ids let the homeserver drop a retried request instead of posting twice
+        let url = format!("{}/{}", self.send_url, Uuid::new_v4());
+
+        let res = self
+            .client
+            .put(url.as_str())
+            .bearer_auth(&self.access_token)
+            .json(&Notice {
+                msgtype: "m.notice",
+                body: event.to_string(),
+            })
+            .send()
+            .await
+            .map_err(|e| ErrorKind::SendRequest(self.send_url.clone(), e.to_string()))?;
+
+        if !res.status().is_success() {
+            return Err(ErrorKind::Status(
+                self.send_url.clone(),
+                crate::http1::status_to_http02(res.status()),
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Matrix;
+    use crate::notify::{stand_in, Event};
+
+    #[tokio::test]
+    async fn sends_room_notice() {
+        let (address, request) = stand_in::http().await;
+        let matrix = Matrix::new(
+            stand_in::client(),
+            &address.parse().unwrap(),
+            String::from("token"),
+            String::from("!room:example.com"),
+        );
+
+        matrix
+            .send(&Event::Subscribed {
+                actor_id: "https://example.com/actor".parse().unwrap(),
+            })
+            .await
+            .unwrap();
+
+        let request = request.await.unwrap();
+        assert!(request.head.starts_with(
+            "PUT /_matrix/client/v3/rooms/%21room%3Aexample.com/send/m.room.message/"
+        ));
+        assert_eq!(request.header("authorization"), Some("Bearer token"));
+
+        let json: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(json["msgtype"], "m.notice");
+        assert_eq!(
+            json["body"],
+            "https://example.com/actor subscribed to the relay"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    error::{Error, ErrorKind},
+    notify::Event,
+};
+use activitystreams::iri_string::types::IriString;
+use reqwest_middleware::ClientWithMiddleware;
+use ring::hmac;
+use std::sync::Arc;
+
+/// The header carrying the HMAC-SHA256 of the request body, keyed with the webhook secret
+const SIGNATURE_HEADER: &str = "x-relay-signature";
+
+#[derive(serde::Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a Event,
+    text: String,
+}
+
+/// POSTs each event as JSON to a list of URLs
+#[derive(Clone)]
+pub(super) struct Webhook {
+    client: ClientWithMiddleware,
+    urls: Arc<[IriString]>,
+    key: hmac::Key,
+}
+
+impl Webhook {
+    pub(super) fn new(client: ClientWithMiddleware, urls: Vec<IriString>, secret: &str) -> Self {
+        Webhook {
+            client,
+            urls: urls.into(),
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+        }
+    }
+
+    /// Send the event to every URL, even when some of them fail
+    ///
+    /// The first failure is returned, and any others are logged.
+    pub(super) async fn send(&self, event: &Event) -> Result<(), Error> {
+        let body = serde_json::to_vec(&Payload {
+            event,
+            text: event.to_string(),
+        })?;
+        let signature = format!("sha256={}", hex(hmac::sign(&self.key, &body).as_ref()));
+
+        let mut failures = Vec::new();
+
+        for url in self.urls.iter() {
+            if let Err(e) = self.post(url, &body, &signature).await {
+                failures.push(e);
+            }
+        }
+
+        let mut failures = failures.into_iter();
+
+        let Some(first) = failures.next() else {
+            return Ok(());
+        };
+
+        for e in failures {
+            tracing::warn!("Failed to send webhook notification: {e}");
+        }
+
+        Err(first)
+    }
+
+    async fn post(&self, url: &IriString, body: &[u8], signature: &str) -> Result<(), Error> {
+        let res = self
+            .client
+            .post(url.as_str())
+            .header("content-type", "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(|e| ErrorKind::SendRequest(url.to_string(), e.to_string()))?;
+
+        if !res.status().is_success() {
+            return Err(ErrorKind::Status(
+                url.to_string(),
+                crate::http1::status_to_http02(res.status()),
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hex, Webhook};
+    use crate::notify::{stand_in, Event};
+    use ring::hmac;
+
+    #[tokio::test]
+    async fn posts_signed_json() {
+        let (address, request) = stand_in::http().await;
+        let webhook = Webhook::new(
+            stand_in::client(),
+            vec![format!("{address}/hook").parse().unwrap()],
+            "secret",
+        );
+
+        webhook
+            .send(&Event::BreakerTripped {
+                domain: String::from("example.com"),
+            })
+            .await
+            .unwrap();
+
+        let request = request.await.unwrap();
+        assert!(request.head.starts_with("POST /hook "));
+
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let expected = format!("sha256={}", hex(hmac::sign(&key, &request.body).as_ref()));
+        assert_eq!(request.header("x-relay-signature"), Some(expected.as_str()));
+
+        let json: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(json["event"], "breaker_tripped");
+        assert_eq!(json["domain"], "example.com");
+    }
+
+    #[tokio::test]
+    async fn keeps_sending_after_a_failure() {
+        let (failing, _) = stand_in::http_status("500 Internal Server Error").await;
+        let (working, request) = stand_in::http().await;
+        let webhook = Webhook::new(
+            stand_in::client(),
+            vec![
+                format!("{failing}/hook").parse().unwrap(),
+                format!("{working}/hook").parse().unwrap(),
+            ],
+            "secret",
+        );
+
+        let res = webhook
+            .send(&Event::BreakerTripped {
+                domain: String::from("example.com"),
+            })
+            .await;
+        assert!(res.is_err());
+
+        let request = request.await.unwrap();
+        assert!(request.head.starts_with("POST /hook "));
+    }
+}
@@ -1,30 +1,29 @@
 use crate::{
     data::LastOnline,
+    db::Db,
     error::{Error, ErrorKind},
+    rfc9421::{content_digest, SignatureInput},
     spawner::Spawner,
     stream::{aggregate, limit_stream},
 };
 use activitystreams::iri_string::types::IriString;
-use actix_web::http::header::Date;
+use actix_web::http::header::{Date, HttpDate};
 use base64::{engine::general_purpose::STANDARD, Engine};
 use dashmap::DashMap;
-use http_signature_normalization_reqwest::{digest::ring::Sha256, prelude::*};
-use reqwest_middleware::ClientWithMiddleware;
+use http_signature_normalization_reqwest::{digest::ring::Sha256, prelude::*, Spawn};
+use reqwest_middleware::{ClientWithMiddleware, RequestBuilder};
 use ring::{
     rand::SystemRandom,
     signature::{RsaKeyPair, RSA_PKCS1_SHA256},
 };
-use rsa::{pkcs1::EncodeRsaPrivateKey, RsaPrivateKey};
+use rsa::{pkcs1::EncodeRsaPrivateKey, RsaPrivateKey, RsaPublicKey};
 use std::{
-    sync::Arc,
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{Arc, Mutex, RwLock},
     time::{Duration, SystemTime},
 };
 
-const ONE_SECOND: u64 = 1;
-const ONE_MINUTE: u64 = 60 * ONE_SECOND;
-const ONE_HOUR: u64 = 60 * ONE_MINUTE;
-const ONE_DAY: u64 = 24 * ONE_HOUR;
-
 // 20 KB
 const JSON_SIZE_LIMIT: usize = 20 * 1024;
 
@@ -38,9 +37,45 @@ pub(crate) enum BreakerStrategy {
     Allow404AndBelow,
 }
 
+/// How long a breaker stays open after repeated failures
+///
+/// Once a domain has failed `threshold` times in a row, requests to it are held off for `base`,
+/// doubling with every further failure up to `max`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BreakerSchedule {
+    threshold: usize,
+    base: Duration,
+    max: Duration,
+}
+
+impl BreakerSchedule {
+    pub(crate) fn new(threshold: usize, base: Duration, max: Duration) -> Self {
+        BreakerSchedule {
+            threshold: threshold.max(1),
+            base,
+            max,
+        }
+    }
+
+    fn wait(&self, failures: usize) -> Duration {
+        let Some(doublings) = failures.checked_sub(self.threshold) else {
+            return Duration::ZERO;
+        };
+
+        let factor = 1u32
+            .checked_shl(u32::try_from(doublings).unwrap_or(u32::MAX))
+            .unwrap_or(u32::MAX);
+
+        self.base.saturating_mul(factor).min(self.max)
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct Breakers {
     inner: Arc<DashMap<String, Breaker>>,
+    dirty: Arc<Mutex<HashSet<String>>>,
+    tripped: Arc<Mutex<Vec<String>>>,
+    schedule: BreakerSchedule,
 }
 
 impl std::fmt::Debug for Breakers {
@@ -50,6 +85,19 @@ impl std::fmt::Debug for Breakers {
 }
 
 impl Breakers {
+    pub(crate) async fn load(db: &Db, schedule: BreakerSchedule) -> Result<Self, Error> {
+        let breakers = db.breakers().await?;
+
+        metrics::gauge!("relay.breakers.size").set(crate::collector::recordable(breakers.len()));
+
+        Ok(Breakers {
+            inner: Arc::new(breakers.into_iter().collect()),
+            dirty: Arc::new(Mutex::new(HashSet::new())),
+            tripped: Arc::new(Mutex::new(Vec::new())),
+            schedule,
+        })
+    }
+
     pub(crate) fn should_try(&self, url: &IriString) -> bool {
         if let Some(authority) = url.authority_str() {
             if let Some(breaker) = self.inner.get(authority) {
@@ -62,14 +110,59 @@ impl Breakers {
         }
     }
 
+    /// How much longer the domain asked us to hold off, if it's still paused
+    pub(crate) fn paused_for(&self, url: &IriString) -> Option<Duration> {
+        let breaker = self.inner.get(url.authority_str()?)?;
+
+        breaker.paused_until?.duration_since(SystemTime::now()).ok()
+    }
+
+    pub(crate) fn snapshot(&self) -> Vec<(String, Breaker)> {
+        self.inner
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
+    /// Collect every breaker changed since the last call, `None` marking a breaker that was reset
+    pub(crate) fn take_dirty(&self) -> HashMap<String, Option<Breaker>> {
+        let dirty = std::mem::take(&mut *self.dirty.lock().unwrap());
+
+        dirty
+            .into_iter()
+            .map(|authority| {
+                let breaker = self.inner.get(&authority).map(|b| b.clone());
+                (authority, breaker)
+            })
+            .collect()
+    }
+
+    /// Collect every domain whose breaker tripped since the last call
+    pub(crate) fn take_tripped(&self) -> Vec<String> {
+        std::mem::take(&mut *self.tripped.lock().unwrap())
+    }
+
+    fn mark_dirty(&self, authority: &str) {
+        self.dirty.lock().unwrap().insert(authority.to_owned());
+    }
+
+    fn reset(&self, authority: &str) {
+        self.inner.remove(authority);
+        self.mark_dirty(authority);
+        metrics::gauge!("relay.breakers.size").set(crate::collector::recordable(self.inner.len()));
+    }
+
     fn fail(&self, url: &IriString) {
         if let Some(authority) = url.authority_str() {
             let should_write = {
                 if let Some(mut breaker) = self.inner.get_mut(authority) {
-                    breaker.fail();
+                    breaker.fail(&self.schedule);
                     if !breaker.should_try() {
                         tracing::warn!("Failed breaker for {authority}");
                     }
+                    if breaker.failures == self.schedule.threshold {
+                        self.tripped.lock().unwrap().push(authority.to_owned());
+                    }
                     false
                 } else {
                     true
@@ -78,19 +171,43 @@ impl Breakers {
 
             if should_write {
                 let mut breaker = self.inner.entry(authority.to_owned()).or_default();
-                breaker.fail();
+                breaker.fail(&self.schedule);
             }
+
+            self.mark_dirty(authority);
+        }
+    }
+
+    /// Hold off on a domain until the time it asked for, without counting a failure
+    fn pause(&self, url: &IriString, until: SystemTime) {
+        if let Some(authority) = url.authority_str() {
+            let now = SystemTime::now();
+            let until = now
+                .checked_add(self.schedule.max)
+                .map_or(until, |max| until.min(max));
+
+            tracing::info!("Pausing requests to {authority} at its request");
+            metrics::counter!("relay.breakers.paused").increment(1);
+
+            self.inner
+                .entry(authority.to_owned())
+                .or_default()
+                .pause(until);
+
+            self.mark_dirty(authority);
         }
     }
 
     fn succeed(&self, url: &IriString) {
         if let Some(authority) = url.authority_str() {
-            let should_write = {
+            // Every successful request lands here, so only a change worth saving marks it dirty
+            let (should_write, changed) = {
                 if let Some(mut breaker) = self.inner.get_mut(authority) {
+                    let changed = breaker.failures > 0;
                     breaker.succeed();
-                    false
+                    (false, changed)
                 } else {
-                    true
+                    (true, true)
                 }
             };
 
@@ -98,43 +215,66 @@ impl Breakers {
                 let mut breaker = self.inner.entry(authority.to_owned()).or_default();
                 breaker.succeed();
             }
-        }
-    }
-}
 
-impl Default for Breakers {
-    fn default() -> Self {
-        Breakers {
-            inner: Arc::new(DashMap::new()),
+            if changed {
+                self.mark_dirty(authority);
+            }
         }
     }
 }
 
-#[derive(Debug)]
-struct Breaker {
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct Breaker {
     failures: usize,
     last_attempt: SystemTime,
     last_success: SystemTime,
+    /// When the domain may be tried again after its recent failures
+    #[serde(default = "SystemTime::now")]
+    next_attempt: SystemTime,
+    /// When the domain asked us to come back with a `Retry-After` header
+    #[serde(default)]
+    paused_until: Option<SystemTime>,
 }
 
 impl Breaker {
-    const FAILURE_WAIT: Duration = Duration::from_secs(ONE_DAY);
-    const FAILURE_THRESHOLD: usize = 10;
+    pub(crate) fn should_try(&self) -> bool {
+        let now = SystemTime::now();
+
+        self.next_attempt <= now && self.paused_until.is_none_or(|until| until <= now)
+    }
+
+    pub(crate) fn failures(&self) -> usize {
+        self.failures
+    }
 
-    fn should_try(&self) -> bool {
-        self.failures < Self::FAILURE_THRESHOLD
-            || self.last_attempt + Self::FAILURE_WAIT < SystemTime::now()
+    pub(crate) fn last_attempt(&self) -> SystemTime {
+        self.last_attempt
     }
 
-    fn fail(&mut self) {
+    pub(crate) fn last_success(&self) -> SystemTime {
+        self.last_success
+    }
+
+    pub(crate) fn paused_until(&self) -> Option<SystemTime> {
+        self.paused_until.filter(|until| *until > SystemTime::now())
+    }
+
+    fn fail(&mut self, schedule: &BreakerSchedule) {
         self.failures += 1;
         self.last_attempt = SystemTime::now();
+        self.next_attempt = self.last_attempt + schedule.wait(self.failures);
+    }
+
+    fn pause(&mut self, until: SystemTime) {
+        self.last_attempt = SystemTime::now();
+        self.paused_until = Some(self.paused_until.map_or(until, |paused| paused.max(until)));
     }
 
     fn succeed(&mut self) {
         self.failures = 0;
         self.last_attempt = SystemTime::now();
         self.last_success = SystemTime::now();
+        self.next_attempt = SystemTime::now();
     }
 }
 
@@ -146,25 +286,171 @@ impl Default for Breaker {
             failures: 0,
             last_attempt: now,
             last_success: now,
+            next_attempt: now,
+            paused_until: None,
         }
     }
 }
 
+/// How requests to a server are signed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(crate) enum SignatureScheme {
+    /// RFC 9421 HTTP Message Signatures
+    Rfc9421,
+
+    /// draft-cavage HTTP Signatures, which most of the fediverse still expects
+    Cavage,
+}
+
+impl SignatureScheme {
+    fn other(self) -> Self {
+        match self {
+            Self::Rfc9421 => Self::Cavage,
+            Self::Cavage => Self::Rfc9421,
+        }
+    }
+}
+
+// How long a server that refused both schemes is only tried with one of them
+const RENEGOTIATE_AFTER: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// What a server made of the relay's signatures
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(crate) enum Negotiated {
+    /// The server accepted a request signed with this scheme
+    Accepted(SignatureScheme),
+
+    /// The server refused both schemes, most likely for reasons that have nothing to do with the
+    /// signature, so only this scheme is tried until `since` is a day old
+    Refused {
+        scheme: SignatureScheme,
+        since: SystemTime,
+    },
+}
+
+/// The signature scheme negotiated with each server, kept across restarts
 #[derive(Clone)]
-pub(crate) struct Requests {
-    client: ClientWithMiddleware,
+pub(crate) struct SignatureSchemes {
+    inner: Arc<DashMap<String, Negotiated>>,
+    dirty: Arc<Mutex<HashSet<String>>>,
+}
+
+impl std::fmt::Debug for SignatureSchemes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignatureSchemes")
+            .field("size", &self.inner.len())
+            .finish()
+    }
+}
+
+impl SignatureSchemes {
+    pub(crate) async fn load(db: &Db) -> Result<Self, Error> {
+        let schemes = db.signature_schemes().await?;
+
+        Ok(SignatureSchemes {
+            inner: Arc::new(schemes.into_iter().collect()),
+            dirty: Arc::new(Mutex::new(HashSet::new())),
+        })
+    }
+
+    /// Collect every negotiation that changed since the last call
+    pub(crate) fn take_dirty(&self) -> HashMap<String, Negotiated> {
+        let dirty = std::mem::take(&mut *self.dirty.lock().unwrap());
+
+        dirty
+            .into_iter()
+            .filter_map(|authority| {
+                let negotiated = *self.inner.get(&authority)?;
+                Some((authority, negotiated))
+            })
+            .collect()
+    }
+
+    /// The scheme to sign with first, and whether to try the other one if it's rejected
+    fn choose(&self, authority: &str) -> (SignatureScheme, bool) {
+        match self.get(authority) {
+            None => (SignatureScheme::Rfc9421, true),
+            Some(Negotiated::Accepted(scheme)) => (scheme, true),
+            Some(Negotiated::Refused { scheme, since }) => {
+                let renegotiate = since
+                    .elapsed()
+                    .is_ok_and(|elapsed| elapsed > RENEGOTIATE_AFTER);
+
+                (scheme, renegotiate)
+            }
+        }
+    }
+
+    fn get(&self, authority: &str) -> Option<Negotiated> {
+        self.inner.get(authority).map(|negotiated| *negotiated)
+    }
+
+    fn accept(&self, authority: &str, scheme: SignatureScheme) {
+        self.set(authority, Negotiated::Accepted(scheme));
+    }
+
+    /// Remember a server refused both schemes, keeping to the one it accepted before if any
+    fn refuse(&self, authority: &str) {
+        let scheme = match self.get(authority) {
+            Some(Negotiated::Accepted(scheme) | Negotiated::Refused { scheme, .. }) => scheme,
+            // Most of the fediverse only understands draft-cavage
+            None => SignatureScheme::Cavage,
+        };
+
+        self.set(
+            authority,
+            Negotiated::Refused {
+                scheme,
+                since: SystemTime::now(),
+            },
+        );
+    }
+
+    fn set(&self, authority: &str, negotiated: Negotiated) {
+        if self.inner.insert(authority.to_owned(), negotiated) != Some(negotiated) {
+            self.dirty.lock().unwrap().insert(authority.to_owned());
+        }
+    }
+}
+
+/// The key requests are signed with, swapped out when the relay's key is rotated
+struct SigningKey {
     key_id: String,
     private_key: Arc<RsaKeyPair>,
+    public_key: RsaPublicKey,
+}
+
+impl SigningKey {
+    fn new(key_id: String, private_key: RsaPrivateKey) -> Self {
+        let public_key = private_key.to_public_key();
+        let private_key_der = private_key.to_pkcs1_der().expect("Can encode der");
+        let private_key = ring::signature::RsaKeyPair::from_der(private_key_der.as_bytes())
+            .expect("Key is valid");
+
+        SigningKey {
+            key_id,
+            private_key: Arc::new(private_key),
+            public_key,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Requests {
+    client: ClientWithMiddleware,
+    key: Arc<RwLock<Arc<SigningKey>>>,
     rng: SystemRandom,
     config: Config<Spawner>,
     breakers: Breakers,
     last_online: Arc<LastOnline>,
+    signature_schemes: SignatureSchemes,
+    spawner: Spawner,
 }
 
 impl std::fmt::Debug for Requests {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Requests")
-            .field("key_id", &self.key_id)
+            .field("key_id", &self.key.read().unwrap().key_id)
             .field("config", &self.config)
             .field("breakers", &self.breakers)
             .finish()
@@ -178,25 +464,37 @@ impl Requests {
         private_key: RsaPrivateKey,
         breakers: Breakers,
         last_online: Arc<LastOnline>,
+        signature_schemes: SignatureSchemes,
         spawner: Spawner,
         client: ClientWithMiddleware,
     ) -> Self {
-        let private_key_der = private_key.to_pkcs1_der().expect("Can encode der");
-        let private_key = ring::signature::RsaKeyPair::from_der(private_key_der.as_bytes())
-            .expect("Key is valid");
         Requests {
             client,
-            key_id,
-            private_key: Arc::new(private_key),
+            key: Arc::new(RwLock::new(Arc::new(SigningKey::new(key_id, private_key)))),
             rng: SystemRandom::new(),
-            config: Config::new_with_spawner(spawner).mastodon_compat(),
+            config: Config::new_with_spawner(spawner.clone()).mastodon_compat(),
             breakers,
             last_online,
+            signature_schemes,
+            spawner,
         }
     }
 
+    /// The id and public half of the key requests are currently signed with
+    pub(crate) fn public_key(&self) -> (String, RsaPublicKey) {
+        let key = self.key.read().unwrap();
+
+        (key.key_id.clone(), key.public_key.clone())
+    }
+
+    /// Sign every request from now on with a new key
+    pub(crate) fn rotate_key(&self, key_id: String, private_key: RsaPrivateKey) {
+        *self.key.write().unwrap() = Arc::new(SigningKey::new(key_id, private_key));
+    }
+
     pub(crate) fn spawner(mut self, spawner: Spawner) -> Self {
-        self.config = self.config.set_spawner(spawner);
+        self.config = self.config.set_spawner(spawner.clone());
+        self.spawner = spawner;
         self
     }
 
@@ -204,6 +502,19 @@ impl Requests {
         self.breakers.succeed(iri);
     }
 
+    pub(crate) fn reset_breaker_for_domain(&self, authority: &str) {
+        self.breakers.reset(authority);
+    }
+
+    /// How much longer the inbox's domain asked us to hold off, if it's still paused
+    pub(crate) fn paused_for(&self, url: &IriString) -> Option<Duration> {
+        self.breakers.paused_for(url)
+    }
+
+    pub(crate) fn breakers(&self) -> Vec<(String, Breaker)> {
+        self.breakers.snapshot()
+    }
+
     async fn check_response(
         &self,
         parsed_url: &IriString,
@@ -211,7 +522,7 @@ impl Requests {
         res: Result<reqwest::Response, reqwest_middleware::Error>,
     ) -> Result<reqwest::Response, Error> {
         if res.is_err() {
-            self.breakers.fail(&parsed_url);
+            self.breakers.fail(parsed_url);
         }
 
         let res = res?;
@@ -225,7 +536,11 @@ impl Requests {
         };
 
         if !success {
-            self.breakers.fail(&parsed_url);
+            // An overloaded server telling us when to come back isn't failing
+            match retry_after(status, res.headers(), self.breakers.schedule.max) {
+                Some(until) => self.breakers.pause(parsed_url, until),
+                None => self.breakers.fail(parsed_url),
+            }
 
             if let Ok(s) = res.text().await {
                 if !s.is_empty() {
@@ -333,29 +648,28 @@ impl Requests {
         accept: &str,
         strategy: BreakerStrategy,
     ) -> Result<reqwest::Response, Error> {
-        if !self.breakers.should_try(url) {
-            return Err(ErrorKind::Breaker.into());
-        }
+        self.send_signed(url, strategy, |scheme| async move {
+            let builder = self.client.get(url.as_str()).header("Accept", accept);
 
-        let signer = self.signer();
-        let span = tracing::Span::current();
-
-        let request = self
-            .client
-            .get(url.as_str())
-            .header("Accept", accept)
-            .header("Date", Date(SystemTime::now().into()).to_string())
-            .signature(&self.config, self.key_id.clone(), move |signing_string| {
-                span.record("signing_string", signing_string);
-                span.in_scope(|| signer.sign(signing_string))
-            })
-            .await?;
+            match scheme {
+                SignatureScheme::Rfc9421 => self.sign_message(builder, "GET", url, None).await,
+                SignatureScheme::Cavage => {
+                    let (key_id, sign) = self.sign_fn();
+                    let span = tracing::Span::current();
 
-        let res = self.client.execute(request).await;
+                    let request = builder
+                        .header("Date", Date(SystemTime::now().into()).to_string())
+                        .signature(&self.config, key_id, move |signing_string| {
+                            span.record("signing_string", signing_string);
+                            span.in_scope(|| sign(signing_string))
+                        })
+                        .await?;
 
-        let res = self.check_response(url, strategy, res).await?;
-
-        Ok(res)
+                    Ok(request)
+                }
+            }
+        })
+        .await
     }
 
     #[tracing::instrument(
@@ -394,48 +708,198 @@ impl Requests {
     where
         T: serde::ser::Serialize + std::fmt::Debug,
     {
-        if !self.breakers.should_try(&inbox) {
+        let item_string = serde_json::to_string(item)?;
+
+        self.send_signed(inbox, strategy, |scheme| {
+            let item_string = item_string.clone();
+
+            async move {
+                let builder = self
+                    .client
+                    .post(inbox.as_str())
+                    .header("Accept", accept)
+                    .header("Content-Type", content_type);
+
+                match scheme {
+                    SignatureScheme::Rfc9421 => {
+                        self.sign_message(builder, "POST", inbox, Some(item_string))
+                            .await
+                    }
+                    SignatureScheme::Cavage => {
+                        let (key_id, sign) = self.sign_fn();
+                        let span = tracing::Span::current();
+
+                        let request = builder
+                            .header("Date", Date(SystemTime::now().into()).to_string())
+                            .signature_with_digest(
+                                self.config.clone(),
+                                key_id,
+                                Sha256::new(),
+                                item_string,
+                                move |signing_string| {
+                                    span.record("signing_string", signing_string);
+                                    span.in_scope(|| sign(signing_string))
+                                },
+                            )
+                            .await?;
+
+                        Ok(request)
+                    }
+                }
+            }
+        })
+        .await
+    }
+
+    /// Send a request signed with the scheme the server is known to accept
+    ///
+    /// Servers we haven't heard from yet are tried with RFC 9421 first. When a server rejects a
+    /// signature with a `401` or `403`, the request is retried once with the other scheme, and
+    /// whichever scheme the server accepts is remembered for next time. A server that refuses
+    /// both isn't sent the second request again for a day.
+    async fn send_signed<F, Fut>(
+        &self,
+        url: &IriString,
+        strategy: BreakerStrategy,
+        build: F,
+    ) -> Result<reqwest::Response, Error>
+    where
+        F: Fn(SignatureScheme) -> Fut,
+        Fut: Future<Output = Result<reqwest::Request, Error>>,
+    {
+        if !self.breakers.should_try(url) {
             return Err(ErrorKind::Breaker.into());
         }
 
+        let authority = url.authority_str().unwrap_or_default();
+        let (scheme, fallback) = self.signature_schemes.choose(authority);
+
+        let mut res = self.client.execute(build(scheme).await?).await;
+
+        let (scheme, res) = match &res {
+            Ok(response) if fallback && is_signature_rejection(response.status()) => {
+                let other = scheme.other();
+                tracing::debug!("{authority} rejected a {scheme:?} signature, trying {other:?}");
+                metrics::counter!("relay.signature.fallback").increment(1);
+
+                res = self.client.execute(build(other).await?).await;
+                (other, res)
+            }
+            _ => (scheme, res),
+        };
+
+        match &res {
+            Ok(response) if response.status().is_success() => {
+                self.signature_schemes.accept(authority, scheme);
+            }
+            Ok(response) if fallback && is_signature_rejection(response.status()) => {
+                tracing::debug!("{authority} refused both signature schemes");
+                self.signature_schemes.refuse(authority);
+            }
+            _ => {}
+        }
+
+        self.check_response(url, strategy, res).await
+    }
+
+    /// Sign a request with an RFC 9421 message signature
+    async fn sign_message(
+        &self,
+        builder: RequestBuilder,
+        method: &str,
+        url: &IriString,
+        body: Option<String>,
+    ) -> Result<reqwest::Request, Error> {
         let signer = self.signer();
+
+        let mut components = vec!["@method", "@target-uri"];
+        let mut digest = None;
+
+        if let Some(body) = &body {
+            components.push("content-digest");
+            digest = Some(content_digest(body.as_bytes()));
+        }
+
+        let input = SignatureInput::new("sig1", &components, &signer.key_id, "rsa-v1_5-sha256");
+        let signature_base = input.signature_base(|name| match name {
+            "@method" => Some(method.to_owned()),
+            "@target-uri" => Some(url.to_string()),
+            "content-digest" => digest.clone(),
+            _ => None,
+        })?;
+
         let span = tracing::Span::current();
-        let item_string = serde_json::to_string(item)?;
+        span.record("signing_string", signature_base.as_str());
 
-        let request = self
-            .client
-            .post(inbox.as_str())
-            .header("Accept", accept)
-            .header("Content-Type", content_type)
-            .header("Date", Date(SystemTime::now().into()).to_string())
-            .signature_with_digest(
-                self.config.clone(),
-                self.key_id.clone(),
-                Sha256::new(),
-                item_string,
-                move |signing_string| {
-                    span.record("signing_string", signing_string);
-                    span.in_scope(|| signer.sign(signing_string))
-                },
-            )
-            .await?;
+        let signature = self
+            .spawner
+            .spawn_blocking(move || span.in_scope(|| signer.sign(&signature_base)))
+            .await??;
 
-        let res = self.client.execute(request).await;
+        let mut builder = builder
+            .header("Signature-Input", input.header())
+            .header("Signature", format!("{}=:{signature}:", input.label));
 
-        let res = self.check_response(inbox, strategy, res).await?;
+        if let (Some(body), Some(digest)) = (body, digest) {
+            builder = builder.header("Content-Digest", digest).body(body);
+        }
 
-        Ok(res)
+        Ok(builder.build()?)
     }
 
     fn signer(&self) -> Signer {
+        let key = self.key.read().unwrap().clone();
+
         Signer {
-            private_key: self.private_key.clone(),
+            key_id: key.key_id.clone(),
+            private_key: key.private_key.clone(),
             rng: self.rng.clone(),
         }
     }
+
+    /// The current key id, along with a signing function for the matching key
+    fn sign_fn(
+        &self,
+    ) -> (
+        String,
+        impl FnOnce(&str) -> Result<String, Error> + Send + 'static,
+    ) {
+        let signer = self.signer();
+        let key_id = signer.key_id.clone();
+
+        (key_id, move |signing_string: &str| {
+            signer.sign(signing_string)
+        })
+    }
+}
+
+/// When a `429` or `503` response asks to be retried, from either delay seconds or an HTTP date
+///
+/// Delays are capped at `max`, since the header's value is up to the remote server.
+fn retry_after(
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    max: Duration,
+) -> Option<SystemTime> {
+    if !matches!(status.as_u16(), 429 | 503) {
+        return None;
+    }
+
+    let value = headers.get("Retry-After")?.to_str().ok()?.trim();
+
+    match value.parse::<u64>() {
+        Ok(seconds) => SystemTime::now().checked_add(Duration::from_secs(seconds).min(max)),
+        Err(_) => value.parse::<HttpDate>().ok().map(SystemTime::from),
+    }
+}
+
+/// Whether a response means the server refused the request's signature
+fn is_signature_rejection(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 401 | 403)
 }
 
 struct Signer {
+    key_id: String,
     private_key: Arc<RsaKeyPair>,
     rng: SystemRandom,
 }
@@ -456,3 +920,162 @@ impl Signer {
         Ok(STANDARD.encode(&signature))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        retry_after, Breaker, BreakerSchedule, Breakers, Negotiated, SignatureScheme,
+        SignatureSchemes,
+    };
+    use crate::db::Db;
+    use activitystreams::iri_string::types::IriString;
+    use reqwest::{header::HeaderMap, StatusCode};
+    use std::time::{Duration, SystemTime};
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn backs_off_exponentially() {
+        let schedule = BreakerSchedule::new(3, MINUTE, MINUTE * 10);
+
+        assert_eq!(schedule.wait(2), Duration::ZERO);
+        assert_eq!(schedule.wait(3), MINUTE);
+        assert_eq!(schedule.wait(4), MINUTE * 2);
+        assert_eq!(schedule.wait(6), MINUTE * 8);
+        assert_eq!(schedule.wait(7), MINUTE * 10);
+        assert_eq!(schedule.wait(usize::MAX), MINUTE * 10);
+
+        let mut breaker = Breaker::default();
+        breaker.fail(&schedule);
+        breaker.fail(&schedule);
+        assert!(breaker.should_try());
+        breaker.fail(&schedule);
+        assert!(!breaker.should_try());
+        breaker.succeed();
+        assert!(breaker.should_try());
+    }
+
+    #[tokio::test]
+    async fn persists_breakers() {
+        let db = Db::temporary();
+        let schedule = BreakerSchedule::new(1, MINUTE, MINUTE);
+        let down: IriString = "https://down.example/inbox".parse().unwrap();
+        let up: IriString = "https://up.example/inbox".parse().unwrap();
+
+        let breakers = Breakers::load(&db, schedule).await.unwrap();
+        breakers.fail(&down);
+        breakers.succeed(&up);
+        assert!(!breakers.should_try(&down));
+
+        let dirty = breakers.take_dirty();
+        assert_eq!(dirty.len(), 2);
+        db.save_breakers(dirty).await.unwrap();
+
+        // A success that changes nothing isn't saved again
+        breakers.succeed(&up);
+        assert!(breakers.take_dirty().is_empty());
+
+        let reloaded = Breakers::load(&db, schedule).await.unwrap();
+        assert!(!reloaded.should_try(&down));
+        assert!(reloaded.should_try(&up));
+        assert_eq!(reloaded.inner.get("down.example").unwrap().failures(), 1);
+    }
+
+    #[tokio::test]
+    async fn persists_resets() {
+        let db = Db::temporary();
+        let schedule = BreakerSchedule::new(1, MINUTE, MINUTE);
+        let down: IriString = "https://down.example/inbox".parse().unwrap();
+
+        let breakers = Breakers::load(&db, schedule).await.unwrap();
+        breakers.fail(&down);
+        db.save_breakers(breakers.take_dirty()).await.unwrap();
+
+        breakers.reset("down.example");
+        assert!(breakers.should_try(&down));
+
+        let dirty = breakers.take_dirty();
+        assert!(matches!(dirty.get("down.example"), Some(None)));
+        db.save_breakers(dirty).await.unwrap();
+
+        let reloaded = Breakers::load(&db, schedule).await.unwrap();
+        assert!(reloaded.should_try(&down));
+        assert!(reloaded.snapshot().is_empty());
+    }
+
+    #[test]
+    fn pauses_without_failing() {
+        let mut breaker = Breaker::default();
+
+        breaker.pause(SystemTime::now() + MINUTE);
+        assert_eq!(breaker.failures(), 0);
+        assert!(!breaker.should_try());
+        assert!(breaker.paused_until().is_some());
+
+        breaker.pause(SystemTime::now() - MINUTE);
+        assert!(!breaker.should_try());
+
+        let mut expired = Breaker::default();
+        expired.pause(SystemTime::now() - MINUTE);
+        assert!(expired.should_try());
+        assert!(expired.paused_until().is_none());
+    }
+
+    #[test]
+    fn caps_retry_after() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Retry-After", u64::MAX.to_string().parse().unwrap());
+
+        let until = retry_after(StatusCode::TOO_MANY_REQUESTS, &headers, MINUTE).unwrap();
+        assert!(until <= SystemTime::now() + MINUTE);
+
+        headers.insert(
+            "Retry-After",
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert!(retry_after(StatusCode::SERVICE_UNAVAILABLE, &headers, MINUTE).is_some());
+        assert!(retry_after(StatusCode::FORBIDDEN, &headers, MINUTE).is_none());
+    }
+
+    #[test]
+    fn remembers_refusals() {
+        let schemes = SignatureSchemes {
+            inner: Default::default(),
+            dirty: Default::default(),
+        };
+
+        assert_eq!(
+            schemes.choose("new.example"),
+            (SignatureScheme::Rfc9421, true)
+        );
+
+        schemes.accept("old.example", SignatureScheme::Cavage);
+        schemes.refuse("old.example");
+        assert_eq!(
+            schemes.choose("old.example"),
+            (SignatureScheme::Cavage, false)
+        );
+
+        schemes.refuse("new.example");
+        assert_eq!(
+            schemes.choose("new.example"),
+            (SignatureScheme::Cavage, false)
+        );
+
+        schemes.set(
+            "stale.example",
+            Negotiated::Refused {
+                scheme: SignatureScheme::Cavage,
+                since: SystemTime::now() - Duration::from_secs(60 * 60 * 25),
+            },
+        );
+        assert_eq!(
+            schemes.choose("stale.example"),
+            (SignatureScheme::Cavage, true)
+        );
+
+        let dirty = schemes.take_dirty();
+        assert_eq!(dirty.len(), 3);
+        assert!(schemes.take_dirty().is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 mod actor;
+mod collection;
 mod healthz;
 mod inbox;
 mod index;
@@ -8,7 +9,8 @@ mod statics;
 mod webfinger;
 
 pub(crate) use self::{
-    actor::route as actor,
+    actor::{key as actor_key, route as actor},
+    collection::{followers, following, outbox},
     healthz::route as healthz,
     inbox::route as inbox,
     index::route as index,
@@ -1,52 +1,58 @@
 use crate::{
-    apub::{PublicKey, PublicKeyInner},
+    apub::{relay_actor, PublicKeyInner},
     config::{Config, UrlKind},
     data::State,
     error::Error,
     routes::ok,
 };
-use activitystreams::{
-    actor::{ApActor, Application, Endpoints},
-    context,
-    prelude::*,
-    security,
-};
-use activitystreams_ext::Ext1;
-use actix_web::{web, Responder};
+use activitystreams::security;
+use actix_web::{web, HttpResponse, Responder};
 use rsa::pkcs8::EncodePublicKey;
+use uuid::Uuid;
 
 #[tracing::instrument(name = "Actor", skip(config, state))]
 pub(crate) async fn route(
     state: web::Data<State>,
     config: web::Data<Config>,
 ) -> Result<impl Responder, Error> {
-    let mut application = Ext1::new(
-        ApActor::new(config.generate_url(UrlKind::Inbox), Application::new()),
-        PublicKey {
-            public_key: PublicKeyInner {
-                id: config.generate_url(UrlKind::MainKey),
-                owner: config.generate_url(UrlKind::Actor),
-                public_key_pem: state
-                    .public_key
-                    .to_public_key_pem(rsa::pkcs8::LineEnding::default())?,
-            },
-        },
-    );
-
-    application
-        .set_id(config.generate_url(UrlKind::Actor))
-        .set_summary("AodeRelay bot")
-        .set_name("Fediverse Relay")
-        .set_url(config.generate_url(UrlKind::Actor))
-        .set_many_contexts(vec![context(), security()])
-        .set_preferred_username("relay")
-        .set_outbox(config.generate_url(UrlKind::Outbox))
-        .set_followers(config.generate_url(UrlKind::Followers))
-        .set_following(config.generate_url(UrlKind::Following))
-        .set_endpoints(Endpoints {
-            shared_inbox: Some(config.generate_url(UrlKind::Inbox)),
-            ..Default::default()
-        });
-
-    Ok(ok(application))
+    let retired = state.retired_keys(config.key_rotation_grace()).await?;
+
+    Ok(ok(relay_actor(&config, &state.requests, &retired)?))
+}
+
+/// Serve one of the relay's keys, including keys retired within the rotation grace period
+#[tracing::instrument(name = "Actor key", skip(config, state))]
+pub(crate) async fn key(
+    state: web::Data<State>,
+    config: web::Data<Config>,
+    uuid: web::Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let key_id = config.generate_url(UrlKind::Key(uuid.into_inner()));
+    let (current_key_id, public_key) = state.requests.public_key();
+
+    let public_key_pem = if current_key_id == key_id.as_str() {
+        Some(public_key.to_public_key_pem(rsa::pkcs8::LineEnding::default())?)
+    } else {
+        state
+            .retired_keys(config.key_rotation_grace())
+            .await?
+            .into_iter()
+            .find(|retired| retired.key_id == key_id)
+            .map(|retired| retired.public_key_pem)
+    };
+
+    let Some(public_key_pem) = public_key_pem else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let key = PublicKeyInner {
+        id: key_id,
+        owner: config.generate_url(UrlKind::Actor),
+        public_key_pem,
+    };
+
+    let mut value = serde_json::to_value(key)?;
+    value["@context"] = serde_json::to_value(security())?;
+
+    Ok(ok(value))
 }
@@ -0,0 +1,151 @@
+use crate::{
+    config::{Config, UrlKind},
+    data::State,
+    error::Error,
+    routes::ok,
+};
+use activitystreams::{
+    base::AnyBase,
+    collection::{OrderedCollection, OrderedCollectionPage},
+    context,
+    iri_string::types::IriString,
+    prelude::*,
+};
+use actix_web::{web, HttpResponse};
+
+const PAGE_SIZE: usize = 20;
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct PageQuery {
+    page: Option<usize>,
+}
+
+#[tracing::instrument(name = "Outbox", skip(config, state))]
+pub(crate) async fn outbox(
+    state: web::Data<State>,
+    config: web::Data<Config>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, Error> {
+    let id = config.generate_url(UrlKind::Outbox);
+    let total_items = state.db.announce_count().await?;
+
+    let Some(page) = query.page else {
+        return Ok(ok(collection(id, total_items)?));
+    };
+
+    let page = page.max(1);
+    let items = state
+        .db
+        .announces(page_offset(page), PAGE_SIZE)
+        .await?
+        .into_iter()
+        .map(AnyBase::from_arbitrary_json)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ok(collection_page(id, page, total_items, items)?))
+}
+
+#[tracing::instrument(name = "Followers", skip(config, state))]
+pub(crate) async fn followers(
+    state: web::Data<State>,
+    config: web::Data<Config>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, Error> {
+    connected(&state, config.generate_url(UrlKind::Followers), query.page).await
+}
+
+#[tracing::instrument(name = "Following", skip(config, state))]
+pub(crate) async fn following(
+    state: web::Data<State>,
+    config: web::Data<Config>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, Error> {
+    connected(&state, config.generate_url(UrlKind::Following), query.page).await
+}
+
+async fn connected(
+    state: &State,
+    id: IriString,
+    page: Option<usize>,
+) -> Result<HttpResponse, Error> {
+    let connected = state.db.connected_ids().await?;
+
+    let Some(page) = page else {
+        return Ok(ok(collection(id, connected.len())?));
+    };
+
+    let page = page.max(1);
+    let total_items = connected.len();
+    let items = connected
+        .into_iter()
+        .skip(page_offset(page))
+        .take(PAGE_SIZE)
+        .map(AnyBase::from)
+        .collect();
+
+    Ok(ok(collection_page(id, page, total_items, items)?))
+}
+
+/// Number of items before `page`, saturating so huge page numbers land past the end
+fn page_offset(page: usize) -> usize {
+    (page - 1).saturating_mul(PAGE_SIZE)
+}
+
+fn page_url(id: &IriString, page: usize) -> Result<IriString, Error> {
+    Ok(format!("{id}?page={page}").parse()?)
+}
+
+fn collection(id: IriString, total_items: usize) -> Result<OrderedCollection, Error> {
+    let mut collection = OrderedCollection::new();
+
+    collection
+        .set_total_items(total_items as u64)
+        .set_first(page_url(&id, 1)?)
+        .set_id(id)
+        .set_context(context());
+
+    Ok(collection)
+}
+
+fn collection_page(
+    id: IriString,
+    page: usize,
+    total_items: usize,
+    items: Vec<AnyBase>,
+) -> Result<OrderedCollectionPage, Error> {
+    let mut collection_page = OrderedCollectionPage::new();
+
+    collection_page
+        .set_part_of(id.clone())
+        .set_many_ordered_items(items)
+        .set_total_items(total_items as u64)
+        .set_id(page_url(&id, page)?)
+        .set_context(context());
+
+    if page.saturating_mul(PAGE_SIZE) < total_items {
+        collection_page.set_next(page_url(&id, page + 1)?);
+    }
+
+    if page > 1 {
+        collection_page.set_prev(page_url(&id, page - 1)?);
+    }
+
+    Ok(collection_page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn huge_pages_are_empty() {
+        let id: IriString = "https://relay.example/outbox".parse().unwrap();
+
+        assert_eq!(page_offset(usize::MAX), usize::MAX);
+
+        let page = collection_page(id, usize::MAX, 3, Vec::new()).unwrap();
+
+        assert!(page.next().is_none());
+        assert!(page.prev().is_some());
+    }
+}
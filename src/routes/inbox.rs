@@ -1,11 +1,15 @@
 use crate::{
     apub::{AcceptedActivities, AcceptedUndoObjects, UndoTypes, ValidTypes},
     config::{Config, UrlKind},
-    data::{ActorCache, State},
-    db::Actor,
+    data::{retry_after, ActorCache, MaybeCached, State, Visibility},
+    data_integrity::Proof,
+    db::{Actor, PendingFollow},
     error::{Error, ErrorKind},
-    jobs::apub::{Announce, Follow, Forward, Reject, Undo},
+    jobs::apub::{Announce, Follow, Forward, Reject, Undo, UndoAnnounce},
     jobs::JobServer,
+    keys::VerifyingKey,
+    middleware::MessageSignatureVerified,
+    notify::{Event, Notifier},
     requests::Requests,
     routes::accepted,
 };
@@ -15,8 +19,11 @@ use activitystreams::{
 };
 use actix_web::{web, HttpResponse};
 use http_signature_normalization_actix::prelude::{DigestVerified, SignatureVerified};
+use std::time::SystemTime;
+use time::OffsetDateTime;
+use uuid::Uuid;
 
-#[tracing::instrument(name = "Inbox", skip_all, fields(id = tracing::field::debug(&input.id_unchecked()), kind = tracing::field::debug(&input.kind())))]
+#[tracing::instrument(name = "Inbox", skip_all, fields(id, kind))]
 #[allow(clippy::too_many_arguments)]
 pub(crate) async fn route(
     state: web::Data<State>,
@@ -24,18 +31,41 @@ pub(crate) async fn route(
     config: web::Data<Config>,
     client: web::Data<Requests>,
     jobs: web::Data<JobServer>,
-    input: web::Json<AcceptedActivities>,
+    notifier: web::Data<Notifier>,
+    body: web::Bytes,
     digest_verified: Option<DigestVerified>,
     signature_verified: Option<SignatureVerified>,
+    message_verified: Option<MessageSignatureVerified>,
 ) -> Result<HttpResponse, Error> {
-    let input = input.into_inner();
+    // Proofs are checked against the JSON as it was received, since re-serializing the typed
+    // activity can rewrite fields the proof covers
+    let activity: serde_json::Value =
+        serde_json::from_slice(&body).map_err(ErrorKind::MalformedActivity)?;
+    let input: AcceptedActivities =
+        serde_json::from_value(activity.clone()).map_err(ErrorKind::MalformedActivity)?;
+
+    let span = tracing::Span::current();
+    span.record("id", tracing::field::debug(&input.id_unchecked()));
+    span.record("kind", tracing::field::debug(&input.kind()));
 
     let kind = input.kind().ok_or(ErrorKind::MissingKind)?;
 
-    if digest_verified.is_some() && signature_verified.is_none() && *kind == ValidTypes::Delete {
+    // An RFC 9421 signature covers the Content-Digest, so it vouches for the body as well
+    let body_verified = digest_verified.is_some() || message_verified.is_some();
+    let key_id = signature_verified
+        .map(|verified| verified.key_id().to_owned())
+        .or_else(|| message_verified.map(|verified| verified.key_id().to_owned()));
+
+    // An integrity proof vouches for the activity itself, however it reached us. It only stands in
+    // for a signature on kinds that get forwarded, since anyone who has seen a proven Follow or
+    // Undo could otherwise replay it
+    let proof = Proof::find(&activity).filter(|_| is_forwarded_kind(kind));
+
+    if body_verified && key_id.is_none() && proof.is_none() && *kind == ValidTypes::Delete {
         return Ok(accepted(serde_json::json!({})));
     } else if config.validate_signatures()
-        && (digest_verified.is_none() || signature_verified.is_none())
+        && proof.is_none()
+        && (!body_verified || key_id.is_none())
     {
         return Err(ErrorKind::NoSignature(None).into());
     }
@@ -49,26 +79,54 @@ pub(crate) async fn route(
             .ok_or(ErrorKind::MissingId)?
     };
 
-    let actor = actors.get(actor_id, &client).await?.into_inner();
+    let actor = actors.get(actor_id, &client).await?;
+
+    let actor = if let Some(proof) = &proof {
+        // Forwarded activities carry the forwarder's signature rather than the author's, so the
+        // proof stands in for checking the signing key
+        check_proof(&actors, &client, actor, proof, &activity).await?
+    } else {
+        let actor = actor.into_inner();
+
+        if let Some(key_id) = key_id {
+            if !actor.owns_key(&key_id) {
+                tracing::error!("Actor signed with wrong key");
+                return Err(ErrorKind::BadActor(actor.public_key_id.to_string(), key_id).into());
+            }
+        } else if config.validate_signatures() {
+            tracing::error!("This case should never be reachable, since I handle signature checks earlier in the flow. If you see this in a log it means I did it wrong");
+            return Err(ErrorKind::NoSignature(Some(actor.public_key_id.to_string())).into());
+        }
+
+        actor
+    };
+
+    if let Some(limiter) = &state.inbox_limits.domains {
+        let domain = actor.id.authority_str().unwrap_or_default();
 
-    if let Some(verified) = signature_verified {
-        if actor.public_key_id.as_str() != verified.key_id() {
-            tracing::error!("Actor signed with wrong key");
-            return Err(ErrorKind::BadActor(
-                actor.public_key_id.to_string(),
-                verified.key_id().to_owned(),
-            )
-            .into());
+        if let Err(wait) = limiter.check(domain) {
+            tracing::warn!("Rate limiting inbox requests from {domain}");
+            metrics::counter!("relay.inbox.rate-limited", "key" => "domain").increment(1);
+            return Err(ErrorKind::RateLimited(retry_after(wait)).into());
         }
-    } else if config.validate_signatures() {
-        tracing::error!("This case should never be reachable, since I handle signature checks earlier in the flow. If you see this in a log it means I did it wrong");
-        return Err(ErrorKind::NoSignature(Some(actor.public_key_id.to_string())).into());
     }
 
     let is_allowed = state.db.is_allowed(actor.id.clone()).await?;
     let is_connected = state.db.is_connected(actor.id.clone()).await?;
 
+    if state.db.is_actor_blocked(actor.id.clone()).await? {
+        return Err(ErrorKind::NotAllowed(actor.id.to_string()).into());
+    }
+
     if !is_allowed {
+        if config.restricted_mode()
+            && *kind == ValidTypes::Follow
+            && !state.db.is_blocked(actor.id.clone()).await?
+        {
+            handle_pending_follow(&config, &state, &notifier, input, actor).await?;
+            return Ok(accepted(serde_json::json!({})));
+        }
+
         return Err(ErrorKind::NotAllowed(actor.id.to_string()).into());
     }
 
@@ -80,10 +138,16 @@ pub(crate) async fn route(
         ValidTypes::Accept => handle_accept(&config, input).await?,
         ValidTypes::Reject => handle_reject(&config, &jobs, input, actor).await?,
         ValidTypes::Announce | ValidTypes::Create => {
-            handle_announce(&state, &jobs, input, actor).await?
+            if state.db.is_silenced(actor.id.clone()).await? {
+                tracing::info!("Not relaying post from silenced domain");
+                return Ok(accepted(serde_json::json!({})));
+            }
+
+            handle_announce(&config, &state, &jobs, input, actor).await?
         }
         ValidTypes::Follow => handle_follow(&config, &jobs, input, actor).await?,
-        ValidTypes::Add | ValidTypes::Delete | ValidTypes::Remove | ValidTypes::Update => {
+        ValidTypes::Delete => handle_delete(&jobs, input, actor).await?,
+        ValidTypes::Add | ValidTypes::Remove | ValidTypes::Update => {
             handle_forward(&jobs, input, actor).await?
         }
         ValidTypes::Undo => handle_undo(&config, &jobs, input, actor, is_connected).await?,
@@ -92,6 +156,60 @@ pub(crate) async fn route(
     Ok(accepted(serde_json::json!({})))
 }
 
+/// Check an activity's integrity proof against its actor's `assertionMethod` keys
+///
+/// A proof made with a key the cached actor doesn't list may be from a key added since, so the
+/// actor is refetched once before the proof is rejected.
+async fn check_proof(
+    actors: &ActorCache,
+    client: &Requests,
+    actor: MaybeCached<Actor>,
+    proof: &Proof<'_>,
+    activity: &serde_json::Value,
+) -> Result<Actor, Error> {
+    proof.check_created(SystemTime::now())?;
+
+    let verification_method = proof
+        .verification_method()
+        .ok_or(ErrorKind::Proof("missing verification method"))?;
+
+    let find_key = |actor: &Actor| {
+        actor
+            .assertion_keys
+            .iter()
+            .find(|key| key.id.as_str() == verification_method)
+            .and_then(|key| VerifyingKey::from_multibase(&key.public_key_multibase))
+    };
+
+    let was_cached = actor.is_cached();
+    let mut actor = actor.into_inner();
+    let mut key = find_key(&actor);
+
+    if key.is_none() && was_cached {
+        actor = actors.get_no_cache(&actor.id, client).await?;
+        key = find_key(&actor);
+    }
+
+    let key = key.ok_or(ErrorKind::Proof("unknown verification method"))?;
+
+    if let Err(e) = proof.verify(activity, &key) {
+        tracing::warn!("Rejecting activity with a forged proof from {}", actor.id);
+        metrics::counter!("relay.proof.rejected").increment(1);
+        return Err(e);
+    }
+
+    metrics::counter!("relay.proof.verified").increment(1);
+
+    Ok(actor)
+}
+
+fn is_forwarded_kind(kind: &ValidTypes) -> bool {
+    matches!(
+        kind,
+        ValidTypes::Announce | ValidTypes::Create | ValidTypes::Delete | ValidTypes::Update
+    )
+}
+
 fn valid_without_listener(input: &AcceptedActivities) -> Result<bool, Error> {
     match input.kind() {
         Some(ValidTypes::Follow) => Ok(true),
@@ -172,6 +290,11 @@ async fn handle_undo(
 
     if !undone_object.is_kind(&UndoTypes::Follow) {
         if is_listener {
+            if let Some(object_id) = undone_object.object_unchecked().as_single_id() {
+                jobs.queue(UndoAnnounce::new(object_id.to_owned(), actor.clone()))
+                    .await?;
+            }
+
             jobs.queue(Forward::new(input, actor)).await?;
             return Ok(());
         } else {
@@ -196,6 +319,19 @@ async fn handle_undo(
     Ok(())
 }
 
+async fn handle_delete(
+    jobs: &JobServer,
+    input: AcceptedActivities,
+    actor: Actor,
+) -> Result<(), Error> {
+    if let Some(object_id) = input.object_unchecked().as_single_id() {
+        jobs.queue(UndoAnnounce::new(object_id.to_owned(), actor.clone()))
+            .await?;
+    }
+
+    handle_forward(jobs, input, actor).await
+}
+
 async fn handle_forward(
     jobs: &JobServer,
     input: AcceptedActivities,
@@ -207,6 +343,7 @@ async fn handle_forward(
 }
 
 async fn handle_announce(
+    config: &Config,
     state: &State,
     jobs: &JobServer,
     input: AcceptedActivities,
@@ -217,11 +354,31 @@ async fn handle_announce(
         .as_single_id()
         .ok_or(ErrorKind::MissingId)?;
 
-    if state.is_cached(object_id) {
+    if state.is_cached(object_id).await? {
         return Err(ErrorKind::Duplicate.into());
     }
 
-    jobs.queue(Announce::new(object_id.to_owned(), actor))
+    // A boost's addressing says nothing about the post being boosted, and relays address theirs
+    // to their followers, so only a Create's own addressing is checked
+    let is_announce = input.kind() == Some(&ValidTypes::Announce);
+
+    let activity = serde_json::to_value(&input)?;
+    let embedded = activity.get("object").filter(|object| object.is_object());
+
+    let is_visible = Some(&activity)
+        .filter(|_| !is_announce)
+        .into_iter()
+        .chain(embedded)
+        .all(|value| config.relay_visibility().allows(Visibility::of(value)));
+
+    if !is_visible {
+        tracing::info!("Not relaying {object_id}, it isn't addressed publicly");
+        metrics::counter!("relay.visibility.dropped").increment(1);
+        return Ok(());
+    }
+
+    // The post behind a boost is fetched, so its own addressing is checked then
+    jobs.queue(Announce::new(object_id.to_owned(), actor, is_announce))
         .await?;
 
     Ok(())
@@ -233,6 +390,46 @@ async fn handle_follow(
     input: AcceptedActivities,
     actor: Actor,
 ) -> Result<(), Error> {
+    check_follow_object(config, &input)?;
+
+    jobs.queue(Follow::new(input, actor)).await?;
+
+    Ok(())
+}
+
+async fn handle_pending_follow(
+    config: &Config,
+    state: &State,
+    notifier: &Notifier,
+    input: AcceptedActivities,
+    actor: Actor,
+) -> Result<(), Error> {
+    check_follow_object(config, &input)?;
+
+    let id = Uuid::new_v4();
+    let actor_id = actor.id.clone();
+
+    let added = state
+        .db
+        .add_pending_follow(PendingFollow {
+            id,
+            input,
+            actor,
+            requested_at: OffsetDateTime::now_utc(),
+        })
+        .await?;
+
+    // Admins hear about each domain once, however many of its actors ask
+    if added {
+        notifier.notify(Event::PendingFollow { id, actor_id });
+    } else {
+        tracing::debug!("A follow request from {actor_id}'s domain is already pending");
+    }
+
+    Ok(())
+}
+
+fn check_follow_object(config: &Config, input: &AcceptedActivities) -> Result<(), Error> {
     let my_id: IriString = config.generate_url(UrlKind::Actor);
 
     if !input.object_is(&my_id) && !input.object_is(&public()) {
@@ -241,7 +438,5 @@ async fn handle_follow(
         );
     }
 
-    jobs.queue(Follow::new(input, actor)).await?;
-
     Ok(())
 }
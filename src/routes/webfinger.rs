@@ -155,7 +155,7 @@ fn respond(config: &Config, state: &State) -> HttpResponse {
                 },
                 {
                     "rel": "magic-public-key",
-                    "href": state.public_key.as_magic_public_key()
+                    "href": state.requests.public_key().1.as_magic_public_key()
                 }
             ]
         }))
@@ -1,12 +1,32 @@
-use crate::db::Db;
-use std::sync::Arc;
+use crate::{
+    collector::{MemoryCollector, Section},
+    data::State,
+    db::{AuditAction, AuditEntry, BlockSeverity, Db, DomainBlock, PendingFollow, TokenScope},
+    error::ErrorKind,
+    jobs::{apub, JobServer},
+    notify::{Event, Notifier},
+};
+use activitystreams::iri_string::types::IriString;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::Arc,
+};
 use teloxide::{
     dispatching::{Dispatcher, UpdateFilterExt},
+    dptree,
+    payloads::{AnswerCallbackQuerySetters, SendMessageSetters},
     requests::Requester,
-    types::{Message, Update},
+    types::{
+        CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, Update, User,
+    },
     utils::command::BotCommands,
     Bot,
 };
+use uuid::Uuid;
+
+/// Telegram refuses messages longer than 4096 characters
+const MESSAGE_LIMIT: usize = 4000;
 
 #[derive(BotCommands, Clone, Debug)]
 #[command(
@@ -26,6 +46,12 @@ enum Command {
     #[command(description = "Unblock a domain from the relay.")]
     Unblock { domain: String },
 
+    #[command(description = "Block a single actor from the relay.")]
+    BlockActor { actor: String },
+
+    #[command(description = "Unblock a single actor from the relay.")]
+    UnblockActor { actor: String },
+
     #[command(description = "Allow a domain to connect to the relay (for RESTRICTED_MODE)")]
     Allow { domain: String },
 
@@ -35,11 +61,197 @@ enum Command {
     #[command(description = "List blocked domains")]
     ListBlocks,
 
+    #[command(description = "List blocked actors")]
+    ListBlockedActors,
+
     #[command(description = "List allowed domains")]
     ListAllowed,
 
     #[command(description = "List connected domains")]
     ListConnected,
+
+    #[command(description = "List follow requests waiting for approval (for RESTRICTED_MODE)")]
+    ListPending,
+
+    #[command(description = "Approve an actor's follow request (for RESTRICTED_MODE)")]
+    Approve { actor: String },
+
+    #[command(description = "Reject an actor's follow request (for RESTRICTED_MODE)")]
+    Reject { actor: String },
+
+    #[command(description = "Show the relay's statistics")]
+    Stats,
+
+    #[command(description = "List domains by when they were last successfully contacted")]
+    LastSeen,
+
+    #[command(description = "List domains whose deliveries are failing")]
+    Breakers,
+
+    #[command(description = "Show what the relay knows about a connected domain")]
+    Nodeinfo { domain: String },
+}
+
+/// Buttons to approve or reject a pending follow
+///
+/// Callback data is limited to 64 bytes, so the buttons carry the request's id rather than the actor.
+fn pending_keyboard(id: Uuid) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback("Approve", format!("approve:{id}")),
+        InlineKeyboardButton::callback("Reject", format!("reject:{id}")),
+    ]])
+}
+
+impl Command {
+    /// The permission an admin needs to run this command
+    fn scope(&self) -> Option<TokenScope> {
+        match self {
+            Self::Start | Self::Help => None,
+            Self::Block { .. }
+            | Self::Unblock { .. }
+            | Self::BlockActor { .. }
+            | Self::UnblockActor { .. }
+            | Self::ListBlocks
+            | Self::ListBlockedActors => Some(TokenScope::Blocks),
+            Self::Allow { .. }
+            | Self::Disallow { .. }
+            | Self::ListAllowed
+            | Self::ListPending
+            | Self::Approve { .. }
+            | Self::Reject { .. } => Some(TokenScope::Allows),
+            Self::ListConnected
+            | Self::Stats
+            | Self::LastSeen
+            | Self::Breakers
+            | Self::Nodeinfo { .. } => Some(TokenScope::Stats),
+        }
+    }
+}
+
+/// A telegram user allowed to administer the relay, parsed from `{user id}:{scope}+{scope}`
+///
+/// An admin listed without scopes may do everything.
+#[derive(Clone, Debug)]
+pub(crate) struct TelegramAdmin {
+    id: u64,
+    scopes: BTreeSet<TokenScope>,
+}
+
+impl std::str::FromStr for TelegramAdmin {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, scopes) = s.trim().split_once(':').unwrap_or((s.trim(), ""));
+
+        let id = id
+            .parse()
+            .map_err(|_| ErrorKind::TelegramAdmin(s.trim().to_owned()))?;
+
+        let mut scopes = scopes
+            .split('+')
+            .filter(|scope| !scope.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<BTreeSet<TokenScope>, _>>()?;
+
+        if scopes.is_empty() {
+            scopes.insert(TokenScope::Admin);
+        }
+
+        Ok(TelegramAdmin { id, scopes })
+    }
+}
+
+/// Who may use the bot, what each of them may do, and in which chats
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Access {
+    /// The single admin from `TELEGRAM_ADMIN_HANDLE`, who may do everything
+    handle: Option<String>,
+    admins: BTreeMap<u64, BTreeSet<TokenScope>>,
+    /// When not empty, the bot ignores every other chat
+    chats: BTreeSet<i64>,
+}
+
+impl Access {
+    pub(crate) fn new(handle: Option<String>, admins: Vec<TelegramAdmin>, chats: Vec<i64>) -> Self {
+        Access {
+            handle,
+            admins: admins
+                .into_iter()
+                .map(|admin| (admin.id, admin.scopes))
+                .collect(),
+            chats: chats.into_iter().collect(),
+        }
+    }
+
+    pub(crate) fn has_admins(&self) -> bool {
+        self.handle.is_some() || !self.admins.is_empty()
+    }
+
+    fn scopes(&self, id: u64, username: Option<&str>) -> Option<BTreeSet<TokenScope>> {
+        if let Some(scopes) = self.admins.get(&id) {
+            return Some(scopes.clone());
+        }
+
+        username
+            .filter(|username| self.handle.as_deref() == Some(*username))
+            .map(|_| BTreeSet::from([TokenScope::Admin]))
+    }
+
+    fn user_scopes(&self, user: Option<&User>) -> Option<BTreeSet<TokenScope>> {
+        user.and_then(|user| self.scopes(user.id.0, user.username.as_deref()))
+    }
+
+    fn is_admin(&self, user: Option<&User>) -> bool {
+        self.user_scopes(user).is_some()
+    }
+
+    fn permits(&self, user: Option<&User>, scope: TokenScope) -> bool {
+        self.user_scopes(user)
+            .map(|scopes| scopes.contains(&TokenScope::Admin) || scopes.contains(&scope))
+            .unwrap_or(false)
+    }
+
+    fn allows_chat(&self, chat_id: ChatId) -> bool {
+        self.chats.is_empty() || self.chats.contains(&chat_id.0)
+    }
+}
+
+/// Sends events to the admins' chats with the bot
+///
+/// Without a chat allowlist, the bot can only message a chat it has already seen, so the last chat an
+/// admin sent a command from is remembered and notified.
+#[derive(Clone)]
+pub(crate) struct Telegram {
+    bot: Bot,
+    db: Db,
+    access: Arc<Access>,
+}
+
+impl Telegram {
+    pub(crate) async fn send(&self, event: &Event) -> Result<(), crate::error::Error> {
+        let chats = if self.access.chats.is_empty() {
+            let Some(chat_id) = self.db.telegram_admin_chat().await? else {
+                tracing::warn!("No admin has messaged the telegram bot yet, can't notify");
+                return Ok(());
+            };
+
+            vec![chat_id]
+        } else {
+            self.access.chats.iter().copied().collect()
+        };
+
+        for chat_id in chats {
+            let mut request = self.bot.send_message(ChatId(chat_id), event.to_string());
+
+            if let Event::PendingFollow { id, .. } = event {
+                request = request.reply_markup(pending_keyboard(*id));
+            }
+
+            request.await?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -68,6 +280,10 @@ fn parse_host(input: &str) -> Result<String, ParseHostError> {
     }
 }
 
+fn parse_actor(input: &str) -> Result<IriString, crate::error::Error> {
+    Ok(input.trim().parse::<IriString>()?)
+}
+
 #[test]
 fn parse_host_parses_hosts() {
     let cases = [
@@ -90,59 +306,182 @@ fn parse_host_parses_hosts() {
     }
 }
 
-pub(crate) fn start(admin_handle: String, db: Db, token: &str) {
+#[test]
+fn admins_get_their_scopes() {
+    let admins = ["1234", "5678:stats+allows"]
+        .into_iter()
+        .map(|admin| admin.parse().expect("valid admin"))
+        .collect();
+    let access = Access::new(Some(String::from("handle")), admins, vec![]);
+
+    assert_eq!(
+        access.scopes(1234, None),
+        Some(BTreeSet::from([TokenScope::Admin]))
+    );
+    assert_eq!(
+        access.scopes(5678, Some("someone")),
+        Some(BTreeSet::from([TokenScope::Stats, TokenScope::Allows]))
+    );
+    assert_eq!(
+        access.scopes(9999, Some("handle")),
+        Some(BTreeSet::from([TokenScope::Admin]))
+    );
+    assert_eq!(access.scopes(9999, Some("someone")), None);
+    assert!("@handle".parse::<TelegramAdmin>().is_err());
+    assert!("1234:everything".parse::<TelegramAdmin>().is_err());
+}
+
+pub(crate) fn start(
+    access: Access,
+    state: State,
+    job_server: JobServer,
+    collector: MemoryCollector,
+    notifier: Notifier,
+    token: &str,
+) -> Telegram {
     let bot = Bot::new(token);
-    let admin_handle = Arc::new(admin_handle);
+    let access = Arc::new(access);
+
+    let telegram = Telegram {
+        bot: bot.clone(),
+        db: state.db.clone(),
+        access: access.clone(),
+    };
 
     tokio::spawn(async move {
-        let command_handler = teloxide::filter_command::<Command, _>().endpoint(
+        let command_handler = teloxide::filter_command::<Command, _>().endpoint({
+            let access = access.clone();
+            let state = state.clone();
+            let job_server = job_server.clone();
+            let notifier = notifier.clone();
+
             move |bot: Bot, msg: Message, cmd: Command| {
-                let admin_handle = admin_handle.clone();
-                let db = db.clone();
+                let access = access.clone();
+                let state = state.clone();
+                let job_server = job_server.clone();
+                let collector = collector.clone();
+                let notifier = notifier.clone();
 
                 async move {
-                    if !is_admin(&admin_handle, &msg) {
+                    if !access.allows_chat(msg.chat.id) {
+                        return Ok(());
+                    }
+
+                    if !access.is_admin(msg.from.as_ref()) {
                         bot.send_message(msg.chat.id, "You are not authorized")
                             .await?;
 
                         return Ok(());
                     }
 
-                    let chat_id = msg.chat.id;
+                    if let Some(scope) = cmd.scope() {
+                        if !access.permits(msg.from.as_ref(), scope) {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("You need the {} permission for that", scope.as_str()),
+                            )
+                            .await?;
 
-                    if let Err(e) = answer(&bot, msg, cmd, db).await {
-                        let root = root_cause(&e).to_string();
+                            return Ok(());
+                        }
+                    }
 
-                        bot.send_message(
-                            chat_id,
-                            format!("Internal server error: {e}, caused by: {root}"),
-                        )
-                        .await?;
+                    let chat_id = msg.chat.id;
+
+                    if let Err(e) = state.db.set_telegram_admin_chat(chat_id.0).await {
+                        tracing::warn!("Failed to save telegram admin chat: {e}");
+                    }
 
-                        Err(e)
+                    if let Err(e) =
+                        answer(&bot, msg, cmd, state, job_server, collector, notifier).await
+                    {
+                        report_error(&bot, chat_id, e).await
                     } else {
                         Ok(())
                     }
                 }
-            },
-        );
+            }
+        });
+
+        let callback_handler =
+            Update::filter_callback_query().endpoint(move |bot: Bot, query: CallbackQuery| {
+                let access = access.clone();
+                let db = state.db.clone();
+                let job_server = job_server.clone();
+                let notifier = notifier.clone();
+
+                async move {
+                    let chat_id = query.message.as_ref().map(|message| message.chat().id);
+
+                    if !chat_id.map(|id| access.allows_chat(id)).unwrap_or(false)
+                        || !access.permits(Some(&query.from), TokenScope::Allows)
+                    {
+                        bot.answer_callback_query(query.id)
+                            .text("You are not authorized")
+                            .await?;
+
+                        return Ok(());
+                    }
+
+                    match (
+                        answer_callback(&bot, query, db, job_server, notifier).await,
+                        chat_id,
+                    ) {
+                        (Err(e), Some(chat_id)) => report_error(&bot, chat_id, e).await,
+                        (Err(e), None) => Err(e),
+                        (Ok(()), _) => Ok(()),
+                    }
+                }
+            });
 
-        let message_handler = Update::filter_message().branch(command_handler);
+        let handler = dptree::entry()
+            .branch(Update::filter_message().branch(command_handler))
+            .branch(callback_handler);
 
-        Dispatcher::builder(bot, message_handler)
-            .build()
-            .dispatch()
-            .await;
+        Dispatcher::builder(bot, handler).build().dispatch().await;
     });
+
+    telegram
+}
+
+async fn report_error(bot: &Bot, chat_id: ChatId, e: AnswerError) -> Result<(), AnswerError> {
+    let root = root_cause(&e).to_string();
+
+    bot.send_message(
+        chat_id,
+        format!("Internal server error: {e}, caused by: {root}"),
+    )
+    .await?;
+
+    Err(e)
+}
+
+/// Who sent a command, as recorded on blocks and in the audit log
+fn sender(user: Option<&User>) -> String {
+    user.map(|user| match &user.username {
+        Some(username) => format!("telegram:@{username}"),
+        None => format!("telegram:{}", user.id),
+    })
+    .unwrap_or_else(|| String::from("telegram"))
 }
 
-fn is_admin(admin_handle: &str, message: &Message) -> bool {
-    message
-        .from
-        .as_ref()
-        .and_then(|user| user.username.as_deref())
-        .map(|username| username == admin_handle)
-        .unwrap_or(false)
+/// Record a command in the audit log before applying `change`, and tell the other
+/// notification backends about it once the change succeeded
+async fn audited<T>(
+    db: &Db,
+    notifier: &Notifier,
+    user: Option<&User>,
+    action: AuditAction,
+    target: impl ToString,
+    change: impl Future<Output = Result<T, crate::error::Error>>,
+) -> Result<T, crate::error::Error> {
+    let entry = AuditEntry::new(sender(user), action, vec![target.to_string()], None);
+
+    db.audit(entry.clone()).await?;
+    let output = change.await?;
+    notifier.notify(Event::AdminAction { entry });
+
+    Ok(output)
 }
 
 #[derive(Debug)]
@@ -196,8 +535,170 @@ fn root_cause<'a>(
     error
 }
 
-#[tracing::instrument(skip(bot, msg, db))]
-async fn answer(bot: &Bot, msg: Message, cmd: Command, db: Db) -> Result<(), AnswerError> {
+/// Join lines into as few messages as telegram allows
+fn paginate(lines: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut pages = vec![String::new()];
+
+    for line in lines {
+        let page = pages.last_mut().expect("pages is never empty");
+
+        if !page.is_empty() && page.len() + line.len() + 1 > MESSAGE_LIMIT {
+            pages.push(line);
+        } else {
+            if !page.is_empty() {
+                page.push('\n');
+            }
+            page.push_str(&line);
+        }
+    }
+
+    pages.retain(|page| !page.is_empty());
+    pages
+}
+
+fn stats_lines(report: Vec<Section>) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for (title, metrics) in report {
+        lines.push(title.to_owned());
+
+        for (key, values) in metrics {
+            lines.push(format!("  {key}"));
+            lines.extend(values.into_iter().map(|value| format!("    {value}")));
+        }
+    }
+
+    lines
+}
+
+fn node_lines(node: crate::data::Node) -> Vec<String> {
+    let mut lines = vec![node.base.to_string()];
+
+    if let Some(info) = node.info {
+        lines.push(format!("Software: {} {}", info.software, info.version));
+        lines.push(format!(
+            "Registrations: {}",
+            if info.reg { "open" } else { "closed" }
+        ));
+    }
+
+    if let Some(instance) = node.instance {
+        lines.push(format!("Title: {}", instance.title));
+        lines.push(format!("Description: {}", instance.description));
+    }
+
+    if let Some(contact) = node.contact {
+        lines.push(format!(
+            "Contact: {} (@{}) {}",
+            contact.display_name, contact.username, contact.url
+        ));
+    }
+
+    lines
+}
+
+async fn send_pending(
+    bot: &Bot,
+    chat_id: ChatId,
+    pending: PendingFollow,
+) -> Result<(), AnswerError> {
+    bot.send_message(
+        chat_id,
+        format!("{} - {}", pending.actor.id, pending.requested_at),
+    )
+    .reply_markup(pending_keyboard(pending.id))
+    .await?;
+
+    Ok(())
+}
+
+/// Whether `actor_id` has a follow request waiting for approval
+async fn is_pending(db: &Db, actor_id: &IriString) -> Result<bool, crate::error::Error> {
+    Ok(db
+        .pending_follows()
+        .await?
+        .iter()
+        .any(|pending| pending.actor.id == *actor_id))
+}
+
+/// Handle a press of a pending follow's Approve or Reject button
+#[tracing::instrument(skip(bot, query, db, job_server, notifier))]
+async fn answer_callback(
+    bot: &Bot,
+    query: CallbackQuery,
+    db: Db,
+    job_server: JobServer,
+    notifier: Notifier,
+) -> Result<(), AnswerError> {
+    let (action, id) = query
+        .data
+        .as_deref()
+        .and_then(|data| data.split_once(':'))
+        .unwrap_or_default();
+
+    let pending = match id.parse::<Uuid>() {
+        Ok(id) => db
+            .pending_follows()
+            .await?
+            .into_iter()
+            .find(|pending| pending.id == id),
+        Err(_) => None,
+    };
+
+    let text = match (action, pending) {
+        ("approve", Some(pending))
+            if audited(
+                &db,
+                &notifier,
+                Some(&query.from),
+                AuditAction::ApproveFollow,
+                &pending.actor.id,
+                apub::approve_follow(&db, &job_server, pending.actor.id.clone()),
+            )
+            .await? =>
+        {
+            format!("{} has been approved", pending.actor.id)
+        }
+        ("reject", Some(pending))
+            if audited(
+                &db,
+                &notifier,
+                Some(&query.from),
+                AuditAction::RejectFollow,
+                &pending.actor.id,
+                apub::reject_follow(&db, &job_server, pending.actor.id.clone()),
+            )
+            .await? =>
+        {
+            format!("{} has been rejected", pending.actor.id)
+        }
+        _ => String::from("This follow request is no longer pending"),
+    };
+
+    bot.answer_callback_query(query.id)
+        .text(text.clone())
+        .await?;
+
+    if let Some(message) = query.message {
+        bot.edit_message_text(message.chat().id, message.id(), text)
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(bot, msg, state, job_server, collector, notifier))]
+async fn answer(
+    bot: &Bot,
+    msg: Message,
+    cmd: Command,
+    state: State,
+    job_server: JobServer,
+    collector: MemoryCollector,
+    notifier: Notifier,
+) -> Result<(), AnswerError> {
+    let db = state.db.clone();
+
     match cmd {
         Command::Help | Command::Start => {
             bot.send_message(msg.chat.id, Command::descriptions().to_string())
@@ -206,7 +707,22 @@ async fn answer(bot: &Bot, msg: Message, cmd: Command, db: Db) -> Result<(), Ans
         Command::Block { domain } => {
             let domain = parse_host(&domain)?;
 
-            db.add_blocks(vec![domain.clone()]).await?;
+            let block = DomainBlock::new(
+                domain.clone(),
+                BlockSeverity::Suspend,
+                None,
+                Some(sender(msg.from.as_ref())),
+            );
+
+            audited(
+                &db,
+                &notifier,
+                msg.from.as_ref(),
+                AuditAction::Block,
+                &domain,
+                db.add_blocks(vec![block]),
+            )
+            .await?;
 
             bot.send_message(msg.chat.id, format!("{domain} has been blocked"))
                 .await?;
@@ -214,15 +730,63 @@ async fn answer(bot: &Bot, msg: Message, cmd: Command, db: Db) -> Result<(), Ans
         Command::Unblock { domain } => {
             let domain = parse_host(&domain)?;
 
-            db.remove_blocks(vec![domain.clone()]).await?;
+            audited(
+                &db,
+                &notifier,
+                msg.from.as_ref(),
+                AuditAction::Unblock,
+                &domain,
+                db.remove_blocks(vec![domain.clone()]),
+            )
+            .await?;
 
             bot.send_message(msg.chat.id, format!("{domain} has been unblocked"))
                 .await?;
         }
+        Command::BlockActor { actor } => {
+            let actor = parse_actor(&actor)?;
+
+            audited(
+                &db,
+                &notifier,
+                msg.from.as_ref(),
+                AuditAction::BlockActor,
+                &actor,
+                db.add_actor_blocks(vec![actor.clone()]),
+            )
+            .await?;
+
+            bot.send_message(msg.chat.id, format!("{actor} has been blocked"))
+                .await?;
+        }
+        Command::UnblockActor { actor } => {
+            let actor = parse_actor(&actor)?;
+
+            audited(
+                &db,
+                &notifier,
+                msg.from.as_ref(),
+                AuditAction::UnblockActor,
+                &actor,
+                db.remove_actor_blocks(vec![actor.clone()]),
+            )
+            .await?;
+
+            bot.send_message(msg.chat.id, format!("{actor} has been unblocked"))
+                .await?;
+        }
         Command::Allow { domain } => {
             let domain = parse_host(&domain)?;
 
-            db.add_allows(vec![domain.clone()]).await?;
+            audited(
+                &db,
+                &notifier,
+                msg.from.as_ref(),
+                AuditAction::Allow,
+                &domain,
+                db.add_allows(vec![domain.clone()]),
+            )
+            .await?;
 
             bot.send_message(msg.chat.id, format!("{domain} has been allowed"))
                 .await?;
@@ -230,7 +794,15 @@ async fn answer(bot: &Bot, msg: Message, cmd: Command, db: Db) -> Result<(), Ans
         Command::Disallow { domain } => {
             let domain = parse_host(&domain)?;
 
-            db.remove_allows(vec![domain.clone()]).await?;
+            audited(
+                &db,
+                &notifier,
+                msg.from.as_ref(),
+                AuditAction::Disallow,
+                &domain,
+                db.remove_allows(vec![domain.clone()]),
+            )
+            .await?;
 
             bot.send_message(msg.chat.id, format!("{domain} has been disallowed"))
                 .await?;
@@ -249,6 +821,13 @@ async fn answer(bot: &Bot, msg: Message, cmd: Command, db: Db) -> Result<(), Ans
                 bot.send_message(msg.chat.id, chunk.join("\n")).await?;
             }
         }
+        Command::ListBlockedActors => {
+            let blocked_actors = db.blocked_actors().await?;
+
+            for chunk in blocked_actors.chunks(50) {
+                bot.send_message(msg.chat.id, chunk.join("\n")).await?;
+            }
+        }
         Command::ListConnected => {
             let connected = db.connected_ids().await?;
 
@@ -256,6 +835,124 @@ async fn answer(bot: &Bot, msg: Message, cmd: Command, db: Db) -> Result<(), Ans
                 bot.send_message(msg.chat.id, chunk.join("\n")).await?;
             }
         }
+        Command::ListPending => {
+            let pending = db.pending_follows().await?;
+
+            if pending.is_empty() {
+                bot.send_message(msg.chat.id, "No pending follow requests")
+                    .await?;
+            }
+
+            for pending in pending {
+                send_pending(bot, msg.chat.id, pending).await?;
+            }
+        }
+        Command::Approve { actor } => {
+            let actor = parse_actor(&actor)?;
+
+            let text = if is_pending(&db, &actor).await? {
+                audited(
+                    &db,
+                    &notifier,
+                    msg.from.as_ref(),
+                    AuditAction::ApproveFollow,
+                    &actor,
+                    apub::approve_follow(&db, &job_server, actor.clone()),
+                )
+                .await?;
+                format!("{actor} has been approved")
+            } else {
+                format!("{actor} has no pending follow request")
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        Command::Reject { actor } => {
+            let actor = parse_actor(&actor)?;
+
+            let text = if is_pending(&db, &actor).await? {
+                audited(
+                    &db,
+                    &notifier,
+                    msg.from.as_ref(),
+                    AuditAction::RejectFollow,
+                    &actor,
+                    apub::reject_follow(&db, &job_server, actor.clone()),
+                )
+                .await?;
+                format!("{actor} has been rejected")
+            } else {
+                format!("{actor} has no pending follow request")
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        Command::Stats => {
+            for page in paginate(stats_lines(collector.snapshot().report())) {
+                bot.send_message(msg.chat.id, page).await?;
+            }
+        }
+        Command::LastSeen => {
+            let lines =
+                db.last_seen()
+                    .await?
+                    .into_iter()
+                    .map(|(domain, datetime)| match datetime {
+                        Some(datetime) => format!("{domain} - {datetime}"),
+                        None => format!("{domain} - never"),
+                    });
+
+            for page in paginate(lines) {
+                bot.send_message(msg.chat.id, page).await?;
+            }
+        }
+        Command::Breakers => {
+            let mut breakers = state
+                .breakers
+                .snapshot()
+                .into_iter()
+                .filter(|(_, breaker)| breaker.failures() > 0 || breaker.paused_until().is_some())
+                .collect::<Vec<_>>();
+            breakers.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            if breakers.is_empty() {
+                bot.send_message(msg.chat.id, "No domains are failing")
+                    .await?;
+            }
+
+            let lines = breakers.into_iter().map(|(domain, breaker)| {
+                let status = if breaker.paused_until().is_some() {
+                    "asked us to wait"
+                } else if breaker.should_try() {
+                    "retrying"
+                } else {
+                    "paused"
+                };
+
+                format!("{domain} - {} failures, {status}", breaker.failures())
+            });
+
+            for page in paginate(lines) {
+                bot.send_message(msg.chat.id, page).await?;
+            }
+        }
+        Command::Nodeinfo { domain } => {
+            let domain = parse_host(&domain)?;
+
+            let node = state
+                .node_cache
+                .nodes()
+                .await?
+                .into_iter()
+                .find(|node| node.base.authority_str() == Some(domain.as_str()));
+
+            let text = match node {
+                Some(node) => node_lines(node).join("\n"),
+                None => format!("{domain} isn't connected to the relay"),
+            };
+
+            bot.send_message(msg.chat.id, text).await?;
+        }
     }
 
     Ok(())